@@ -44,7 +44,8 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 
     {
         let path = Path::new("fixtures/tictactoe.qnt");
-        let parsed = helpers::parse_from_path(path, "init", "step", Some("inv"), None).unwrap();
+        let parsed =
+            helpers::parse_from_path(path, "init", "step", Some("inv"), None, None).unwrap();
         group.bench_function("tictactoe", |b| {
             b.iter(|| run(black_box(&parsed), 10).unwrap())
         });
@@ -52,9 +53,15 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 
     {
         let path = Path::new("fixtures/jmt/apply_state_machine.qnt");
-        let parsed =
-            helpers::parse_from_path(path, "init", "step_fancy", Some("allInvariants"), None)
-                .unwrap();
+        let parsed = helpers::parse_from_path(
+            path,
+            "init",
+            "step_fancy",
+            Some("allInvariants"),
+            None,
+            None,
+        )
+        .unwrap();
         group.bench_function("JMT", |b| b.iter(|| run(black_box(&parsed), 3).unwrap()));
     }
 
@@ -7,7 +7,7 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use quint_evaluator::helpers;
 
 fn run_in_rust(file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None)?;
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None, None)?;
 
     let result = parsed.simulate(10, 1_000, 0, None);
 
@@ -19,15 +19,18 @@
 //! while lazy ops will be compiled into closures that take colusures as
 //! arguments (which should be called to evaluate each argument).
 
+use crate::choices::for_each_choice;
 use crate::evaluator::{CompiledExprWithArgs, CompiledExprWithLazyArgs};
 use crate::ir::QuintError;
+use crate::temporal;
 use crate::value::{ImmutableMap, ImmutableSet, ImmutableVec, Value};
 use fxhash::FxHashSet;
 use itertools::Itertools;
+use std::ops::ControlFlow;
 use std::rc::Rc;
 
 /// A list of operators that need to be compiled lazily (with `compile_lazy_op`).
-pub const LAZY_OPS: [&str; 13] = [
+pub const LAZY_OPS: [&str; 20] = [
     "assign",
     "actionAny",
     "actionAll",
@@ -41,6 +44,13 @@ pub const LAZY_OPS: [&str; 13] = [
     "then",
     "reps",
     "expect",
+    "always",
+    "eventually",
+    "weakFair",
+    "strongFair",
+    "enabled",
+    "orKeep",
+    "mustChange",
 ];
 
 /// Compile an operator in a lazy way, where the arguments should only be
@@ -81,11 +91,25 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
             // Returns false if no enabled actions are found.
             let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
 
+            if env.is_exhaustive() {
+                // When enumerating choices, the branch itself is the choice, so
+                // each branch is tried exactly once across all sequences.
+                if args.is_empty() {
+                    return Ok(Value::Bool(false));
+                }
+                let i = env.next_choice(args.len());
+                let result = args[i].execute(env)?;
+                if !result.as_bool() {
+                    env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+                }
+                return Ok(result);
+            }
+
             // Create array of indices and shuffle them
             let mut indices: Vec<usize> = (0..args.len()).collect();
             // Fisher-Yates shuffle algorithm using our randomizer
             for i in (0..indices.len()).rev() {
-                let j: usize = env.rand.next(i + 1);
+                let j: usize = env.next_choice(i + 1);
                 indices.swap(i, j);
             }
 
@@ -94,8 +118,8 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 let result = args[i].execute(env)?;
 
                 if result.as_bool() {
-                    // Found an enabled action - record it and return true
-                    // TODO: Record in the trace recorder
+                    // Found an enabled action. If it is a named action, it
+                    // already recorded itself as the action taken.
                     return Ok(Value::Bool(true));
                 }
 
@@ -163,25 +187,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
             // Some sets require multiple random numbers in order to pick an element efficiently.
             // For example, a cross product will require one random number per set, and return a tuple like
             // (set1.pick(r1), set2.pick(r2), ..., setn.pick(rn))
-
-            // The ranges in which to generate which random number
-            let bounds = set.bounds();
-            // The generated random number for each bound
-            let mut positions = Vec::with_capacity(bounds.len());
-
-            for bound in bounds {
-                if bound == 0 {
-                    return Err(QuintError::new("QNT509", "Applied oneOf on an empty set"));
-                }
-
-                // TODO: The old simulator generates a limited bound for infinite sets
-                // Not sure if we want to keep this behavior
-                // Related: https://github.com/informalsystems/quint/issues/279
-
-                positions.push(env.rand.next(bound))
-            }
-
-            Ok(set.pick(&mut positions.into_iter()))
+            set.pick_with(env)
         },
         "then" => |env, args| {
             // Compose two actions, executing the second one only if the first one results in true.
@@ -194,7 +200,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
             }
 
             env.shift();
-            // TODO: record state on recorder
+            env.collect_state(false);
             args[1].execute(env)
         },
         "reps" => {
@@ -216,6 +222,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                     // Don't shift the last one
                     if i < reps - 1 {
                         env.shift();
+                        env.collect_state(false);
                     }
                 }
                 Ok(result)
@@ -241,6 +248,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
 
                 let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
                 env.shift();
+                env.collect_state(true);
                 let predicate_result = predicate.execute(env)?;
                 env.var_storage.borrow_mut().restore(&next_vars_snapshot);
 
@@ -254,14 +262,131 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 Ok(Value::Bool(true))
             }
         }
+        "enabled" => |env, args| {
+            // Whether the action can be taken, without taking it. Every possible
+            // choice of nondeterministic values is tried until one enables it.
+            let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
+            let mut enabled = false;
+            let result = for_each_choice(env, |env| {
+                env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+                if args[0].execute(env)?.as_bool() {
+                    enabled = true;
+                    return Ok(ControlFlow::Break(()));
+                }
+                Ok(ControlFlow::Continue(()))
+            });
+            env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+            result.map(|()| Value::Bool(enabled))
+        },
+        "orKeep" => |env, args| {
+            // A.orKeep(vars), or [A]_vars in TLA+: take the action, or keep the
+            // variables unchanged if it is disabled. When enumerating choices,
+            // keeping the variables is also a choice, even if A is enabled.
+            let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
+            if !env.is_exhaustive() || env.next_choice(2) == 0 {
+                if args[0].execute(env)?.as_bool() {
+                    return Ok(Value::Bool(true));
+                }
+                if env.is_exhaustive() {
+                    return Ok(Value::Bool(false));
+                }
+            }
+
+            // Only the variables in `vars` are kept, which the interpreter
+            // compiles `vars` to do (see `Interpreter::compile_keep`)
+            env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+            args[1].execute(env)
+        },
+        "mustChange" => |env, args| {
+            // A.mustChange(vars), or <A>_vars in TLA+: take the action, as long as
+            // it changes the value of `vars`.
+            let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
+            if !args[0].execute(env)?.as_bool() {
+                env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+                return Ok(Value::Bool(false));
+            }
+
+            // Evaluate `vars` in the next state, and go back to the current one
+            let before = args[1].execute(env)?;
+            let current = env.var_storage.borrow().vars_record();
+            let next = env.var_storage.borrow().next_state_record();
+            let assigned = env.var_storage.borrow().take_snapshot();
+            env.var_storage.borrow_mut().set_vars_from_record(&next);
+            let after = args[1].execute(env);
+            let mut storage = env.var_storage.borrow_mut();
+            storage.set_vars_from_record(&current);
+            storage.restore(&assigned);
+
+            if after? == before {
+                storage.restore(&next_vars_snapshot);
+                return Ok(Value::Bool(false));
+            }
+            Ok(Value::Bool(true))
+        },
+        // Temporal operators, evaluated over the trace being checked (see the
+        // `temporal` module)
+        "always" => |env, args| temporal::always(env, &args[0]),
+        "eventually" => |env, args| temporal::eventually(env, &args[0]),
+        "weakFair" => |env, args| temporal::fair(env, &args[0], &args[1], false),
+        "strongFair" => |env, args| temporal::fair(env, &args[0], &args[1], true),
         _ => {
             panic!("Unknown lazy op: {op}")
         }
     })
 }
 
-/// Compile an operator in an eager way, where the arguments are evaluated beforehand
+/// A list of (eager) operators that need to enumerate the sets given as
+/// arguments, and therefore fail on infinite sets.
+const ENUMERATING_OPS: [&str; 15] = [
+    "allListsUpTo",
+    "chooseSome",
+    "exclude",
+    "exists",
+    "filter",
+    "flatten",
+    "fold",
+    "forall",
+    "getOnlyElement",
+    "intersect",
+    "map",
+    "mapBy",
+    "setToMap",
+    "size",
+    "union",
+];
+
+/// Compile an operator in an eager way, where the arguments are evaluated
+/// beforehand. Operators that enumerate sets check that their arguments are
+/// finite first.
 pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
+    let compiled = compile_finite_eager_op(op);
+    if !ENUMERATING_OPS.contains(&op) {
+        return compiled;
+    }
+
+    CompiledExprWithArgs::new(move |env, args| {
+        if let Some(set) = args.iter().find(|arg| arg.is_infinite()) {
+            return Err(QuintError::new(
+                "QNT501",
+                &format!("Cannot enumerate the infinite set {set}"),
+            ));
+        }
+        compiled.execute(env, args)
+    })
+}
+
+/// Whether an operator is built into the interpreter, as opposed to defined in
+/// a spec.
+pub fn is_builtin_op(op: &str) -> bool {
+    LAZY_OPS.contains(&op) || finite_eager_op(op).is_some()
+}
+
+/// Compile an eager operator, assuming that sets can be enumerated.
+fn compile_finite_eager_op(op: &str) -> CompiledExprWithArgs {
+    finite_eager_op(op).unwrap_or_else(|| panic!("Unknown eager op: {op}"))
+}
+
+fn finite_eager_op(op: &str) -> Option<CompiledExprWithArgs> {
     // To be used at `item` and `nth` which share the same behavior
     fn at_index(list: &ImmutableVec<Value>, index: i64) -> Result<Value, QuintError> {
         if index < 0 || index >= list.len().try_into().unwrap() {
@@ -271,7 +396,7 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
         Ok(list[index as usize].clone())
     }
 
-    CompiledExprWithArgs::from_fn(match op {
+    Some(CompiledExprWithArgs::from_fn(match op {
         // Constructs a set from the given arguments.
         "Set" => |_env, args| Ok(Value::Set(args.into_iter().collect())),
         "Rec" => |_env, args| {
@@ -500,10 +625,7 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
         "size" => |_env, args| Ok(Value::Int(args[0].cardinality().try_into().unwrap())),
 
         // Whether a set is finite.
-        "isFinite" => |_env, _args| {
-            // at the moment, we support only finite sets, so just return true
-            Ok(Value::Bool(true))
-        },
+        "isFinite" => |_env, args| Ok(Value::Bool(!args[0].is_infinite())),
         // Construct a set of integers from a to b.
         "to" => |_env, args| {
             let start = args[0].as_int();
@@ -547,12 +669,15 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Flatten a set of sets.
         "flatten" => |_env, args| {
+            let sets = args[0].as_set();
+            if let Some(set) = sets.iter().find(|set| set.is_infinite()) {
+                return Err(QuintError::new(
+                    "QNT501",
+                    &format!("Cannot enumerate the infinite set {set}"),
+                ));
+            }
             Ok(Value::Set(
-                args[0]
-                    .as_set()
-                    .iter()
-                    .flat_map(|v| v.as_set().into_owned())
-                    .collect(),
+                sets.iter().flat_map(|v| v.as_set().into_owned()).collect(),
             ))
         },
 
@@ -761,27 +886,24 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
             Ok(args[1].clone())
         },
 
-        // `allLists` is not supported in the REPL, but we have `allListsUpTo`
-        "allLists" => |_env, _args| {
-            Err(QuintError::new(
-                "QNT501",
-                "Runtime does not support allLists. Use `allListsUpTo` instead",
-            ))
-        },
+        // The (infinite) set of all lists with elements from the given set
+        "allLists" => |_env, args| Ok(Value::AllLists(Rc::new(args[0].clone()))),
 
-        // These are not supported in the REPL
-        "chooseSome" | "always" | "eventually" | "enabled" | "orKeep" | "mustChange"
-        | "weakFair" | "strongFair" => |_env, _args| {
-            Err(QuintError::new(
-                "QNT501",
-                "Runtime does not support this built-in operator",
-            ))
+        // Deterministically choose an element of the set, which is always the
+        // same for the same set
+        "chooseSome" => |_env, args| {
+            let set = &args[0];
+            if set.bounds().contains(&0) {
+                return Err(QuintError::new(
+                    "QNT509",
+                    "Applied chooseSome on an empty set",
+                ));
+            }
+            Ok(set.pick(&mut std::iter::repeat(0)))
         },
 
-        _ => {
-            panic!("Unknown eager op: {op}")
-        }
-    })
+        _ => return None,
+    }))
 }
 
 /// Utility to fold iterables from left to right. This exists mostly to keep a
@@ -0,0 +1,119 @@
+//! Sequences of nondeterministic choices.
+//!
+//! Every nondeterministic choice made during evaluation (an index given to
+//! [`Value::pick`](crate::value::Value::pick) in `oneOf`, or a branch in
+//! `actionAny`) is a number within a bound. During simulation, these numbers
+//! come from [`Rand`](crate::rand::Rand). A [`ChoiceSequence`] provides them
+//! from a fixed sequence instead, so that an evaluation can be replayed, or so
+//! that we can systematically go through all sequences and enumerate every
+//! possible outcome of an action, as the model checker does.
+
+use crate::evaluator::Env;
+use crate::ir::QuintError;
+use std::ops::ControlFlow;
+
+/// A choice that was made during evaluation: `value` is in `0..bound`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Choice {
+    pub value: usize,
+    pub bound: usize,
+}
+
+/// A sequence of choices to be used instead of random numbers.
+///
+/// Choices are taken from `prefix` while it lasts, and are `0` after that. All
+/// the choices made are recorded, so we know which bounds were used and can
+/// move on to the next sequence with [`ChoiceSequence::advance`].
+#[derive(Debug, Clone)]
+pub struct ChoiceSequence {
+    prefix: Vec<usize>,
+    taken: Vec<Choice>,
+    exhaustive: bool,
+}
+
+impl ChoiceSequence {
+    /// A sequence meant to be advanced until all choices are exhausted. In
+    /// this mode, `actionAny` picks a single branch instead of shuffling them,
+    /// so each branch is explored exactly once.
+    pub fn exhaustive() -> Self {
+        Self {
+            prefix: Vec::new(),
+            taken: Vec::new(),
+            exhaustive: true,
+        }
+    }
+
+    /// A sequence that replays the given choices, as a random evaluation would
+    /// have made them.
+    pub fn replay(prefix: Vec<usize>) -> Self {
+        Self {
+            prefix,
+            taken: Vec::new(),
+            exhaustive: false,
+        }
+    }
+
+    /// Whether this sequence is used to exhaustively enumerate choices.
+    pub fn is_exhaustive(&self) -> bool {
+        self.exhaustive
+    }
+
+    /// Make the next choice within the given bound. Replayed values that are
+    /// no longer within the bound (because the evaluation diverged from the
+    /// original one) are clamped to it.
+    pub fn next(&mut self, bound: usize) -> usize {
+        let value = self
+            .prefix
+            .get(self.taken.len())
+            .map_or(0, |value| (*value).min(bound.saturating_sub(1)));
+        self.taken.push(Choice { value, bound });
+        value
+    }
+
+    /// The choices made so far.
+    pub fn taken(&self) -> &[Choice] {
+        &self.taken
+    }
+
+    /// Move on to the next sequence, in lexicographic order, based on the
+    /// choices made in the current one. Returns false if there are no more
+    /// sequences to explore.
+    pub fn advance(&mut self) -> bool {
+        while let Some(choice) = self.taken.pop() {
+            if choice.value + 1 < choice.bound {
+                self.prefix = self.taken.iter().map(|c| c.value).collect();
+                self.prefix.push(choice.value + 1);
+                self.taken.clear();
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Call `f` once for each possible sequence of choices, until it breaks or
+/// returns an error. `f` is expected to evaluate something in the given
+/// environment, which will make its choices from the sequence being explored.
+///
+/// The environment's previous source of choices is restored at the end.
+pub fn for_each_choice(
+    env: &mut Env,
+    mut f: impl FnMut(&mut Env) -> Result<ControlFlow<()>, QuintError>,
+) -> Result<(), QuintError> {
+    let previous = env.choices.replace(ChoiceSequence::exhaustive());
+
+    let result = loop {
+        match f(env) {
+            Err(err) => break Err(err),
+            Ok(ControlFlow::Break(())) => break Ok(()),
+            Ok(ControlFlow::Continue(())) => {}
+        }
+
+        if !env.choices.as_mut().is_some_and(|c| c.advance()) {
+            break Ok(());
+        }
+    };
+
+    env.choices = previous;
+    result
+}
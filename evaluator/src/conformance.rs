@@ -0,0 +1,197 @@
+//! Conformance checking of traces against a specification.
+//!
+//! A trace produced elsewhere (e.g. from the logs of an implementation) is
+//! checked state by state: the first state must be reachable through `init`,
+//! and each following state must be reachable from the previous one through
+//! `step`. The variables are set from each state, and the transition is
+//! evaluated until it produces the next one.
+//!
+//! When a state has the metadata recorded for model-based testing (see
+//! [`Storage::as_record`](crate::storage::Storage::as_record)), its nondet
+//! picks are replayed instead of being picked, and the action taken must
+//! match. If that does not produce the state (or there is no metadata), every
+//! possible choice of nondeterministic values is tried (see
+//! [`for_each_choice`]). Since that enumerates the sets given to `oneOf`,
+//! checking traces without metadata can be slow for large sets.
+
+use crate::{
+    choices::for_each_choice,
+    evaluator::{CompiledExpr, Env, Interpreter},
+    ir::{QuintError, QuintName},
+    itf::Trace,
+    simulator::ParsedQuint,
+    types::var_types,
+    value::{Value, ValueDiff},
+};
+use fxhash::FxHashMap;
+use std::ops::ControlFlow;
+
+/// The outcome of checking a trace.
+pub struct ConformanceResult {
+    /// How many states were found to conform, in order
+    pub conforming_states: usize,
+    /// The first state that does not conform, if any
+    pub failure: Option<NonConformance>,
+}
+
+impl ConformanceResult {
+    pub fn conforms(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// A state that cannot be produced from the previous one.
+pub struct NonConformance {
+    /// The index of the state in the trace (0 for the initial state)
+    pub index: usize,
+    /// The action recorded for the transition to this state, if any
+    pub action: Option<String>,
+    /// The produced state with the fewest differences from the expected one,
+    /// or `None` if the transition is disabled for all choices
+    pub closest: Option<Value>,
+    /// How the variables of the closest state differ from the expected ones,
+    /// from the expected state to the produced one. Paths start at the
+    /// variable (a record field).
+    pub diff: ValueDiff,
+}
+
+impl ParsedQuint {
+    /// Check that an ITF trace can be produced by this specification (see the
+    /// module documentation).
+    pub fn check_conformance(
+        &self,
+        trace: &itf::Trace<itf::Value>,
+    ) -> Result<ConformanceResult, QuintError> {
+        let trace = Trace::from_itf(trace, &var_types(&self.table))?;
+        self.check_conformance_of_states(&trace.states)
+    }
+
+    /// Check that a sequence of states, as records, can be produced by this
+    /// specification (see the module documentation).
+    pub fn check_conformance_of_states(
+        &self,
+        states: &[Value],
+    ) -> Result<ConformanceResult, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        interpreter.var_storage.borrow_mut().store_metadata = true;
+        let mut env = Env::new(interpreter.var_storage.clone());
+
+        let init = interpreter.compile(&self.init);
+        let step = interpreter.compile(&self.step);
+
+        let mut previous = Value::Record(Default::default());
+        for (index, state) in states.iter().enumerate() {
+            let transition = if index == 0 { &init } else { &step };
+            if let Err(failure) = check_transition(&mut env, transition, &previous, state, index)? {
+                return Ok(ConformanceResult {
+                    conforming_states: index,
+                    failure: Some(failure),
+                });
+            }
+            previous = state.clone();
+        }
+
+        Ok(ConformanceResult {
+            conforming_states: states.len(),
+            failure: None,
+        })
+    }
+}
+
+/// Check whether `transition` can produce `expected` from `previous`. The
+/// recorded nondet picks are tried first, then every possible choice.
+fn check_transition(
+    env: &mut Env,
+    transition: &CompiledExpr,
+    previous: &Value,
+    expected: &Value,
+    index: usize,
+) -> Result<Result<(), NonConformance>, QuintError> {
+    let fields = expected.as_record_map();
+    let action = fields
+        .get("mbt::actionTaken")
+        .map(|action| action.as_str().to_string())
+        .filter(|action| !action.is_empty());
+    let picks = fields.get("mbt::nondetPicks").map(recorded_picks);
+    let expected_vars = without_metadata(expected);
+
+    let mut closest: Option<(Value, ValueDiff)> = None;
+    let mut found = false;
+    for replayed_picks in [picks, None] {
+        let replaying = replayed_picks.is_some();
+        env.replayed_picks = replayed_picks;
+        for_each_choice(env, |env| {
+            env.var_storage.borrow_mut().set_vars_from_record(previous);
+
+            if !transition.execute(env)?.as_bool() {
+                return Ok(ControlFlow::Continue(()));
+            }
+
+            let storage = env.var_storage.borrow();
+            let produced = storage.next_state_record();
+            let diff = expected_vars.diff(&without_metadata(&produced));
+            let same_action = !replaying
+                || action.as_ref().is_none_or(|action| {
+                    storage
+                        .next_action_taken
+                        .as_ref()
+                        .is_some_and(|taken| taken.as_str() == action)
+                });
+            if diff.is_empty() && same_action {
+                found = true;
+                return Ok(ControlFlow::Break(()));
+            }
+            if closest
+                .as_ref()
+                .is_none_or(|(_, d)| diff.differences.len() < d.differences.len())
+            {
+                closest = Some((produced, diff));
+            }
+            Ok(ControlFlow::Continue(()))
+        })?;
+        env.replayed_picks = None;
+
+        if found {
+            return Ok(Ok(()));
+        }
+    }
+
+    let (closest, diff) = match closest {
+        Some((state, diff)) => (Some(state), diff),
+        None => (None, ValueDiff::default()),
+    };
+    Ok(Err(NonConformance {
+        index,
+        action,
+        closest,
+        diff,
+    }))
+}
+
+/// The nondet picks in the `mbt::nondetPicks` record of a state, where each
+/// binding is a `Some` or `None` variant. Bindings that were not evaluated
+/// (`None`) are left out, so they are picked as usual.
+fn recorded_picks(picks: &Value) -> FxHashMap<QuintName, Value> {
+    picks
+        .as_record_map()
+        .iter()
+        .filter_map(|(name, pick)| match pick {
+            Value::Variant(label, value) if label == "Some" => {
+                Some((name.clone(), value.as_ref().clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The variables of a state, without the metadata for model-based testing.
+fn without_metadata(state: &Value) -> Value {
+    Value::Record(
+        state
+            .as_record_map()
+            .iter()
+            .filter(|(name, _)| !name.starts_with("mbt::"))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    )
+}
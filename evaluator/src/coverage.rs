@@ -0,0 +1,129 @@
+//! Coverage of a specification during evaluation.
+//!
+//! When coverage is enabled in the [`Interpreter`](crate::evaluator::Interpreter),
+//! compiled expressions are instrumented with hit counters, keyed by the
+//! [`QuintId`] of the instrumented expression or definition:
+//!  - each branch of `actionAny` and `actionAll` counts the times it was
+//!    enabled (that is, it evaluated to true);
+//!  - each arm of `ite` and each case of `matchVariant` counts the times it
+//!    was evaluated;
+//!  - each top-level definition counts the times it was evaluated (or, for
+//!    operators with parameters, applied).
+//!
+//! Definitions are registered upfront from the lookup table, so the ones that
+//! are referenced but never evaluated show up in the report. Branches are
+//! registered as they are compiled.
+
+use crate::ir::QuintId;
+use fxhash::FxHashMap;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// What is being counted in a coverage point.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageKind {
+    /// A branch of `actionAny` or `actionAll`, hit when enabled
+    Action,
+    /// An arm of `ite` or a case of `matchVariant`, hit when evaluated
+    Branch,
+    /// A top-level definition, hit when evaluated
+    Definition,
+}
+
+/// A counter shared between the compiled expression and the [`Coverage`].
+pub type HitCounter = Rc<Cell<usize>>;
+
+struct Point {
+    kind: CoverageKind,
+    label: String,
+    hits: HitCounter,
+}
+
+/// The hit counters registered during compilation. Clones share the counters.
+#[derive(Clone, Default)]
+pub struct Coverage {
+    points: Rc<RefCell<FxHashMap<QuintId, Point>>>,
+}
+
+impl Coverage {
+    /// The counter for `id`, registering it if it is not there yet.
+    pub fn counter(&self, id: QuintId, kind: CoverageKind, label: &str) -> HitCounter {
+        self.points
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| Point {
+                kind,
+                label: label.to_string(),
+                hits: HitCounter::default(),
+            })
+            .hits
+            .clone()
+    }
+
+    /// A snapshot of the current counts.
+    pub fn report(&self) -> CoverageReport {
+        let mut points = self
+            .points
+            .borrow()
+            .iter()
+            .map(|(id, point)| CoveragePoint {
+                id: *id,
+                kind: point.kind,
+                label: point.label.clone(),
+                hits: point.hits.get(),
+            })
+            .collect::<Vec<_>>();
+        points.sort_by_key(|point| point.id);
+
+        CoverageReport::new(points)
+    }
+}
+
+/// A coverage point in a [`CoverageReport`].
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoveragePoint {
+    pub id: QuintId,
+    pub kind: CoverageKind,
+    /// The name of the action or definition, or a description of the branch
+    pub label: String,
+    pub hits: usize,
+}
+
+/// The counts for every coverage point, ordered by id, along with the ones
+/// that were never hit.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageReport {
+    pub points: Vec<CoveragePoint>,
+    pub actions_never_enabled: Vec<CoveragePoint>,
+    pub definitions_never_run: Vec<CoveragePoint>,
+    pub branches_never_taken: Vec<CoveragePoint>,
+}
+
+impl CoverageReport {
+    fn new(points: Vec<CoveragePoint>) -> Self {
+        let missed = |kind| {
+            points
+                .iter()
+                .filter(|p| p.kind == kind && p.hits == 0)
+                .cloned()
+                .collect()
+        };
+
+        Self {
+            actions_never_enabled: missed(CoverageKind::Action),
+            definitions_never_run: missed(CoverageKind::Definition),
+            branches_never_taken: missed(CoverageKind::Branch),
+            points,
+        }
+    }
+
+    /// How many points of the given kind were hit, out of how many.
+    pub fn ratio(&self, kind: CoverageKind) -> (usize, usize) {
+        let points = self.points.iter().filter(|p| p.kind == kind);
+        let hit = points.clone().filter(|p| p.hits > 0).count();
+        (hit, points.count())
+    }
+}
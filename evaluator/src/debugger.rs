@@ -0,0 +1,503 @@
+//! Step-through debugging of evaluation.
+//!
+//! When debugging is enabled in the [`Interpreter`], every compiled expression
+//! is wrapped with a hook that runs before it is evaluated. The hook pauses
+//! evaluation when a breakpoint is hit or a step is done, and hands control to
+//! a [`DebugFrontend`], which can inspect the state variables (current and
+//! next) and the lambda parameters in scope, and decides how to go on.
+//!
+//! Breakpoints are set on top-level definitions, pausing right before their
+//! body is evaluated, or on the ids of expressions. Steps follow the nesting of
+//! expressions: stepping in pauses at the very next expression, stepping over
+//! skips the sub-expressions of the current one, and stepping out skips the
+//! rest of the expression enclosing the current one.
+//!
+//! [`CommandLineFrontend`] is a simple frontend reading commands from a
+//! terminal (or any other input).
+
+use crate::evaluator::{CompiledExpr, Env, EvalResult, Interpreter};
+use crate::ir::{QuintError, QuintEx, QuintId, QuintName};
+use crate::render::QuintSyntax;
+use crate::simulator::ParsedQuint;
+use crate::storage::VariableRegister;
+use crate::value::{ImmutableMap, Value};
+use itertools::Itertools;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Where to pause evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before evaluating the body of a top-level definition
+    Definition(QuintName),
+    /// Before evaluating the expression with this id
+    Id(QuintId),
+}
+
+impl Breakpoint {
+    fn matches(&self, location: &Location) -> bool {
+        match self {
+            Breakpoint::Definition(name) => location.definition.as_ref() == Some(name),
+            Breakpoint::Id(id) => location.id == *id,
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// Numbers are read as expression ids, and anything else as definition
+    /// names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(id) => Ok(Breakpoint::Id(id)),
+            Err(_) if s.is_empty() => Err("Missing definition name or id".to_string()),
+            Err(_) => Ok(Breakpoint::Definition(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Definition(name) => write!(f, "{name}"),
+            Breakpoint::Id(id) => write!(f, "#{id}"),
+        }
+    }
+}
+
+/// How to go on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// Run until the next breakpoint
+    Continue,
+    /// Pause at the next expression
+    StepIn,
+    /// Pause at the next expression that is not part of the current one
+    StepOver,
+    /// Pause at the next expression that is not part of the one enclosing the
+    /// current one
+    StepOut,
+    /// Stop evaluation with an error
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    Breakpoint,
+    Step,
+}
+
+/// Everything a frontend can see when evaluation is paused, right before an
+/// expression is evaluated.
+pub struct Pause<'a> {
+    pub reason: PauseReason,
+    /// The id of the expression about to be evaluated
+    pub id: QuintId,
+    /// A short description of the expression (e.g. `iadd(..)`)
+    pub label: &'a str,
+    /// The top-level definitions being evaluated, outermost first
+    pub stack: &'a [QuintName],
+    /// How many expressions enclose this one, including itself
+    pub depth: usize,
+    /// The index of this expression among the branches of an `actionAny`
+    /// being tried, and the number of branches, if it is one
+    pub branch: Option<(usize, usize)>,
+    /// The state variables in the current state, sorted by name
+    pub vars: Vec<(QuintName, Option<Value>)>,
+    /// The state variables in the next state, sorted by name
+    pub next_vars: Vec<(QuintName, Option<Value>)>,
+    /// The lambda parameters in scope that have a value
+    pub params: Vec<(QuintName, Value)>,
+    /// The result of the last expression that was evaluated, if any
+    pub last_result: Option<&'a EvalResult>,
+}
+
+/// Decides what to do when evaluation is paused. Breakpoints can be changed
+/// while paused.
+pub trait DebugFrontend {
+    fn paused(&mut self, pause: &Pause, breakpoints: &mut Vec<Breakpoint>) -> DebugCommand;
+}
+
+/// An expression wrapped by the debugger.
+pub(crate) struct Location {
+    pub id: QuintId,
+    pub label: String,
+    /// The top-level definition this expression is the body of, if any
+    pub definition: Option<QuintName>,
+    /// The index of this expression among the branches of an `actionAny`, and
+    /// the number of branches, if it is one
+    pub branch: Option<(usize, usize)>,
+}
+
+impl Location {
+    pub fn new(
+        expr: &QuintEx,
+        definition: Option<QuintName>,
+        branch: Option<(usize, usize)>,
+    ) -> Self {
+        Self {
+            id: expr.id(),
+            label: describe(expr),
+            definition,
+            branch,
+        }
+    }
+}
+
+/// The lambda parameters in scope of an expression, with the registers
+/// holding their values.
+pub(crate) type ParamsInScope = Vec<(QuintName, Rc<RefCell<EvalResult>>)>;
+
+enum Mode {
+    Run,
+    StepIn,
+    StepOver(usize),
+    StepOut(usize),
+}
+
+struct DebugState {
+    frontend: Box<dyn DebugFrontend>,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+    depth: usize,
+    stack: Vec<QuintName>,
+    last_result: Option<EvalResult>,
+    aborted: bool,
+}
+
+/// The hooks called around the evaluation of each expression. Cloning gives a
+/// handle to the same debugger.
+#[derive(Clone)]
+pub struct Debugger(Rc<RefCell<DebugState>>);
+
+impl Debugger {
+    /// A debugger pausing at the given breakpoints, or at the very first
+    /// expression if there are none.
+    pub fn new(frontend: impl DebugFrontend + 'static, breakpoints: Vec<Breakpoint>) -> Self {
+        let mode = if breakpoints.is_empty() {
+            Mode::StepIn
+        } else {
+            Mode::Run
+        };
+        Self(Rc::new(RefCell::new(DebugState {
+            frontend: Box::new(frontend),
+            breakpoints,
+            mode,
+            depth: 0,
+            stack: Vec::new(),
+            last_result: None,
+            aborted: false,
+        })))
+    }
+
+    /// Whether to pause at the very first expression, regardless of the
+    /// breakpoints.
+    pub fn stop_on_entry(self, stop: bool) -> Self {
+        self.0.borrow_mut().mode = if stop { Mode::StepIn } else { Mode::Run };
+        self
+    }
+
+    /// Wrap a compiled expression with the debugging hooks.
+    pub(crate) fn wrap(
+        &self,
+        location: Location,
+        params: ParamsInScope,
+        expr: CompiledExpr,
+    ) -> CompiledExpr {
+        let debugger = self.clone();
+        CompiledExpr::new(move |env| {
+            debugger.enter(&location, &params, env)?;
+            let result = expr.execute(env);
+            debugger.exit(&location, &result);
+            result
+        })
+    }
+
+    fn enter(
+        &self,
+        location: &Location,
+        params: &ParamsInScope,
+        env: &Env,
+    ) -> Result<(), QuintError> {
+        let mut state = self.0.borrow_mut();
+        if state.aborted {
+            return Err(aborted());
+        }
+
+        state.depth += 1;
+        if let Some(name) = &location.definition {
+            state.stack.push(name.clone());
+        }
+
+        let depth = state.depth;
+        let reason = if state.breakpoints.iter().any(|b| b.matches(location)) {
+            PauseReason::Breakpoint
+        } else {
+            match state.mode {
+                Mode::StepIn => PauseReason::Step,
+                Mode::StepOver(d) if depth <= d => PauseReason::Step,
+                // Anything outside of the expression enclosing the paused one
+                Mode::StepOut(d) if depth < d => PauseReason::Step,
+                _ => return Ok(()),
+            }
+        };
+
+        let (vars, next_vars) = {
+            let storage = env.var_storage.borrow();
+            (
+                read_registers(&storage.vars),
+                read_registers(&storage.next_vars),
+            )
+        };
+        let params = params
+            .iter()
+            .filter_map(|(name, register)| match &*register.borrow() {
+                Ok(value) => Some((name.clone(), value.clone())),
+                Err(_) => None,
+            })
+            .collect();
+
+        let DebugState {
+            frontend,
+            breakpoints,
+            stack,
+            last_result,
+            ..
+        } = &mut *state;
+        let pause = Pause {
+            reason,
+            id: location.id,
+            label: &location.label,
+            stack,
+            depth,
+            branch: location.branch,
+            vars,
+            next_vars,
+            params,
+            last_result: last_result.as_ref(),
+        };
+        let command = frontend.paused(&pause, breakpoints);
+
+        state.mode = match command {
+            DebugCommand::Continue => Mode::Run,
+            DebugCommand::StepIn => Mode::StepIn,
+            DebugCommand::StepOver => Mode::StepOver(depth),
+            DebugCommand::StepOut => Mode::StepOut(depth),
+            DebugCommand::Abort => {
+                state.aborted = true;
+                return Err(aborted());
+            }
+        };
+        Ok(())
+    }
+
+    fn exit(&self, location: &Location, result: &EvalResult) {
+        let mut state = self.0.borrow_mut();
+        state.depth -= 1;
+        if location.definition.is_some() {
+            state.stack.pop();
+        }
+        state.last_result = Some(result.clone());
+    }
+}
+
+/// The names and values of variable registers, sorted by name.
+fn read_registers(
+    registers: &ImmutableMap<QuintName, Rc<RefCell<VariableRegister>>>,
+) -> Vec<(QuintName, Option<Value>)> {
+    registers
+        .values()
+        .map(|register| {
+            let register = register.borrow();
+            (register.name.clone(), register.value.clone())
+        })
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+        .collect()
+}
+
+fn aborted() -> QuintError {
+    QuintError::new("QNT521", "Evaluation aborted by the debugger")
+}
+
+/// A short description of an expression, without its sub-expressions.
+fn describe(expr: &QuintEx) -> String {
+    match expr {
+        QuintEx::QuintName { name, .. } => name.to_string(),
+        QuintEx::QuintBool { value, .. } => value.to_string(),
+        QuintEx::QuintInt { value, .. } => value.to_string(),
+        QuintEx::QuintStr { value, .. } => format!("{value:?}"),
+        QuintEx::QuintApp { opcode, args, .. } if args.is_empty() => format!("{opcode}()"),
+        QuintEx::QuintApp { opcode, .. } => format!("{opcode}(..)"),
+        QuintEx::QuintLambda { params, .. } => {
+            format!("({}) => ..", params.iter().map(|p| &p.name).format(", "))
+        }
+        QuintEx::QuintLet { opdef, .. } => format!("let {} = ..", opdef.name),
+    }
+}
+
+const HELP: &str = "\
+c, continue      run until the next breakpoint
+s, step          step in, pausing at the next expression
+n, next          step over the current expression
+o, out           step out of the enclosing expression
+v, vars          show the current and next state variables
+p, params        show the lambda parameters in scope
+bt               show the definitions being evaluated
+b <name|id>      add a breakpoint on a definition or expression id
+d <name|id>      delete a breakpoint
+l                list the breakpoints
+q, quit          abort evaluation";
+
+/// A frontend reading commands, one per line, from `input` and writing to
+/// `output`. Evaluation is aborted when the input ends.
+pub struct CommandLineFrontend<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> CommandLineFrontend<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    fn show_pause(&mut self, pause: &Pause) -> std::io::Result<()> {
+        let reason = match pause.reason {
+            PauseReason::Breakpoint => "Breakpoint",
+            PauseReason::Step => "Step",
+        };
+        write!(self.output, "{reason} at #{}: {}", pause.id, pause.label)?;
+        if let Some(definition) = pause.stack.last() {
+            write!(self.output, " in {definition}")?;
+        }
+        if let Some((index, count)) = pause.branch {
+            write!(self.output, " (branch {} of {count})", index + 1)?;
+        }
+        writeln!(self.output)?;
+        match pause.last_result {
+            Some(Ok(value)) => writeln!(self.output, "  last result: {}", QuintSyntax(value)),
+            Some(Err(err)) => writeln!(self.output, "  last result: {err}"),
+            None => Ok(()),
+        }
+    }
+
+    fn show_vars(&mut self, pause: &Pause) -> std::io::Result<()> {
+        for (title, vars) in [("vars", &pause.vars), ("next vars", &pause.next_vars)] {
+            writeln!(self.output, "{title}:")?;
+            for (name, value) in vars {
+                match value {
+                    Some(value) => writeln!(self.output, "  {name} = {}", QuintSyntax(value))?,
+                    None => writeln!(self.output, "  {name} (not set)")?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the pause and run commands until one of them resumes evaluation.
+    fn session(
+        &mut self,
+        pause: &Pause,
+        breakpoints: &mut Vec<Breakpoint>,
+    ) -> std::io::Result<DebugCommand> {
+        self.show_pause(pause)?;
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(DebugCommand::Abort);
+            }
+            if let Some(command) = self.run_command(line.trim(), pause, breakpoints)? {
+                return Ok(command);
+            }
+        }
+    }
+
+    fn run_command(
+        &mut self,
+        line: &str,
+        pause: &Pause,
+        breakpoints: &mut Vec<Breakpoint>,
+    ) -> std::io::Result<Option<DebugCommand>> {
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+        match command {
+            "c" | "continue" => return Ok(Some(DebugCommand::Continue)),
+            "s" | "step" => return Ok(Some(DebugCommand::StepIn)),
+            "n" | "next" => return Ok(Some(DebugCommand::StepOver)),
+            "o" | "out" => return Ok(Some(DebugCommand::StepOut)),
+            "q" | "quit" => return Ok(Some(DebugCommand::Abort)),
+            "v" | "vars" => self.show_vars(pause)?,
+            "p" | "params" => {
+                for (name, value) in &pause.params {
+                    writeln!(self.output, "  {name} = {}", QuintSyntax(value))?;
+                }
+            }
+            "bt" => {
+                for (i, name) in pause.stack.iter().rev().enumerate() {
+                    writeln!(self.output, "  {i}: {name}")?;
+                }
+            }
+            "b" | "d" => match arg.parse::<Breakpoint>() {
+                Ok(breakpoint) if command == "b" => {
+                    writeln!(self.output, "Breakpoint set on {breakpoint}")?;
+                    breakpoints.push(breakpoint);
+                }
+                Ok(breakpoint) => {
+                    breakpoints.retain(|b| b != &breakpoint);
+                    writeln!(self.output, "Breakpoint on {breakpoint} deleted")?;
+                }
+                Err(err) => writeln!(self.output, "{err}")?,
+            },
+            "l" => {
+                for breakpoint in breakpoints.iter() {
+                    writeln!(self.output, "  {breakpoint}")?;
+                }
+            }
+            "" => {}
+            _ => writeln!(self.output, "{HELP}")?,
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead, W: Write> DebugFrontend for CommandLineFrontend<R, W> {
+    fn paused(&mut self, pause: &Pause, breakpoints: &mut Vec<Breakpoint>) -> DebugCommand {
+        self.session(pause, breakpoints)
+            .unwrap_or(DebugCommand::Abort)
+    }
+}
+
+impl ParsedQuint {
+    /// Evaluate `init` and up to `steps` steps under the debugger, returning
+    /// the states reached. Evaluation stops early if an action is disabled.
+    pub fn debug(
+        &self,
+        steps: usize,
+        seed: u64,
+        debugger: Debugger,
+    ) -> Result<Vec<Value>, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        interpreter.enable_debugging(debugger);
+        let mut env = Env::with_rand_state(interpreter.var_storage.clone(), seed);
+
+        let init = interpreter.compile(&self.init);
+        let step = interpreter.compile(&self.step);
+
+        let mut states = Vec::new();
+        for action in std::iter::once(&init).chain(std::iter::repeat_n(&step, steps)) {
+            if !action.execute(&mut env)?.as_bool() {
+                break;
+            }
+            interpreter.shift();
+            states.push(interpreter.var_storage.borrow().as_record());
+        }
+        Ok(states)
+    }
+}
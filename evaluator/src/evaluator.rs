@@ -3,8 +3,14 @@
 //! Includes the compilation types and stateful datastructures used for
 //! memoization, caching, state variable storage, etc.
 
+use crate::choices::ChoiceSequence;
+use crate::coverage::{Coverage, CoverageKind, HitCounter};
+use crate::debugger::{Debugger, Location, ParamsInScope};
+use crate::picker::SamplingBounds;
+use crate::profiler::{CacheKind, Profiler, Profiling};
 use crate::rand::Rand;
 use crate::storage::{Storage, VariableRegister};
+use crate::temporal::TemporalContext;
 use crate::{builtins::*, ir::*, value::*};
 use fxhash::FxHashMap;
 use std::cell::RefCell;
@@ -81,8 +87,52 @@ pub struct Env {
 
     // The random number generator, used for nondeterministic choices. This is stateful.
     pub rand: Rand,
-    // TODO: trace recorder (for --verbosity) and trace collector (for proper
-    // trace tracking in runs)
+
+    // A fixed sequence of choices to be used instead of `rand`, when set. Used
+    // to replay evaluations and to enumerate all choices.
+    pub choices: Option<ChoiceSequence>,
+
+    // The trace where temporal operators are evaluated, when checking a
+    // temporal property.
+    pub temporal: Option<TemporalContext>,
+
+    // Bounds for picking elements from infinite sets.
+    pub sampling: SamplingBounds,
+
+    // The choices made with `rand` since recording started, if recording.
+    pub recorded_choices: Option<Vec<usize>>,
+
+    // The states produced by `then`, `reps` and `expect` since collection
+    // started, if collecting. Used to report the trace of runs.
+    pub trace_collector: Option<TraceCollector>,
+
+    // Values to be picked by `nondet` bindings, by name, instead of making a
+    // choice. Used to replay transitions recorded with their nondet picks.
+    pub replayed_picks: Option<FxHashMap<QuintName, Value>>,
+    // TODO: trace recorder (for --verbosity)
+}
+
+/// The states reached while evaluating a run. A state is collected when it
+/// becomes the current state, that is, when the state is shifted.
+///
+/// `expect` also shifts to evaluate its predicate, but then goes back so the
+/// same state can be shifted again by whatever comes next (or is left as the
+/// final state of the run). That state is collected as pending, so it is not
+/// collected twice.
+#[derive(Debug, Default)]
+pub struct TraceCollector {
+    states: Vec<Value>,
+    pending: bool,
+}
+
+impl TraceCollector {
+    fn push(&mut self, state: Value, pending: bool) {
+        match self.states.last_mut() {
+            Some(last) if self.pending => *last = state,
+            _ => self.states.push(state),
+        }
+        self.pending = pending;
+    }
 }
 
 impl Env {
@@ -90,6 +140,12 @@ impl Env {
         Self {
             var_storage,
             rand: Rand::new(),
+            choices: None,
+            temporal: None,
+            sampling: SamplingBounds::default(),
+            recorded_choices: None,
+            trace_collector: None,
+            replayed_picks: None,
         }
     }
 
@@ -98,13 +154,81 @@ impl Env {
         Self {
             var_storage,
             rand: Rand::with_state(state),
+            choices: None,
+            temporal: None,
+            sampling: SamplingBounds::default(),
+            recorded_choices: None,
+            trace_collector: None,
+            replayed_picks: None,
+        }
+    }
+
+    /// Make a nondeterministic choice in `0..bound`, either from the choice
+    /// sequence (if set) or from the random number generator.
+    pub fn next_choice(&mut self, bound: usize) -> usize {
+        match &mut self.choices {
+            Some(choices) => choices.next(bound),
+            None => {
+                let value = self.rand.next(bound);
+                if let Some(recorded) = &mut self.recorded_choices {
+                    recorded.push(value);
+                }
+                value
+            }
         }
     }
 
+    /// Start recording the choices made with the random number generator,
+    /// discarding any recorded so far. Replaying the recorded choices with a
+    /// [`ChoiceSequence`] reproduces the same evaluation.
+    pub fn record_choices(&mut self) {
+        self.recorded_choices = Some(Vec::new());
+    }
+
+    /// Stop recording choices, returning the ones recorded.
+    pub fn take_recorded_choices(&mut self) -> Vec<usize> {
+        self.recorded_choices.take().unwrap_or_default()
+    }
+
+    /// Whether choices are being exhaustively enumerated (see
+    /// [`ChoiceSequence::exhaustive`]).
+    pub fn is_exhaustive(&self) -> bool {
+        self.choices.as_ref().is_some_and(|c| c.is_exhaustive())
+    }
+
     /// Shift the state, moving `next_vars` to `vars`.
     pub fn shift(&mut self) {
         self.var_storage.borrow_mut().shift_vars();
     }
+
+    /// Start collecting the states reached by runs (see [`TraceCollector`]),
+    /// discarding any collected so far.
+    pub fn collect_trace(&mut self) {
+        self.trace_collector = Some(TraceCollector::default());
+    }
+
+    /// Collect the current state, if collecting. If `pending`, the state is
+    /// expected to be shifted into again, and is only collected once.
+    pub fn collect_state(&mut self, pending: bool) {
+        if let Some(collector) = &mut self.trace_collector {
+            collector.push(self.var_storage.borrow().as_record(), pending);
+        }
+    }
+
+    /// Stop collecting states, returning the ones collected. If the run
+    /// `finished` successfully, its last action's resulting state (which is
+    /// not shifted into) is included.
+    pub fn take_collected_trace(&mut self, finished: bool) -> Vec<Value> {
+        let Some(mut collector) = self.trace_collector.take() else {
+            return Vec::new();
+        };
+
+        let storage = self.var_storage.borrow();
+        if finished && !collector.pending && storage.next_vars_assigned() {
+            collector.states.push(storage.next_state_record());
+        }
+        collector.states
+    }
 }
 
 /// A stateful interpreter, with memoization, caching, state variable storage
@@ -146,21 +270,134 @@ pub struct Interpreter<'a> {
     // import/instantiation history. Here, we track that history to know which
     // variable from the storage to use during evaluation.
     namespaces: Vec<QuintName>,
-    // TODO: Other params from Typescript implementation, for future reference:
-    // initialNondetPicks: Map<string, RuntimeValue | undefined> = new Map()
+
+    // Hit counters for the expressions compiled after coverage was enabled.
+    coverage: Option<Coverage>,
+
+    // Measurements for the expressions compiled after profiling was enabled,
+    // and whether to also measure built-in operators.
+    profiler: Option<Profiler>,
+    profile_builtins: bool,
+
+    // The debugger wrapping the expressions compiled after debugging was
+    // enabled, and the lambda parameters in scope of the expression being
+    // compiled, which it can inspect.
+    debugger: Option<Debugger>,
+    params_in_scope: ParamsInScope,
+    // Set right before compiling a branch of `actionAny` when debugging, so
+    // the debugger can show which branch is being tried
+    debug_branch: Option<(usize, usize)>,
+
+    // Top-level definitions, keyed by the id of their body (which, for
+    // operators with parameters, is the body of the lambda), when coverage or
+    // profiling is enabled. Definitions are instrumented when their body is
+    // compiled, so they are also covered when it is compiled directly, like
+    // `init` and `step` in simulation.
+    definition_bodies: FxHashMap<QuintId, &'a OpDef>,
 }
 
 impl<'a> Interpreter<'a> {
     pub fn new(table: &'a LookupTable) -> Self {
+        // All nondet bindings are known upfront, so the trace can show every
+        // one of them in every state, even if they were not picked
+        let mut storage = Storage::default();
+        for def in table.values() {
+            if let LookupDefinition::Definition(QuintDeclaration::QuintOpDef(OpDef {
+                qualifier: OpQualifier::Nondet,
+                name,
+                ..
+            })) = def
+            {
+                storage.add_nondet_name(name.clone());
+            }
+        }
+
         Self {
             table,
             param_registry: FxHashMap::default(),
             const_registry: FxHashMap::default(),
             scoped_cached_values: FxHashMap::default(),
-            var_storage: Rc::new(RefCell::new(Storage::default())),
+            var_storage: Rc::new(RefCell::new(storage)),
             memo: Rc::new(RefCell::new(FxHashMap::default())),
             memo_by_instance: FxHashMap::default(),
             namespaces: Vec::new(),
+            coverage: None,
+            profiler: None,
+            profile_builtins: false,
+            debugger: None,
+            params_in_scope: Vec::new(),
+            debug_branch: None,
+            definition_bodies: FxHashMap::default(),
+        }
+    }
+
+    /// Instrument the expressions compiled from now on with hit counters (see
+    /// the [`coverage`](crate::coverage) module). Returns the coverage, which
+    /// can be used to get a report after evaluation.
+    pub fn enable_coverage(&mut self) -> Coverage {
+        let coverage = Coverage::default();
+        self.index_definition_bodies();
+        for op in self.definition_bodies.values() {
+            coverage.counter(op.id, CoverageKind::Definition, &op.name);
+        }
+
+        self.coverage = Some(coverage.clone());
+        coverage
+    }
+
+    /// Measure the evaluation time of the expressions compiled from now on
+    /// (see the [`profiler`](crate::profiler) module). Returns the profiler,
+    /// which can be used to get a report after evaluation, or `None` if
+    /// profiling is disabled.
+    pub fn enable_profiling(&mut self, profiling: Profiling) -> Option<Profiler> {
+        if profiling == Profiling::Disabled {
+            return None;
+        }
+
+        self.index_definition_bodies();
+        self.profile_builtins = profiling == Profiling::Builtins;
+        self.profiler = Some(Profiler::default());
+        self.profiler.clone()
+    }
+
+    /// Wrap the expressions compiled from now on with the hooks of the given
+    /// debugger (see the [`debugger`](crate::debugger) module).
+    pub fn enable_debugging(&mut self, debugger: Debugger) {
+        self.index_definition_bodies();
+        self.debugger = Some(debugger);
+    }
+
+    fn index_definition_bodies(&mut self) {
+        for def in self.table.values() {
+            if let LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op)) = def {
+                if op.qualifier != OpQualifier::Nondet && op.depth.is_none_or(|x| x == 0) {
+                    let body = match &op.expr {
+                        QuintEx::QuintLambda { expr, .. } => expr.id(),
+                        expr => expr.id(),
+                    };
+                    self.definition_bodies.insert(body, op);
+                }
+            }
+        }
+    }
+
+    /// Wrap the body of a top-level definition with its hit counter and
+    /// profiling, if enabled. Other expressions are returned unchanged.
+    fn instrument_definition(&self, body: QuintId, expr: CompiledExpr) -> CompiledExpr {
+        let Some(op) = self.definition_bodies.get(&body) else {
+            return expr;
+        };
+
+        let expr = match &self.coverage {
+            Some(coverage) => count_hits(
+                coverage.counter(op.id, CoverageKind::Definition, &op.name),
+                expr,
+            ),
+            None => expr,
+        };
+        match &self.profiler {
+            Some(profiler) => profiler.wrap(&op.name, expr),
+            None => expr,
         }
     }
 
@@ -169,6 +406,36 @@ impl<'a> Interpreter<'a> {
         self.var_storage.borrow_mut().shift_vars();
     }
 
+    /// Create the registers for a state variable, so it is part of the state
+    /// records even if no compiled expression refers to it.
+    pub fn declare_var(&mut self, id: QuintId, name: &QuintName) {
+        self.create_var(id, name);
+    }
+
+    /// The values given to constants through instance overrides, sorted by
+    /// name. Constants only get their values when an expression from the
+    /// instance is evaluated, so this is empty before evaluation.
+    pub fn const_values(&self) -> Vec<(QuintName, Value)> {
+        let mut values = self
+            .table
+            .values()
+            .filter_map(|def| match def {
+                LookupDefinition::Definition(QuintDeclaration::QuintConst(QuintConst {
+                    id,
+                    name,
+                    ..
+                })) => match &*self.const_registry.get(id)?.borrow() {
+                    Ok(value) => Some((name.clone(), value.clone())),
+                    Err(_) => None,
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        values.sort_by(|(a, _), (b, _)| a.cmp(b));
+        values.dedup_by(|(a, _), (b, _)| a == b);
+        values
+    }
+
     fn get_or_create_param(&mut self, param: &QuintLambdaParameter) -> Rc<RefCell<EvalResult>> {
         self.param_registry
             .entry(param.id)
@@ -238,6 +505,62 @@ impl<'a> Interpreter<'a> {
             .clone()
     }
 
+    /// Compile `vars`, from `A.orKeep(vars)`, into an expression that keeps
+    /// the variables it refers to unchanged, unless they were assigned already.
+    /// Variables can be referred to directly (e.g. `(x, y)`), or through the
+    /// definitions used in `vars` (e.g. `val vars = (x, y)`).
+    fn compile_keep(&mut self, vars: &QuintEx) -> CompiledExpr {
+        let mut keeps = Vec::new();
+        self.collect_keeps(vars, &mut keeps);
+        CompiledExpr::new(move |env| {
+            for keep in keeps.iter() {
+                keep.execute(env)?;
+            }
+            Ok(Value::Bool(true))
+        })
+    }
+
+    fn collect_keeps(&mut self, expr: &QuintEx, keeps: &mut Vec<CompiledExpr>) {
+        match expr {
+            QuintEx::QuintName { id, .. } => match self.table.get(id) {
+                Some(def @ LookupDefinition::Definition(QuintDeclaration::QuintVar(var))) => {
+                    // Under the context of the variable, as it may come from
+                    // an instance, like in `assign`
+                    let keep = self.compile_under_context(def, |interpreter| {
+                        let current = interpreter.get_or_create_var(var.id, &var.name);
+                        let next = interpreter.get_next_var(var.id);
+                        CompiledExpr::new(move |_| {
+                            let mut next = next.borrow_mut();
+                            if next.value.is_none() {
+                                next.value = current.borrow().value.clone();
+                            }
+                            Ok(Value::Bool(true))
+                        })
+                    });
+                    keeps.push(keep);
+                }
+                Some(def @ LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op))) => {
+                    let keep = self.compile_under_context(def, |interpreter| {
+                        interpreter.compile_keep(&op.expr)
+                    });
+                    keeps.push(keep);
+                }
+                _ => {}
+            },
+            QuintEx::QuintApp { args, .. } => {
+                for arg in args {
+                    self.collect_keeps(arg, keeps);
+                }
+            }
+            QuintEx::QuintLambda { expr, .. } => self.collect_keeps(expr, keeps),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.collect_keeps(&opdef.expr, keeps);
+                self.collect_keeps(expr, keeps);
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+        }
+    }
+
     fn get_next_var(&self, id: QuintId) -> Rc<RefCell<VariableRegister>> {
         let key = var_with_namespaces(id, &self.namespaces);
 
@@ -291,6 +614,7 @@ impl<'a> Interpreter<'a> {
                             name: param.name.clone(),
                             qualifier: OpQualifier::PureVal,
                             expr,
+                            type_annotation: None,
                             imported_from: None,
                             namespaces: None,
                             depth: None,
@@ -340,7 +664,16 @@ impl<'a> Interpreter<'a> {
                 {
                     // We need to avoid scoped caching in lambdas or top-level expressions
                     // We still have memoization. This caching is special for scoped defs (let-ins)
-                    self.compile(&op.expr)
+                    if op.depth.is_none_or(|x| x == 0) {
+                        // Top-level definitions don't see the parameters
+                        // where they are referred to
+                        let outer_params = std::mem::take(&mut self.params_in_scope);
+                        let compiled = self.compile(&op.expr);
+                        self.params_in_scope = outer_params;
+                        compiled
+                    } else {
+                        self.compile(&op.expr)
+                    }
                 } else {
                     let cached_value = {
                         let cached = self.scoped_cached_values.get(&op.id).unwrap();
@@ -389,6 +722,29 @@ impl<'a> Interpreter<'a> {
             d => unimplemented!("{:#?}", d),
         };
 
+        // Actions record their name as the action taken when they succeed.
+        // Parameterized actions are lambdas, so they are recorded when they are
+        // applied instead (see `compile_op`).
+        let compiled_def = match def {
+            LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op))
+                if op.qualifier == OpQualifier::Action
+                    && !matches!(op.expr, QuintEx::QuintLambda { .. }) =>
+            {
+                let name = op.name.clone();
+                CompiledExpr::new(move |env| {
+                    let depth = env.var_storage.borrow_mut().enter_action();
+                    let result = compiled_def.execute(env);
+                    let mut storage = env.var_storage.borrow_mut();
+                    storage.exit_action();
+                    if result.as_ref().is_ok_and(|r| r.as_bool()) {
+                        storage.record_action_taken(&name, depth);
+                    }
+                    result
+                })
+            }
+            _ => compiled_def,
+        };
+
         // For top-level value definitions, we can cache the resulting value,
         // as long as we are careful with state changes.
         match can_cache(def) {
@@ -404,6 +760,14 @@ impl<'a> Interpreter<'a> {
                 // right setting (i.e. with constants properly set)
 
                 let cached_value = Rc::new(RefCell::new(None));
+                let cache_counter = self.profiler.as_ref().map(|profiler| {
+                    let kind = if cache == Cache::ForState {
+                        CacheKind::ForState
+                    } else {
+                        CacheKind::Forever
+                    };
+                    profiler.cache_counter(def.name(), kind)
+                });
                 if cache == Cache::ForState {
                     // This definition may use variables, so we need to clear the cache when they change
                     self.var_storage
@@ -416,8 +780,14 @@ impl<'a> Interpreter<'a> {
                     let mut cached = cached_value.borrow_mut();
                     if let Some(value) = cached.as_ref() {
                         // If the value is already cached, return it
+                        if let Some(counter) = &cache_counter {
+                            counter.hit();
+                        }
                         Ok(value.clone())
                     } else {
+                        if let Some(counter) = &cache_counter {
+                            counter.miss();
+                        }
                         let result = compiled_def.execute(env)?;
                         *cached = Some(result.clone());
                         Ok(result)
@@ -444,6 +814,7 @@ impl<'a> Interpreter<'a> {
     }
 
     pub fn compile(&mut self, expr: &QuintEx) -> CompiledExpr {
+        let debug_branch = self.debug_branch.take();
         if let Some(cached) = self.memo.borrow().get(&expr.id()) {
             return cached.clone();
         }
@@ -451,6 +822,18 @@ impl<'a> Interpreter<'a> {
         let id = expr.id();
 
         let compiled_expr = self.compile_expr_core(expr);
+        let compiled_expr = self.instrument_definition(id, compiled_expr);
+        let compiled_expr = match &self.debugger {
+            Some(debugger) => {
+                let definition = self.definition_bodies.get(&id).map(|op| op.name.clone());
+                debugger.wrap(
+                    Location::new(expr, definition, debug_branch),
+                    self.params_in_scope.clone(),
+                    compiled_expr,
+                )
+            }
+            None => compiled_expr,
+        };
         let wrapped_expr = CompiledExpr::new(move |env| {
             compiled_expr.execute(env).map_err(|err| {
                 // This is where we add the reference to the error, if it is not already there.
@@ -493,13 +876,30 @@ impl<'a> Interpreter<'a> {
                 params,
                 expr,
             } => {
+                let outer_params = self.params_in_scope.len();
+                if self.debugger.is_some() {
+                    for param in params {
+                        let register = self.get_or_create_param(param);
+                        self.params_in_scope.push((param.name.clone(), register));
+                    }
+                }
                 let body = self.compile(expr);
+                self.params_in_scope.truncate(outer_params);
                 let lambda = self.mk_lambda(params.to_vec(), body);
                 CompiledExpr::new(move |_| Ok(lambda.clone()))
             }
 
             QuintEx::QuintApp { id, opcode, args } => {
-                let compiled_args = args.iter().map(|arg| self.compile(arg)).collect::<Vec<_>>();
+                let compiled_args = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        if opcode == "actionAny" && self.debugger.is_some() {
+                            self.debug_branch = Some((i, args.len()));
+                        }
+                        self.compile(arg)
+                    })
+                    .collect::<Vec<_>>();
 
                 if opcode == "assign" {
                     // Assign is too special, so we handle it separately.
@@ -520,11 +920,28 @@ impl<'a> Interpreter<'a> {
                 } else if LAZY_OPS.contains(&opcode.as_str()) {
                     // Lazy operator, compile the arguments and give their
                     // closures to the operator so it decides when to eval
-                    let opcode = opcode.clone();
-                    CompiledExpr::new(move |env| {
-                        let op = compile_lazy_op(&opcode);
+                    let compiled_args = if opcode == "orKeep" {
+                        vec![compiled_args[0].clone(), self.compile_keep(&args[1])]
+                    } else {
+                        compiled_args
+                    };
+                    let compiled_args = match &self.coverage {
+                        Some(coverage) => {
+                            self.count_branches(coverage, opcode, args, compiled_args)
+                        }
+                        None => compiled_args,
+                    };
+                    let name = opcode.clone();
+                    let compiled_expr = CompiledExpr::new(move |env| {
+                        let op = compile_lazy_op(&name);
                         op.execute(env, &compiled_args)
-                    })
+                    });
+                    match &self.profiler {
+                        Some(profiler) if self.profile_builtins => {
+                            profiler.wrap(opcode, compiled_expr)
+                        }
+                        _ => compiled_expr,
+                    }
                 } else {
                     // Otherwise, this is either a normal (eager) builtin, or an user-defined operator.
                     // For both, we first evaluate the arguments and then apply the operator.
@@ -551,6 +968,51 @@ impl<'a> Interpreter<'a> {
                 // Then, we build the expression for the let body. It will use the lookup table and, every time it needs the value
                 // for the definition under the let, it will use the cached value (or eval a new value and store it).
                 let compiled_expr = self.compile(expr);
+
+                if opdef.qualifier == OpQualifier::Nondet {
+                    // Nondet bindings are evaluated upfront, so the pick can be
+                    // recorded. The body then reads it from the cached value.
+                    let name = opdef.name.clone();
+                    let def = self.table.get(&opdef.id).cloned().unwrap_or_else(|| {
+                        LookupDefinition::Definition(QuintDeclaration::QuintOpDef(*opdef.clone()))
+                    });
+                    let compiled_def = self.compile_def(&def);
+                    // A replayed pick is only taken if it could have been
+                    // picked, that is, if it is in the set given to `oneOf`
+                    let compiled_set = match &opdef.expr {
+                        QuintEx::QuintApp { opcode, args, .. } if opcode == "oneOf" => {
+                            Some(self.compile(&args[0]))
+                        }
+                        _ => None,
+                    };
+                    return CompiledExpr::new(move |env| {
+                        let replayed = env
+                            .replayed_picks
+                            .as_ref()
+                            .and_then(|picks| picks.get(&name).cloned());
+                        let pick = match replayed {
+                            Some(value) => {
+                                if let Some(set) = &compiled_set {
+                                    if !set.execute(env)?.contains(&value) {
+                                        return Ok(Value::Bool(false));
+                                    }
+                                }
+                                cached_value.replace(Some(Ok(value.clone())));
+                                Ok(value)
+                            }
+                            None => compiled_def.execute(env),
+                        };
+                        let result = pick.and_then(|value| {
+                            env.var_storage
+                                .borrow_mut()
+                                .record_nondet_pick(&name, &value);
+                            compiled_expr.execute(env)
+                        });
+                        cached_value.replace(None);
+                        result
+                    });
+                }
+
                 CompiledExpr::new(move |env| {
                     let result = compiled_expr.execute(env);
                     // After evaluating the whole let expression, we clear the cached value, as it is no longer in scope.
@@ -562,19 +1024,91 @@ impl<'a> Interpreter<'a> {
         }
     }
 
+    /// Wrap the branches of `actionAny`, `actionAll`, `ite` and `matchVariant`
+    /// with hit counters. Arguments of other operators are left unchanged.
+    fn count_branches(
+        &self,
+        coverage: &Coverage,
+        opcode: &str,
+        args: &[QuintEx],
+        compiled_args: Vec<CompiledExpr>,
+    ) -> Vec<CompiledExpr> {
+        args.iter()
+            .zip(compiled_args)
+            .enumerate()
+            .map(|(i, (arg, compiled_arg))| match opcode {
+                "actionAny" | "actionAll" => {
+                    let label = match arg {
+                        QuintEx::QuintName { id, .. } | QuintEx::QuintApp { id, .. } => {
+                            self.table.get(id).map(|def| def.name().to_string())
+                        }
+                        _ => None,
+                    }
+                    .unwrap_or_else(|| format!("{opcode} branch {}", i + 1));
+                    let hits = coverage.counter(arg.id(), CoverageKind::Action, &label);
+                    CompiledExpr::new(move |env| {
+                        let result = compiled_arg.execute(env)?;
+                        if result.as_bool() {
+                            hits.set(hits.get() + 1);
+                        }
+                        Ok(result)
+                    })
+                }
+                "ite" if i > 0 => {
+                    let label = if i == 1 { "ite then" } else { "ite else" };
+                    let hits = coverage.counter(arg.id(), CoverageKind::Branch, label);
+                    count_hits(hits, compiled_arg)
+                }
+                // Cases come in pairs of label and eliminator, after the matched expression
+                "matchVariant" if i > 0 && i % 2 == 0 => {
+                    let label = match &args[i - 1] {
+                        QuintEx::QuintStr { value, .. } => format!("match case {value}"),
+                        _ => "match case".to_string(),
+                    };
+                    let hits = coverage.counter(arg.id(), CoverageKind::Branch, &label);
+                    count_hits(hits, compiled_arg)
+                }
+                _ => compiled_arg,
+            })
+            .collect()
+    }
+
     pub fn compile_op(&mut self, id: &QuintId, op: &str) -> CompiledExprWithArgs {
         match self.table.get(id) {
             Some(def) => {
                 // A user-defined operator
+                let action_name = match def {
+                    LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op))
+                        if op.qualifier == OpQualifier::Action =>
+                    {
+                        Some(op.name.clone())
+                    }
+                    _ => None,
+                };
                 let op = self.compile_def(def);
                 CompiledExprWithArgs::new(move |env, args| {
                     let lambda = op.execute(env)?;
                     let closure = lambda.as_closure();
-                    closure(env, args)
+                    let Some(name) = &action_name else {
+                        return closure(env, args);
+                    };
+                    let depth = env.var_storage.borrow_mut().enter_action();
+                    let result = closure(env, args);
+                    let mut storage = env.var_storage.borrow_mut();
+                    storage.exit_action();
+                    if result.as_ref().is_ok_and(|r| r.as_bool()) {
+                        storage.record_action_taken(name, depth);
+                    }
+                    result
                 })
             }
             // A built-in. We already checked that this is not lazy before.
-            None => compile_eager_op(op),
+            None => match &self.profiler {
+                Some(profiler) if self.profile_builtins => {
+                    profiler.wrap_with_args(op, compile_eager_op(op))
+                }
+                _ => compile_eager_op(op),
+            },
         }
     }
 
@@ -584,6 +1118,14 @@ impl<'a> Interpreter<'a> {
     }
 }
 
+/// Count every evaluation of `expr`.
+fn count_hits(hits: HitCounter, expr: CompiledExpr) -> CompiledExpr {
+    CompiledExpr::new(move |env| {
+        hits.set(hits.get() + 1);
+        expr.execute(env)
+    })
+}
+
 fn builtin_value(name: &str) -> CompiledExpr {
     match name {
         "true" => CompiledExpr::new(move |_| Ok(Value::Bool(true))),
@@ -594,6 +1136,8 @@ fn builtin_value(name: &str) -> CompiledExpr {
                 Value::Bool(false),
             ])))
         }),
+        "Int" => CompiledExpr::new(move |_| Ok(Value::IntSet)),
+        "Nat" => CompiledExpr::new(move |_| Ok(Value::NatSet)),
         _ => unimplemented!("Unknown builtin name: {}", name),
     }
 }
@@ -0,0 +1,259 @@
+//! Exporters writing [`Trace`]s in formats other than plain ITF files:
+//!  - [`TlaExporter`] writes TLC-style text (`State 1: /\ x = 3`), for reading;
+//!  - [`JsonlExporter`] writes each ITF state as a JSON object on its own line,
+//!    for log pipelines that consume streams;
+//!  - [`DotExporter`] writes a Graphviz graph of the states, with the
+//!    transitions labelled by the action taken.
+//!
+//! Actions are only known when traces record metadata for model-based testing
+//! (see [`Storage::as_record`](crate::storage::Storage::as_record)), and
+//! metadata fields are not written as variables in the text formats.
+
+use crate::ir::QuintError;
+use crate::itf::{ItfContext, Trace};
+use crate::value::Value;
+use itertools::Itertools;
+use std::fmt;
+use std::str::FromStr;
+
+/// Writes a trace in some output format.
+pub trait TraceExporter {
+    /// The extension of the files this exporter writes, without the leading dot
+    fn extension(&self) -> &'static str;
+
+    /// Render the trace. The `context` is only used by formats that carry
+    /// metadata, like ITF.
+    fn export(&self, trace: &Trace, context: &ItfContext) -> Result<String, QuintError>;
+}
+
+/// The output formats for traces, as given in the command line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutFormat {
+    #[default]
+    Itf,
+    Tla,
+    Jsonl,
+    Dot,
+}
+
+impl OutFormat {
+    pub fn exporter(&self) -> &'static dyn TraceExporter {
+        match self {
+            OutFormat::Itf => &ItfExporter,
+            OutFormat::Tla => &TlaExporter,
+            OutFormat::Jsonl => &JsonlExporter,
+            OutFormat::Dot => &DotExporter,
+        }
+    }
+}
+
+impl FromStr for OutFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "itf" => Ok(OutFormat::Itf),
+            "tla" => Ok(OutFormat::Tla),
+            "jsonl" => Ok(OutFormat::Jsonl),
+            "dot" => Ok(OutFormat::Dot),
+            _ => Err(format!(
+                "Unknown output format `{s}`, expected one of: itf, tla, jsonl, dot"
+            )),
+        }
+    }
+}
+
+/// Writes the whole trace as a single ITF document (see [`Trace::to_itf`]).
+pub struct ItfExporter;
+
+impl TraceExporter for ItfExporter {
+    fn extension(&self) -> &'static str {
+        "itf.json"
+    }
+
+    fn export(&self, trace: &Trace, context: &ItfContext) -> Result<String, QuintError> {
+        let itf_trace = trace.clone().to_itf(context)?;
+        serde_json::to_string(&itf_trace).map_err(|e| QuintError::new("QNT520", &e.to_string()))
+    }
+}
+
+/// Writes each ITF state on its own line, with its index in the `#meta` field.
+pub struct JsonlExporter;
+
+impl TraceExporter for JsonlExporter {
+    fn extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn export(&self, trace: &Trace, context: &ItfContext) -> Result<String, QuintError> {
+        let itf_trace = trace.clone().to_itf(context)?;
+        let mut output = String::new();
+        for state in &itf_trace.states {
+            let line = serde_json::to_string(state)
+                .map_err(|e| QuintError::new("QNT520", &e.to_string()))?;
+            output.push_str(&line);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
+
+/// Writes the trace the way TLC prints counterexamples, with values in TLA+
+/// syntax. Variants are written as in Apalache, `Variant("A", value)`.
+pub struct TlaExporter;
+
+impl TraceExporter for TlaExporter {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(&self, trace: &Trace, _context: &ItfContext) -> Result<String, QuintError> {
+        let mut output = String::new();
+        if trace.deadlock {
+            output.push_str("Error: Deadlock reached.\n");
+        } else if let Some(invariant) = &trace.violated_invariant {
+            output.push_str(&format!("Error: Invariant {invariant} is violated.\n"));
+        } else if trace.violation {
+            output.push_str("Error: The trace is a violation.\n");
+        }
+        if trace.violation {
+            output.push_str("Error: The behavior up to this point is:\n");
+        }
+
+        for (i, state) in trace.states.iter().enumerate() {
+            let label = match action_taken(state) {
+                Some(action) => format!("<{action}>"),
+                None if i == 0 => "<Initial predicate>".to_string(),
+                None => "<Next>".to_string(),
+            };
+            output.push_str(&format!("State {}: {label}\n", i + 1));
+            for (name, value) in state_vars(state) {
+                output.push_str(&format!("/\\ {name} = {}\n", Tla(value)));
+            }
+            output.push('\n');
+        }
+
+        if let Some(index) = trace.loop_index {
+            output.push_str(&format!(
+                "State {}: Back to state {}\n",
+                trace.states.len() + 1,
+                index + 1
+            ));
+        }
+        Ok(output)
+    }
+}
+
+/// Writes the trace as a Graphviz graph, with one node per state listing its
+/// variables. The last state is highlighted if the trace is a violation.
+pub struct DotExporter;
+
+impl TraceExporter for DotExporter {
+    fn extension(&self) -> &'static str {
+        "dot"
+    }
+
+    fn export(&self, trace: &Trace, _context: &ItfContext) -> Result<String, QuintError> {
+        let mut output = String::from("digraph trace {\n  node [shape=box];\n");
+        let last = trace.states.len().saturating_sub(1);
+        for (i, state) in trace.states.iter().enumerate() {
+            let mut label = format!("State {}\\l", i + 1);
+            for (name, value) in state_vars(state) {
+                label.push_str(&dot_escape(&format!("{name} = {}", Tla(value))));
+                label.push_str("\\l");
+            }
+            let style = if i == last && trace.violation {
+                ", color=red"
+            } else {
+                ""
+            };
+            output.push_str(&format!("  s{i} [label=\"{label}\"{style}];\n"));
+        }
+
+        for (i, state) in trace.states.iter().enumerate().skip(1) {
+            output.push_str(&format!("  s{} -> s{i}", i - 1));
+            if let Some(action) = action_taken(state) {
+                output.push_str(&format!(" [label=\"{}\"]", dot_escape(action)));
+            }
+            output.push_str(";\n");
+        }
+        if let Some(index) = trace.loop_index {
+            output.push_str(&format!("  s{last} -> s{index} [style=dashed];\n"));
+        }
+
+        output.push_str("}\n");
+        Ok(output)
+    }
+}
+
+/// The action recorded as taken to reach a state, if any.
+fn action_taken(state: &Value) -> Option<&str> {
+    match state.as_record_map().get("mbt::actionTaken") {
+        Some(Value::Str(action)) if !action.is_empty() => Some(action),
+        _ => None,
+    }
+}
+
+/// The variables of a state, sorted by name, leaving metadata out.
+fn state_vars(state: &Value) -> Vec<(&str, &Value)> {
+    state
+        .as_record_map()
+        .iter()
+        .filter(|(name, _)| !name.starts_with("mbt::"))
+        .map(|(name, value)| (name.as_ref(), value))
+        .sorted_by_key(|(name, _)| *name)
+        .collect()
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A value written in TLA+ syntax. Elements of sets and maps are sorted by
+/// their text, so the output is stable.
+struct Tla<'a>(&'a Value);
+
+impl fmt::Display for Tla<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Bool(true) => write!(f, "TRUE"),
+            Value::Bool(false) => write!(f, "FALSE"),
+            Value::Str(s) => write!(f, "{s:?}"),
+            Value::IntSet => write!(f, "Int"),
+            Value::NatSet => write!(f, "Nat"),
+            value @ (Value::Set(_)
+            | Value::Interval(_, _)
+            | Value::CrossProduct(_)
+            | Value::PowerSet(_)
+            | Value::MapSet(_, _))
+                if !value.is_infinite() =>
+            {
+                let elems = value.as_set().iter().map(|v| Tla(v).to_string()).sorted();
+                write!(f, "{{{}}}", elems.format(", "))
+            }
+            Value::Tuple(elems) | Value::List(elems) => {
+                write!(f, "<<{}>>", elems.iter().map(Tla).format(", "))
+            }
+            Value::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .sorted_by_key(|(name, _)| name.to_string())
+                    .map(|(name, value)| format!("{name} |-> {}", Tla(value)));
+                write!(f, "[{}]", fields.format(", "))
+            }
+            // TLC writes the empty function as `<<>>`
+            Value::Map(map) if map.is_empty() => write!(f, "<<>>"),
+            Value::Map(map) => {
+                let pairs = map
+                    .iter()
+                    .map(|(key, value)| format!("{} :> {}", Tla(key), Tla(value)))
+                    .sorted();
+                write!(f, "({})", pairs.format(" @@ "))
+            }
+            Value::Variant(label, value) => write!(f, "Variant({label:?}, {})", Tla(value)),
+            // Infinite sets and lambdas have no TLA+ value
+            value => write!(f, "{value}"),
+        }
+    }
+}
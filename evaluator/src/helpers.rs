@@ -10,7 +10,7 @@
 
 use crate::ir::OpDef;
 use crate::ir::{QuintDeclaration, QuintOutput};
-use crate::simulator::ParsedQuint;
+use crate::simulator::{NamedInvariant, ParsedQuint};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -56,8 +56,68 @@ pub fn parse_from_path(
     init: &str,
     step: &str,
     inv: Option<&str>,
+    temporal: Option<&str>,
     main: Option<&str>,
 ) -> Result<ParsedQuint, Box<dyn Error>> {
+    parse_from_path_with_invariants(file_path, init, step, inv, &[], temporal, main)
+}
+
+/// Like [`parse_from_path`], also reading the definitions of the `invariants`
+/// to be checked and reported on separately.
+pub fn parse_from_path_with_invariants(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: Option<&str>,
+    invariants: &[&str],
+    temporal: Option<&str>,
+    main: Option<&str>,
+) -> Result<ParsedQuint, Box<dyn Error>> {
+    let mut args = vec!["--init", init, "--step", step];
+    args.extend(["--invariant", inv.unwrap_or("true")]);
+    args.extend(temporal.map(|t| ["--temporal", t]).unwrap_or_default());
+    let output = compile_from_path(file_path, main, &args)?;
+
+    Ok(ParsedQuint {
+        init: output
+            .find_definition_by_name("q::init")
+            .unwrap()
+            .expr
+            .clone(),
+        step: output
+            .find_definition_by_name("q::step")
+            .unwrap()
+            .expr
+            .clone(),
+        invariant: output
+            .find_definition_by_name("q::inv")
+            .unwrap()
+            .expr
+            .clone(),
+        invariants: output.find_invariants(invariants)?,
+        temporal: temporal.map(|_| {
+            output
+                .find_definition_by_name("q::temporal")
+                .unwrap()
+                .expr
+                .clone()
+        }),
+        table: output.table,
+    })
+}
+
+/// Compile a Quint file with the `quint` typescript tool, passing any extra
+/// `args` to `quint compile`, and read its output. If the file is a `.json`,
+/// it is read as the output of a previous compilation instead.
+pub fn compile_from_path(
+    file_path: &Path,
+    main: Option<&str>,
+    args: &[&str],
+) -> Result<QuintOutput, Box<dyn Error>> {
+    if file_path.extension().is_some_and(|ext| ext == "json") {
+        return Ok(serde_json::from_reader(File::open(file_path)?)?);
+    }
+
     let dir = tempfile::tempdir()?;
     let file_name = dir.path().join("tictactoe.json");
     let file = File::create(file_name.clone()).expect("failed to open file");
@@ -67,9 +127,7 @@ pub fn parse_from_path(
     let output = Command::new("quint")
         .arg("compile")
         .arg(file_path)
-        .args(["--init", init])
-        .args(["--step", step])
-        .args(["--invariant", inv.unwrap_or("true")])
+        .args(args)
         .args(main.map(|m| vec!["--main", m]).unwrap_or_default())
         .args(["--flatten", "false"])
         .stdout(file)
@@ -90,22 +148,34 @@ pub fn parse_from_path(
     let jd = &mut serde_json::Deserializer::from_str(serialized_quint.as_str());
     let output: QuintOutput = serde_path_to_error::deserialize(jd).unwrap();
 
+    Ok(output)
+}
+
+/// Read the JSON output of a previous `quint compile` call (like the ones in
+/// `fixtures/`) and build the simulation input from the given definitions of
+/// the main module. This doesn't require `quint` to be installed.
+pub fn parse_from_json(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: &str,
+    temporal: Option<&str>,
+) -> Result<ParsedQuint, Box<dyn Error>> {
+    let file = File::open(file_path)?;
+    let output: QuintOutput = serde_json::from_reader(file)?;
+
     Ok(ParsedQuint {
-        init: output
-            .find_definition_by_name("q::init")
-            .unwrap()
-            .expr
-            .clone(),
-        step: output
-            .find_definition_by_name("q::step")
-            .unwrap()
-            .expr
-            .clone(),
-        invariant: output
-            .find_definition_by_name("q::inv")
-            .unwrap()
-            .expr
-            .clone(),
+        init: output.find_definition_by_name(init)?.expr.clone(),
+        step: output.find_definition_by_name(step)?.expr.clone(),
+        invariant: output.find_definition_by_name(inv)?.expr.clone(),
+        invariants: Vec::new(),
+        temporal: temporal
+            .map(|t| {
+                output
+                    .find_definition_by_name(t)
+                    .map(|def| def.expr.clone())
+            })
+            .transpose()?,
         table: output.table,
     })
 }
@@ -123,4 +193,17 @@ impl QuintOutput {
             })
             .ok_or_else(|| "Input definition not found".into())
     }
+
+    /// The definitions of the given invariants, by name.
+    pub fn find_invariants(&self, names: &[&str]) -> Result<Vec<NamedInvariant>, Box<dyn Error>> {
+        names
+            .iter()
+            .map(|name| {
+                Ok(NamedInvariant {
+                    name: name.to_string(),
+                    expr: self.find_definition_by_name(name)?.expr.clone(),
+                })
+            })
+            .collect()
+    }
 }
@@ -1,6 +1,7 @@
 //! Internal Representation (IR) of Quint, to be deserialized from the Quint compiler
 //! input.
 
+use crate::types::QuintType;
 use fxhash::FxBuildHasher;
 use hipstr::LocalHipStr;
 use indexmap::IndexMap;
@@ -94,6 +95,9 @@ pub struct OpDef {
     pub name: QuintName,
     pub qualifier: OpQualifier,
     pub expr: QuintEx,
+    // Only set by the Rust parser, for tooling: the interpreter doesn't need it
+    #[serde(skip)]
+    pub type_annotation: Option<QuintType>,
     #[serde(rename = "importedFrom")]
     pub imported_from: Option<ImportedFrom>,
     pub namespaces: Option<Vec<QuintName>>,
@@ -118,6 +122,12 @@ pub enum ImportedFrom {
 pub struct QuintVar {
     pub id: QuintId,
     pub name: QuintName,
+    #[serde(
+        rename = "typeAnnotation",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub type_annotation: Option<QuintType>,
     #[serde(rename = "importedFrom")]
     pub imported_from: Option<ImportedFrom>,
     pub namespaces: Option<Vec<QuintName>>,
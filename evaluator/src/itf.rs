@@ -1,74 +1,174 @@
-//! Conversion of Traces with [`Value`]s to ITF (Informal Trace Format).
+//! Conversion of Traces with [`Value`]s to ITF (Informal Trace Format), and
+//! of ITF values back to [`Value`]s.
 //!
 //! Read more about it [here](https://apalache-mc.org/docs/adr/015adr-trace.html).
 //!
 //! This format can be parsed by Quint's typescript tool and by the ITF trace
 //! viewer extension on VSCode.
 
+use crate::ir::{QuintError, QuintName};
+use crate::types::QuintType;
 use crate::value::Value;
 use chrono::{self};
+use fxhash::FxHashMap;
 use itf;
 use std::collections::BTreeMap;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Trace {
     pub states: Vec<Value>,
     pub violation: bool,
+    /// Whether the last state is a deadlock, that is, no step can be taken
+    /// from it. Deadlocks are also violations.
+    pub deadlock: bool,
+    /// For traces representing infinite behaviors (lassos), the index of the
+    /// state the last state loops back to
+    pub loop_index: Option<usize>,
+    /// The nondeterministic choices made in the transition to each state,
+    /// starting with `init`, if they were recorded. Replaying them reproduces
+    /// the trace (see [`ChoiceSequence::replay`](crate::choices::ChoiceSequence::replay)).
+    pub choices: Vec<Vec<usize>>,
+    /// The state of the random number generator when the run that produced
+    /// this trace started, which reproduces it
+    pub seed: Option<u64>,
+    /// The number of the sample that produced this trace, starting at 1
+    pub sample: Option<usize>,
+    /// The name of the invariant violated in the last state, if known
+    pub violated_invariant: Option<QuintName>,
+}
+
+/// Information about the specification and the run that produced a trace,
+/// written in the ITF metadata along with the trace's own.
+#[derive(Debug, Clone, Default)]
+pub struct ItfContext {
+    /// The file the specification was read from
+    pub source: String,
+    /// The declared types of the state variables (see
+    /// [`var_types`](crate::types::var_types))
+    pub var_types: FxHashMap<QuintName, QuintType>,
+    /// The values of the constants used in the run, which are written as
+    /// ITF parameters
+    pub params: Vec<(QuintName, Value)>,
+}
+
+impl ItfContext {
+    /// A context with the source only, without types or parameters.
+    pub fn from_source(source: String) -> Self {
+        Self {
+            source,
+            ..Default::default()
+        }
+    }
 }
 
 impl Trace {
-    pub fn to_itf(self, source: String) -> itf::Trace<itf::Value> {
+    /// Convert the trace to ITF. Fails if the trace has no states or if its
+    /// states are not records.
+    pub fn to_itf(self, context: &ItfContext) -> Result<itf::Trace<itf::Value>, QuintError> {
+        // Find the variable names by taking the fields from the first state
+        // (which should be a record)
+        let vars = match self.states.first() {
+            Some(Value::Record(map)) => map.keys().map(|v| v.to_string()).collect::<Vec<_>>(),
+            Some(state) => {
+                return Err(QuintError::new(
+                    "QNT520",
+                    &format!("Expected a record as the first state, got {state}"),
+                ))
+            }
+            None => {
+                return Err(QuintError::new(
+                    "QNT520",
+                    "Cannot write a trace with no states",
+                ))
+            }
+        };
+
+        // The parameters are written in every state, as ITF requires
+        let params = context
+            .params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_itf()))
+            .collect::<Vec<_>>();
         let states = self
             .states
             .iter()
             .enumerate()
-            .map(|(i, v)| itf::State {
-                meta: itf::state::Meta {
-                    index: Some(i as u64),
-                    other: BTreeMap::default(),
-                },
-                value: v.to_itf(),
+            .map(|(i, v)| {
+                let value = match v.to_itf() {
+                    itf::Value::Record(record) if !params.is_empty() => {
+                        itf::Value::Record(record.into_iter().chain(params.clone()).collect())
+                    }
+                    value => value,
+                };
+                itf::State {
+                    meta: itf::state::Meta {
+                        index: Some(i as u64),
+                        other: BTreeMap::default(),
+                    },
+                    value,
+                }
             })
             .collect::<Vec<itf::State<itf::Value>>>();
 
-        // Find the variable names by taking the fields from the first state
-        // (which should be a record)
-        let vars = if let Some(Value::Record(map)) = self.states.first() {
-            map.keys().map(|v| v.to_string()).collect::<Vec<_>>()
-        } else {
-            panic!("Expected a record, got {}", self.states[0]);
-        };
+        let var_types = vars
+            .iter()
+            .filter_map(|var| {
+                let ty = context.var_types.get(var.as_str())?;
+                Some((var.clone(), ty.to_string()))
+            })
+            .collect();
 
         let mut other = BTreeMap::new();
         other.insert(
             "status".to_string(),
-            if self.violation {
+            if self.deadlock {
+                "deadlock".to_string()
+            } else if self.violation {
                 "violation".to_string()
             } else {
                 "ok".to_string()
             },
         );
+        if let Some(seed) = self.seed {
+            other.insert("seed".to_string(), format!("0x{seed:x}"));
+        }
+        if let Some(sample) = self.sample {
+            other.insert("sample".to_string(), sample.to_string());
+        }
+        if let Some(invariant) = &self.violated_invariant {
+            other.insert("invariant".to_string(), invariant.to_string());
+        }
 
-        itf::Trace {
+        let mut description = format!(
+            "Created by Quint (Rust version) on {}",
+            chrono::offset::Local::now().to_rfc2822()
+        );
+        if let Some(invariant) = &self.violated_invariant {
+            description.push_str(&format!(", violating {invariant}"));
+        }
+
+        Ok(itf::Trace {
             meta: itf::trace::Meta {
                 format: Some("ITF".to_string()),
                 format_description: Some(
                     "https://apalache-mc.org/docs/adr/015adr-trace.html".to_string(),
                 ),
-                source: Some(source),
-                description: Some(format!(
-                    "Created by Quint (Rust version) on {}",
-                    chrono::offset::Local::now().to_rfc2822()
-                )),
-                var_types: BTreeMap::default(),
+                source: Some(context.source.clone()),
+                description: Some(description),
+                var_types,
                 timestamp: Some(chrono::offset::Local::now().timestamp_millis() as u64),
                 other,
             },
             vars,
             states,
-            params: vec![],
-            loop_index: None,
-        }
+            params: context
+                .params
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            loop_index: self.loop_index.map(|i| i as u64),
+        })
     }
 }
 
@@ -78,6 +178,9 @@ impl Value {
             Self::Int(i) => itf::Value::Number(*i),
             Self::Bool(b) => itf::Value::Bool(*b),
             Self::Str(s) => itf::Value::String(s.to_string()),
+            Self::IntSet | Self::NatSet | Self::AllLists(_) => unserializable(self),
+            // Powersets, cross products and map sets over infinite sets
+            _ if self.is_infinite() => unserializable(self),
             Self::Set(_)
             | Self::Interval(_, _)
             | Self::CrossProduct(_)
@@ -108,3 +211,214 @@ impl Value {
         }
     }
 }
+
+/// Sets that can't be enumerated are written out in Quint syntax.
+fn unserializable(value: &Value) -> itf::Value {
+    itf::Value::Unserializable(
+        serde_json::from_value(serde_json::json!({ "#unserializable": value.to_string() }))
+            .expect("an unserializable value should be built from a string"),
+    )
+}
+
+impl Value {
+    /// Convert an ITF value back to a [`Value`]. This is the inverse of
+    /// [`Value::to_itf`], except that records with exactly the fields `tag`
+    /// (a string) and `value` are read as variants, since ITF uses that
+    /// encoding for both.
+    pub fn from_itf(value: &itf::Value) -> Result<Value, QuintError> {
+        Ok(match value {
+            itf::Value::Bool(b) => Value::Bool(*b),
+            itf::Value::Number(n) => Value::Int(*n),
+            itf::Value::String(s) => Value::Str(s.as_str().into()),
+            itf::Value::BigInt(n) => Value::Int(i64::try_from(n.get()).map_err(|_| {
+                QuintError::new("QNT520", &format!("Integer {n} does not fit in 64 bits"))
+            })?),
+            itf::Value::List(elems) => Value::List(
+                elems
+                    .iter()
+                    .map(Value::from_itf)
+                    .collect::<Result<_, _>>()?,
+            ),
+            itf::Value::Tuple(elems) => Value::Tuple(
+                elems
+                    .iter()
+                    .map(Value::from_itf)
+                    .collect::<Result<_, _>>()?,
+            ),
+            itf::Value::Set(elems) => Value::Set(
+                elems
+                    .iter()
+                    .map(Value::from_itf)
+                    .collect::<Result<_, _>>()?,
+            ),
+            itf::Value::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(k, v)| Ok((Value::from_itf(k)?, Value::from_itf(v)?)))
+                    .collect::<Result<_, QuintError>>()?,
+            ),
+            itf::Value::Record(fields) => match (fields.len(), fields.get("tag")) {
+                (2, Some(itf::Value::String(tag))) if fields.contains_key("value") => {
+                    let value = Value::from_itf(fields.get("value").unwrap())?;
+                    Value::Variant(tag.as_str().into(), Rc::new(value))
+                }
+                _ => Value::Record(
+                    fields
+                        .iter()
+                        .map(|(k, v)| Ok((k.as_str().into(), Value::from_itf(v)?)))
+                        .collect::<Result<_, QuintError>>()?,
+                ),
+            },
+            itf::Value::Unserializable(_) => {
+                return Err(QuintError::new(
+                    "QNT520",
+                    "Unserializable ITF values cannot be converted",
+                ))
+            }
+        })
+    }
+}
+
+impl Value {
+    /// Convert an ITF value back to a [`Value`] of the given type. Unlike
+    /// [`Value::from_itf`], records and variants are told apart by the type.
+    /// Values of constant types or type variables, which are unknown, are
+    /// converted without type information.
+    pub fn from_itf_with_type(value: &itf::Value, ty: &QuintType) -> Result<Value, QuintError> {
+        let mismatch = || {
+            QuintError::new(
+                "QNT520",
+                &format!("ITF value {value:?} does not have type {ty}"),
+            )
+        };
+
+        Ok(match (ty, value) {
+            (QuintType::Bool, itf::Value::Bool(_))
+            | (QuintType::Int, itf::Value::Number(_) | itf::Value::BigInt(_))
+            | (QuintType::Str, itf::Value::String(_))
+            | (
+                QuintType::Const { .. }
+                | QuintType::Var { .. }
+                | QuintType::App { .. }
+                | QuintType::Oper { .. },
+                _,
+            ) => Value::from_itf(value)?,
+            (QuintType::Set { elem }, itf::Value::Set(elems)) => Value::Set(
+                elems
+                    .iter()
+                    .map(|v| Value::from_itf_with_type(v, elem))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (QuintType::List { elem }, itf::Value::List(elems)) => Value::List(
+                elems
+                    .iter()
+                    .map(|v| Value::from_itf_with_type(v, elem))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (QuintType::Fun { arg, res }, itf::Value::Map(map)) => Value::Map(
+                map.iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            Value::from_itf_with_type(k, arg)?,
+                            Value::from_itf_with_type(v, res)?,
+                        ))
+                    })
+                    .collect::<Result<_, QuintError>>()?,
+            ),
+            (QuintType::Tup { fields }, itf::Value::Tuple(elems)) => {
+                let fields = fields.fields();
+                if fields.len() != elems.len() {
+                    return Err(mismatch());
+                }
+                Value::Tuple(
+                    elems
+                        .iter()
+                        .zip(fields)
+                        .map(|(v, field)| Value::from_itf_with_type(v, &field.field_type))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            (QuintType::Rec { fields }, itf::Value::Record(record)) => Value::Record(
+                record
+                    .iter()
+                    .map(|(k, v)| {
+                        let value = match fields.field_type(k) {
+                            Some(field_type) => Value::from_itf_with_type(v, field_type)?,
+                            None => Value::from_itf(v)?,
+                        };
+                        Ok((k.as_str().into(), value))
+                    })
+                    .collect::<Result<_, QuintError>>()?,
+            ),
+            (QuintType::Sum { fields }, itf::Value::Record(record)) => {
+                let (Some(itf::Value::String(tag)), Some(value)) =
+                    (record.get("tag"), record.get("value"))
+                else {
+                    return Err(mismatch());
+                };
+                let field_type = fields.field_type(tag).ok_or_else(mismatch)?;
+                Value::Variant(
+                    tag.as_str().into(),
+                    Rc::new(Value::from_itf_with_type(value, field_type)?),
+                )
+            }
+            _ => return Err(mismatch()),
+        })
+    }
+}
+
+impl Trace {
+    /// Read a trace from ITF. Variables are converted with their type from
+    /// the `var_types` metadata of the trace or, if it is not there, from
+    /// `types` (e.g. from [`var_types`](crate::types::var_types)). Other
+    /// fields, like the metadata for model-based testing, are converted
+    /// without type information (see [`Value::from_itf`]), while parameters
+    /// are left out of the states.
+    pub fn from_itf(
+        trace: &itf::Trace<itf::Value>,
+        types: &FxHashMap<QuintName, QuintType>,
+    ) -> Result<Trace, QuintError> {
+        let itf_types = trace
+            .meta
+            .var_types
+            .iter()
+            .filter_map(|(name, ty)| Some((name.as_str(), ty.parse::<QuintType>().ok()?)))
+            .collect::<FxHashMap<_, _>>();
+        let type_of = |name: &str| itf_types.get(name).or_else(|| types.get(name));
+
+        let states = trace
+            .states
+            .iter()
+            .map(|state| match &state.value {
+                itf::Value::Record(fields) => Ok(Value::Record(
+                    fields
+                        .iter()
+                        .filter(|(name, _)| !trace.params.contains(name))
+                        .map(|(name, value)| {
+                            let value = match type_of(name) {
+                                Some(ty) => Value::from_itf_with_type(value, ty)?,
+                                None => Value::from_itf(value)?,
+                            };
+                            Ok((name.as_str().into(), value))
+                        })
+                        .collect::<Result<_, QuintError>>()?,
+                )),
+                value => Value::from_itf(value),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let meta = &trace.meta.other;
+        let status = meta.get("status").map(String::as_str);
+        Ok(Trace {
+            states,
+            violation: matches!(status, Some("violation" | "deadlock")),
+            deadlock: status == Some("deadlock"),
+            loop_index: trace.loop_index.map(|i| i as usize),
+            choices: Vec::new(),
+            seed: meta
+                .get("seed")
+                .and_then(|seed| u64::from_str_radix(seed.trim_start_matches("0x"), 16).ok()),
+            sample: meta.get("sample").and_then(|sample| sample.parse().ok()),
+            violated_invariant: meta.get("invariant").map(|name| name.as_str().into()),
+        })
+    }
+}
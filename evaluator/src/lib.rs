@@ -4,14 +4,29 @@
 //! which can be evaluated to yield the expression's result.
 
 pub mod builtins;
+pub mod choices;
+pub mod conformance;
+pub mod coverage;
+pub mod debugger;
 pub mod evaluator;
+pub mod export;
 pub mod helpers;
 pub mod ir;
 pub mod itf;
 pub mod log;
+pub mod model_checker;
 pub mod normalizer;
+pub mod outcome;
 pub mod picker;
+pub mod profiler;
 pub mod rand;
+pub mod render;
+pub mod server;
+pub mod shrinker;
 pub mod simulator;
 pub mod storage;
+pub mod temporal;
+pub mod tester;
+pub mod transport;
+pub mod types;
 pub mod value;
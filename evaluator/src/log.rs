@@ -3,7 +3,20 @@
 use std::fmt;
 use std::sync::atomic::AtomicBool;
 
-static _HEADERS: &[&str] = &["Parsing", "Simulation", "Result", "Elapsed"];
+static _HEADERS: &[&str] = &[
+    "Parsing",
+    "Simulation",
+    "Checking",
+    "Coverage",
+    "Profile",
+    "Test",
+    "Report",
+    "Invariant",
+    "Diff",
+    "States",
+    "Result",
+    "Elapsed",
+];
 
 pub static JSON: AtomicBool = AtomicBool::new(false);
 
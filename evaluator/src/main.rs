@@ -1,19 +1,44 @@
-//! The CLI has two main commands:
+//! The CLI has seven main commands:
 //!  1. `run`: Runs the simulation on a file with specified parameters,
 //!     to be used for development and tests.
-//!  2. `simulate-from-stdin`: Reads input from standard input (STDIN) and
+//!  2. `check`: Exhaustively explores the state space of a file with the
+//!     model checker, to be used for development and tests.
+//!  3. `test`: Runs the `run` definitions of a file as tests, optionally
+//!     writing a JUnit XML report.
+//!  4. `conform`: Checks that an ITF trace (e.g. from an implementation) can be
+//!     produced by the `init` and `step` of a file.
+//!  5. `debug`: Steps through the evaluation of `init` and `step`, pausing at
+//!     breakpoints, with commands read from the terminal.
+//!  6. `simulate-from-stdin`: Reads input from standard input (STDIN) and
 //!     simulates based on that input, used in the integration with the `quint` typescript tool.
+//!  7. `serve`: Answers JSON-RPC requests to simulate, evaluate and test a
+//!     specification loaded once, for the `quint` typescript tool (see the
+//!     `server` module).
 
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use argh::FromArgs;
 use eyre::bail;
-use quint_evaluator::ir::{QuintError, QuintEx};
-use quint_evaluator::simulator::{ParsedQuint, ProgressUpdate, SimulationResult, TraceStatistics};
+use quint_evaluator::coverage::{CoverageKind, CoverageReport};
+use quint_evaluator::debugger::{Breakpoint, CommandLineFrontend, Debugger};
+use quint_evaluator::export::OutFormat;
+use quint_evaluator::ir::QuintEx;
+use quint_evaluator::itf::{ItfContext, Trace};
+use quint_evaluator::outcome::to_outcome;
+use quint_evaluator::picker::SamplingBounds;
+use quint_evaluator::profiler::{CacheKind, ProfileReport, Profiling};
+use quint_evaluator::rand::{parse_seed, Rand};
+use quint_evaluator::render::{self, QuintSyntax};
+use quint_evaluator::server::Server;
+use quint_evaluator::simulator::{ParsedQuint, ProgressUpdate, SimulationConfig, StopCondition};
+use quint_evaluator::tester::{self, TestOptions};
+use quint_evaluator::types::var_types;
+use quint_evaluator::value::{Difference, DifferenceKind, PathSegment};
 use quint_evaluator::{helpers, log};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 #[derive(FromArgs)]
@@ -27,7 +52,12 @@ struct TopLevel {
 #[argh(subcommand)]
 enum Command {
     Run(RunArgs),
+    Check(CheckArgs),
+    Conform(ConformArgs),
+    Test(TestArgs),
+    Debug(DebugArgs),
     SimulateFromStdin(SimulateQuintArgs),
+    Serve(ServeArgs),
 }
 
 /// Run simulation with command-line arguments
@@ -50,6 +80,19 @@ struct RunArgs {
     #[argh(option)]
     inv: Option<String>,
 
+    /// name of an invariant to check and report on separately (can be repeated)
+    #[argh(option)]
+    invariants: Vec<String>,
+
+    /// whether to keep simulating after one of the --invariants is violated,
+    /// until a counterexample is found for each of them
+    #[argh(switch)]
+    find_all_violations: bool,
+
+    /// name of the temporal property to check
+    #[argh(option)]
+    temporal: Option<String>,
+
     /// name of the main module to check (default: computed from filename)
     #[argh(option)]
     main: Option<String>,
@@ -65,6 +108,267 @@ struct RunArgs {
     /// how many traces to generate (only affects output to out-itf) (default: 1)
     #[argh(option, default = "1")]
     n_traces: usize,
+
+    /// the seed for the random number generator, in decimal or hexadecimal
+    /// with a 0x prefix (default: random)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+
+    /// whether to record the action taken and the nondet picks in each state
+    /// of the traces, for model-based testing
+    #[argh(switch)]
+    mbt: bool,
+
+    /// whether to report a deadlock when `step` cannot be taken from a state
+    /// with any choice of nondeterministic values
+    #[argh(switch)]
+    deadlock: bool,
+
+    /// the smallest integer to pick from `Int` (default: -2^31)
+    #[argh(option, default = "-(1 << 31)")]
+    min_int: i64,
+
+    /// the largest integer to pick from `Int` and `Nat` (default: 2^31 - 1)
+    #[argh(option, default = "(1 << 31) - 1")]
+    max_int: i64,
+
+    /// the maximum size of lists and sets picked from infinite sets (default: 10)
+    #[argh(option, default = "10")]
+    max_size: usize,
+
+    /// whether to minimize traces violating the invariant before writing them
+    #[argh(switch)]
+    shrink: bool,
+
+    /// whether to report which actions, definitions and branches were never
+    /// exercised, writing the full report to coverage.json
+    #[argh(switch)]
+    coverage: bool,
+
+    /// whether to measure the time spent in each definition, writing a
+    /// flamegraph-compatible profile to profile.folded
+    #[argh(switch)]
+    profile: bool,
+
+    /// whether to also measure the time spent in each built-in operator
+    /// (implies --profile)
+    #[argh(switch)]
+    profile_builtins: bool,
+
+    /// the format of the trace files: itf, tla (TLC-style text), jsonl (one
+    /// state per line) or dot (Graphviz) (default: itf)
+    #[argh(option, default = "OutFormat::Itf")]
+    out_format: OutFormat,
+
+    /// stop simulating after this long, reporting on the samples completed
+    /// by then, in seconds or with a unit (ms, s, m, h)
+    #[argh(option, from_str_fn(parse_duration))]
+    max_duration: Option<Duration>,
+
+    /// fail if the command takes longer than this, parsing included, after
+    /// reporting on the samples completed by then, in seconds or with a unit
+    /// (ms, s, m, h)
+    #[argh(option, from_str_fn(parse_duration))]
+    timeout: Option<Duration>,
+}
+
+impl RunArgs {
+    fn config(&self) -> eyre::Result<SimulationConfig> {
+        let sampling = SamplingBounds {
+            min_int: self.min_int,
+            max_int: self.max_int,
+            max_size: self.max_size,
+        };
+        if let Err(error) = sampling.validate() {
+            bail!("Invalid --min-int and --max-int: {}", error.message);
+        }
+
+        let profiling = if self.profile_builtins {
+            Profiling::Builtins
+        } else if self.profile {
+            Profiling::Definitions
+        } else {
+            Profiling::Disabled
+        };
+        Ok(SimulationConfig {
+            steps: self.max_steps,
+            samples: self.max_samples,
+            traces: self.n_traces,
+            seed: self.seed,
+            stop: StopCondition::from_find_all_violations(self.find_all_violations),
+            check_deadlocks: self.deadlock,
+            store_metadata: self.mbt,
+            collect_coverage: self.coverage,
+            profiling,
+            sampling,
+            shrink: self.shrink,
+            max_duration: self.max_duration,
+            ..SimulationConfig::default()
+        })
+    }
+}
+
+/// Exhaustively check the state space with command-line arguments
+#[derive(FromArgs)]
+#[argh(subcommand, name = "check")]
+struct CheckArgs {
+    /// the file to read
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// name of the initializer action (default: "init")
+    #[argh(option, default = "\"init\".to_string()")]
+    init: String,
+
+    /// name of the step action (default: "step")
+    #[argh(option, default = "\"step\".to_string()")]
+    step: String,
+
+    /// name of the invariant to check
+    #[argh(option)]
+    inv: Option<String>,
+
+    /// name of the main module to check (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// the maximum number of steps to explore from the initial states (default: unbounded)
+    #[argh(option)]
+    max_steps: Option<usize>,
+
+    /// the format of the trace files: itf, tla (TLC-style text), jsonl (one
+    /// state per line) or dot (Graphviz) (default: itf)
+    #[argh(option, default = "OutFormat::Itf")]
+    out_format: OutFormat,
+}
+
+/// Check that a trace conforms to a specification with command-line arguments
+#[derive(FromArgs)]
+#[argh(subcommand, name = "conform")]
+struct ConformArgs {
+    /// the file to read
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// the ITF trace to check
+    #[argh(positional)]
+    trace: PathBuf,
+
+    /// name of the initializer action (default: "init")
+    #[argh(option, default = "\"init\".to_string()")]
+    init: String,
+
+    /// name of the step action (default: "step")
+    #[argh(option, default = "\"step\".to_string()")]
+    step: String,
+
+    /// name of the main module to check (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+}
+
+/// Run the `run` definitions of a file as tests
+#[derive(FromArgs)]
+#[argh(subcommand, name = "test")]
+struct TestArgs {
+    /// the file to read (a Quint file, or the JSON output of `quint compile`)
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// name of the main module (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// only run the tests whose names match this regular expression
+    #[argh(option, long = "match")]
+    filter: Option<String>,
+
+    /// the maximum number of times to run each test (default: 10_000)
+    #[argh(option, default = "10000")]
+    max_samples: usize,
+
+    /// the seed for the random number generator, in decimal or hexadecimal
+    /// with a 0x prefix (default: random)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+
+    /// where to write a JUnit XML report
+    #[argh(option)]
+    junit: Option<PathBuf>,
+
+    /// the format of the trace files: itf, tla (TLC-style text), jsonl (one
+    /// state per line) or dot (Graphviz) (default: itf)
+    #[argh(option, default = "OutFormat::Itf")]
+    out_format: OutFormat,
+}
+
+/// Step through the evaluation of a file with command-line arguments
+#[derive(FromArgs)]
+#[argh(subcommand, name = "debug")]
+struct DebugArgs {
+    /// the file to read
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// name of the initializer action (default: "init")
+    #[argh(option, default = "\"init\".to_string()")]
+    init: String,
+
+    /// name of the step action (default: "step")
+    #[argh(option, default = "\"step\".to_string()")]
+    step: String,
+
+    /// name of the main module (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// the number of steps to take after `init` (default: 10)
+    #[argh(option, default = "10")]
+    max_steps: usize,
+
+    /// the seed for the random number generator, in decimal or hexadecimal
+    /// with a 0x prefix (default: random)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+
+    /// pause before evaluating a definition (by name) or an expression (by
+    /// id). Can be given multiple times. Without breakpoints, evaluation
+    /// pauses at the first expression.
+    #[argh(option, long = "break")]
+    breakpoints: Vec<Breakpoint>,
+}
+
+/// Writes a trace to a file named after `name`, with the extension of the
+/// output format, and returns the file name.
+fn write_trace(
+    trace: &Trace,
+    context: &ItfContext,
+    format: OutFormat,
+    name: &str,
+) -> eyre::Result<String> {
+    let exporter = format.exporter();
+    let filename = format!("{name}.{}", exporter.extension());
+    let mut file = File::create(&filename)?;
+    file.write_all(exporter.export(trace, context)?.as_bytes())?;
+    Ok(filename)
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number = number
+        .parse::<f64>()
+        .map_err(|e| format!("invalid duration {value}: {e}"))?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "ms" => number / 1000.0,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration {value}: unknown unit {unit}")),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid duration {value}: {e}"))
 }
 
 /// Run simulation with input from STDIN
@@ -72,6 +376,11 @@ struct RunArgs {
 #[argh(subcommand, name = "simulate-from-stdin")]
 struct SimulateQuintArgs {}
 
+/// Serve JSON-RPC requests on STDIN and STDOUT
+#[derive(FromArgs)]
+#[argh(subcommand, name = "serve")]
+struct ServeArgs {}
+
 /// Data expected on STDIN for simulation
 #[derive(Serialize, Deserialize)]
 struct SimulateInput {
@@ -81,47 +390,51 @@ struct SimulateInput {
     nruns: usize,
     nsteps: usize,
     ntraces: usize,
+    #[serde(default)]
+    mbt: bool,
+    #[serde(default)]
+    deadlock: bool,
+    #[serde(default)]
+    shrink: bool,
+    #[serde(default)]
+    coverage: bool,
+    #[serde(default)]
+    find_all_violations: bool,
+    #[serde(default)]
+    seed: Option<u64>,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "lowercase")]
-enum SimulationStatus {
-    #[serde(rename = "ok")]
-    Success,
-    Violation,
-    Error,
-}
-
-/// Data to be written to STDOUT after simulation
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct Outcome {
-    status: SimulationStatus,
-    errors: Vec<QuintError>,
-    best_traces: Vec<SimulationTrace>,
-    trace_statistics: TraceStatistics,
-    witnessing_traces: Vec<usize>,
-    samples: usize,
-}
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct SimulationTrace {
-    seed: usize,
-    states: itf::Trace<itf::Value>,
-    result: bool,
+impl SimulateInput {
+    fn config(&self) -> SimulationConfig {
+        SimulationConfig {
+            steps: self.nsteps,
+            samples: self.nruns,
+            traces: self.ntraces,
+            seed: self.seed,
+            witnesses: self.witnesses.clone(),
+            stop: StopCondition::from_find_all_violations(self.find_all_violations),
+            check_deadlocks: self.deadlock,
+            store_metadata: self.mbt,
+            collect_coverage: self.coverage,
+            shrink: self.shrink,
+            ..SimulationConfig::default()
+        }
+    }
 }
 
-/// The CLI has two main commands: 1. `run`: Runs the simulation on a file with
-/// specified parameters, to be used for development and tests. 2.
-/// `simulate-from-stdin`: Reads input from standard input (STDIN) and simulates
-/// based on that input, used in the integration with the `quint` typescript tool.
+/// Runs the command given in the arguments. See the module documentation for
+/// the list of commands.
 fn main() -> eyre::Result<()> {
     let top_level: TopLevel = argh::from_env();
 
     match top_level.command {
         Command::Run(args) => run_simulation(args),
+        Command::Check(args) => run_model_checker(args),
+        Command::Conform(args) => run_conformance(args),
+        Command::Test(args) => run_tests(args),
+        Command::Debug(args) => run_debugger(args),
         Command::SimulateFromStdin(_) => simulate_from_stdin(),
+        Command::Serve(_) => serve(),
     }
 }
 
@@ -131,46 +444,414 @@ fn main() -> eyre::Result<()> {
 /// installed and in the PATH).
 fn run_simulation(args: RunArgs) -> eyre::Result<()> {
     log::set_json(false);
+    let timeout = args.timeout.map(|timeout| Instant::now() + timeout);
+    let mut config = args.config()?;
 
     if !fs::exists(&args.file)? {
         bail!("File not found: {}", args.file.display());
     }
 
     log!("Parsing", "Parsing file: {}", args.file.display());
-    let parsed = helpers::parse_from_path(
+    let invariants = args
+        .invariants
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let parsed = helpers::parse_from_path_with_invariants(
         &args.file,
         args.init.as_str(),
         args.step.as_str(),
         args.inv.as_deref(),
+        &invariants,
+        args.temporal.as_deref(),
         args.main.as_deref(),
     )
     .unwrap();
 
     let start = Instant::now();
+    if let Some(deadline) = timeout {
+        config = config.deadline(deadline);
+    }
+
     log!("Simulation", "Starting simulation");
-    let result = parsed.simulate(args.max_steps, args.max_samples, args.n_traces, None);
+    let result = parsed.simulate_with(&config, None);
 
     let elapsed = start.elapsed();
+    let timed_out = result.as_ref().is_ok_and(|r| r.interrupted)
+        && timeout.is_some_and(|deadline| Instant::now() >= deadline);
 
     match result {
         Ok(result) => {
             log!("Result", "{}", result.result);
-            for (i, trace) in result.best_traces.into_iter().enumerate() {
-                let itf_trace = trace.to_itf(args.file.display().to_string());
-                let json_data = serde_json::to_string(&itf_trace)?;
-                let filename = format!("out_{i}.itf.json");
-                let mut file = File::create(filename.clone())?;
-                file.write_all(json_data.as_bytes())?;
+            if result.interrupted {
+                log!(
+                    "Result",
+                    "Ran out of time after {} of {} samples",
+                    result.samples,
+                    args.max_samples
+                );
+            }
+            if result.deadlock {
+                log!("Result", "Deadlock found");
+            }
+            let context = ItfContext {
+                source: args.file.display().to_string(),
+                var_types: var_types(&parsed.table),
+                params: result.params,
+            };
+            for invariant in result.invariants {
+                let Some(trace) = invariant.counterexample else {
+                    log!("Invariant", "{} holds", invariant.name);
+                    continue;
+                };
+                let filename = format!("violation_{}", invariant.name);
+                let filename = write_trace(&trace, &context, args.out_format, &filename)?;
+                log!("Invariant", "{} violated: {filename}", invariant.name);
+            }
+            let mut printed_counterexample = false;
+            for (i, mut trace) in result.best_traces.into_iter().enumerate() {
+                // Without a temporal property, any other violation is of `--inv`
+                if trace.violation && !trace.deadlock && parsed.temporal.is_none() {
+                    trace.violated_invariant = trace
+                        .violated_invariant
+                        .or_else(|| args.inv.as_deref().map(Into::into));
+                }
+                if trace.violation && !printed_counterexample {
+                    log!("Trace", "Counterexample:");
+                    print!("{}", render::render_trace(&trace));
+                    printed_counterexample = true;
+                }
+                let filename = write_trace(&trace, &context, args.out_format, &format!("out_{i}"))?;
                 log!("Trace", "{filename}")
             }
+            if let Some(coverage) = result.coverage {
+                log_coverage(&coverage);
+                let mut file = File::create("coverage.json")?;
+                file.write_all(serde_json::to_string(&coverage)?.as_bytes())?;
+                log!("Coverage", "coverage.json");
+            }
+            if let Some(profile) = result.profile {
+                log_profile(&profile);
+                let mut file = File::create("profile.folded")?;
+                file.write_all(profile.folded_stacks().as_bytes())?;
+                log!("Profile", "profile.folded");
+            }
         }
         Err(e) => log!("Error", "Simulation failed: {e}"),
     }
 
     log!("Elapsed", "{elapsed:.2?}");
+    if let Some(timeout) = args.timeout.filter(|_| timed_out) {
+        bail!("Timed out after {timeout:.2?}");
+    }
     Ok(())
 }
 
+/// Prints a summary of the coverage report, listing everything never exercised.
+fn log_coverage(coverage: &CoverageReport) {
+    let (actions, total_actions) = coverage.ratio(CoverageKind::Action);
+    let (definitions, total_definitions) = coverage.ratio(CoverageKind::Definition);
+    let (branches, total_branches) = coverage.ratio(CoverageKind::Branch);
+    log!(
+        "Coverage",
+        "{actions}/{total_actions} actions enabled, \
+         {definitions}/{total_definitions} definitions run, \
+         {branches}/{total_branches} branches taken"
+    );
+
+    for point in &coverage.actions_never_enabled {
+        log!(
+            "Coverage",
+            "Never enabled: {} (id {})",
+            point.label,
+            point.id
+        );
+    }
+    for point in &coverage.definitions_never_run {
+        log!("Coverage", "Never run: {} (id {})", point.label, point.id);
+    }
+    for point in &coverage.branches_never_taken {
+        log!("Coverage", "Never taken: {} (id {})", point.label, point.id);
+    }
+}
+
+/// Prints the most expensive entries of the profile, and the cache hit rates.
+fn log_profile(profile: &ProfileReport) {
+    for entry in profile.entries.iter().take(10) {
+        let hit_rate = entry.cache_hit_rate().map_or_else(String::new, |rate| {
+            format!(", {:.1}% cache hits", rate * 100.0)
+        });
+        log!(
+            "Profile",
+            "{}: {} calls, {:.2?} inclusive, {:.2?} exclusive{hit_rate}",
+            entry.name,
+            entry.calls,
+            Duration::from_nanos(entry.inclusive_ns as u64),
+            Duration::from_nanos(entry.exclusive_ns as u64)
+        );
+    }
+
+    for (kind, name) in [
+        (CacheKind::ForState, "per-state"),
+        (CacheKind::Forever, "permanent"),
+    ] {
+        let (hits, misses) = profile.cache_totals(kind);
+        if hits + misses > 0 {
+            log!("Profile", "{name} cache: {hits} hits, {misses} misses");
+        }
+    }
+}
+
+/// Utility to run the tests in a file with command-line arguments. Like `run`,
+/// this calls the `quint` typescript binary to parse the provided file, unless
+/// it is already a JSON.
+fn run_tests(args: TestArgs) -> eyre::Result<()> {
+    log::set_json(false);
+
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let output = helpers::compile_from_path(&args.file, args.main.as_deref(), &[])
+        .map_err(|e| eyre::eyre!("{e}"))?;
+
+    let options = TestOptions {
+        filter: args.filter.as_deref().map(Regex::new).transpose()?,
+        max_samples: args.max_samples,
+        seed: args.seed,
+    };
+
+    let start = Instant::now();
+    let results = tester::run_tests(&output, &options);
+
+    for result in &results {
+        if result.passed {
+            log!(
+                "Test",
+                "ok {} passed {} sample(s)",
+                result.name,
+                result.samples
+            );
+            continue;
+        }
+
+        match &result.error {
+            Some(error) => log!("Test", "FAILED {}: {error}", result.name),
+            None => log!("Test", "FAILED {}: returned false", result.name),
+        }
+        log!(
+            "Test",
+            "Use --seed 0x{:x} --match {} to reproduce",
+            result.seed,
+            result.name
+        );
+        if let Some(trace) = result.trace.clone() {
+            let context = ItfContext::from_source(args.file.display().to_string());
+            let filename = write_trace(&trace, &context, args.out_format, &result.name)?;
+            log!("Trace", "{filename}");
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    log!(
+        "Result",
+        "{} passing, {failed} failing",
+        results.len() - failed
+    );
+
+    if let Some(path) = &args.junit {
+        let mut file = File::create(path)?;
+        file.write_all(tester::to_junit_xml(&output.main, &results).as_bytes())?;
+        log!("Report", "{}", path.display());
+    }
+
+    log!("Elapsed", "{:.2?}", start.elapsed());
+    if failed > 0 {
+        bail!("{failed} test(s) failed");
+    }
+    Ok(())
+}
+
+/// Utility to step through a single run in the terminal, stopping at the
+/// given breakpoints, and print the states it went through. Like `run`, this
+/// calls the `quint` typescript binary to parse the provided file.
+fn run_debugger(args: DebugArgs) -> eyre::Result<()> {
+    log::set_json(false);
+
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let parsed = helpers::parse_from_path(
+        &args.file,
+        args.init.as_str(),
+        args.step.as_str(),
+        None,
+        None,
+        args.main.as_deref(),
+    )
+    .map_err(|e| eyre::eyre!("{e}"))?;
+
+    let seed = args.seed.unwrap_or_else(|| Rand::new().get_state());
+    log!("Debugging", "Type `help` for the commands, seed: {seed:#x}");
+    let frontend = CommandLineFrontend::new(io::stdin().lock(), io::stdout());
+    let debugger = Debugger::new(frontend, args.breakpoints);
+    let states = parsed.debug(args.max_steps, seed, debugger)?;
+
+    let trace = Trace {
+        states,
+        violation: false,
+        deadlock: false,
+        loop_index: None,
+        choices: Vec::new(),
+        seed: Some(seed),
+        sample: None,
+        violated_invariant: None,
+    };
+    print!("{}", render::render_trace(&trace));
+    Ok(())
+}
+
+/// Utility to run the model checker with command-line arguments. Like `run`,
+/// this calls the `quint` typescript binary to parse the provided file.
+fn run_model_checker(args: CheckArgs) -> eyre::Result<()> {
+    log::set_json(false);
+
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let parsed = helpers::parse_from_path(
+        &args.file,
+        args.init.as_str(),
+        args.step.as_str(),
+        args.inv.as_deref(),
+        None,
+        args.main.as_deref(),
+    )
+    .unwrap();
+
+    let start = Instant::now();
+    log!("Checking", "Starting model checker");
+    let result = parsed.check(args.max_steps);
+
+    let elapsed = start.elapsed();
+
+    match result {
+        Ok(result) => {
+            log!("Result", "{}", result.result);
+            let stats = &result.statistics;
+            log!(
+                "States",
+                "{} distinct states, {} transitions, depth {}{}",
+                stats.distinct_states,
+                stats.transitions,
+                stats.max_depth,
+                if stats.complete { "" } else { " (bounded)" }
+            );
+            if let Some(trace) = result.counterexample {
+                let context = ItfContext {
+                    source: args.file.display().to_string(),
+                    var_types: var_types(&parsed.table),
+                    params: Vec::new(),
+                };
+                let filename = write_trace(&trace, &context, args.out_format, "counterexample")?;
+                log!("Trace", "{filename}")
+            }
+        }
+        Err(e) => log!("Error", "Model checking failed: {e}"),
+    }
+
+    log!("Elapsed", "{elapsed:.2?}");
+    Ok(())
+}
+
+/// Utility to check a trace against a specification with command-line
+/// arguments. Like `run`, this calls the `quint` typescript binary to parse
+/// the provided file.
+fn run_conformance(args: ConformArgs) -> eyre::Result<()> {
+    log::set_json(false);
+
+    for path in [&args.file, &args.trace] {
+        if !fs::exists(path)? {
+            bail!("File not found: {}", path.display());
+        }
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let parsed = helpers::parse_from_path(
+        &args.file,
+        args.init.as_str(),
+        args.step.as_str(),
+        None,
+        None,
+        args.main.as_deref(),
+    )
+    .unwrap();
+    let trace: itf::Trace<itf::Value> = serde_json::from_reader(File::open(&args.trace)?)?;
+
+    let start = Instant::now();
+    log!("Checking", "Checking {}", args.trace.display());
+    let result = parsed.check_conformance(&trace)?;
+    let elapsed = start.elapsed();
+
+    let Some(failure) = result.failure else {
+        log!("Result", "{} states conform", result.conforming_states);
+        log!("Elapsed", "{elapsed:.2?}");
+        return Ok(());
+    };
+
+    log!("Result", "State {} does not conform", failure.index);
+    if let Some(action) = &failure.action {
+        log!("Result", "Recorded action: {action}");
+    }
+    if failure.closest.is_none() {
+        log!("Result", "No transition is enabled");
+    }
+    for difference in &failure.diff.differences {
+        log!("Diff", "{}", describe_difference(difference));
+    }
+
+    log!("Elapsed", "{elapsed:.2?}");
+    bail!(
+        "Trace does not conform after {} states",
+        result.conforming_states
+    )
+}
+
+/// A difference from the expected state to the produced one, starting with the
+/// variable it is in (e.g. `board.get(1): expected 0, got 1`).
+fn describe_difference(difference: &Difference) -> String {
+    let path = match difference.path.split_first() {
+        Some((PathSegment::Field(name), rest)) => render::quint_path(name, rest),
+        _ => render::quint_path("state", &difference.path),
+    };
+    match &difference.kind {
+        DifferenceKind::Changed { old, new } => {
+            format!(
+                "{path}: expected {}, got {}",
+                QuintSyntax(old),
+                QuintSyntax(new)
+            )
+        }
+        DifferenceKind::Added(new) => {
+            format!("{path}: expected (missing), got {}", QuintSyntax(new))
+        }
+        DifferenceKind::Removed(old) => {
+            format!("{path}: expected {}, got (missing)", QuintSyntax(old))
+        }
+        DifferenceKind::ElementAdded(elem) => {
+            format!("{path}: unexpected element {}", QuintSyntax(elem))
+        }
+        DifferenceKind::ElementRemoved(elem) => {
+            format!("{path}: missing element {}", QuintSyntax(elem))
+        }
+    }
+}
+
 /// Reads input from standard input (STDIN), parses it, and performs a simulation based on the parsed input.
 /// The result of the simulation is then printed in JSON format to standard output (STDOUT).
 fn simulate_from_stdin() -> eyre::Result<()> {
@@ -181,6 +862,7 @@ fn simulate_from_stdin() -> eyre::Result<()> {
     io::stdin().read_to_string(&mut input)?;
 
     let input: SimulateInput = serde_json::from_str(&input)?;
+    let config = input.config();
     let parsed = input.parsed;
 
     // Create a progress callback that writes progress to stderr in JSON format
@@ -194,15 +876,10 @@ fn simulate_from_stdin() -> eyre::Result<()> {
         eprintln!("{progress}");
     });
 
-    let result = parsed.simulate(
-        input.nsteps,
-        input.nruns,
-        input.ntraces,
-        Some(progress_callback),
-    );
+    let result = parsed.simulate_with(&config, Some(progress_callback));
 
     // Transform the SimulationResult into the Outcome format expected by Quint
-    let outcome = to_outcome(input.source, result);
+    let outcome = to_outcome(input.source, &parsed, result);
 
     // Serialize the outcome to JSON and print it to STDOUT
     println!("{}", serde_json::to_string(&outcome)?);
@@ -210,46 +887,8 @@ fn simulate_from_stdin() -> eyre::Result<()> {
     Ok(())
 }
 
-/// Converts the result of a simulation into an `Outcome` struct.
-///
-/// The status is determined based on whether the simulation result indicates success, violation, or error.
-/// Errors are collected into a vector if any are present.
-/// Best traces are converted to the intermediate trace format (ITF).
-fn to_outcome(source: String, result: Result<SimulationResult, QuintError>) -> Outcome {
-    let status = match &result {
-        Ok(r) if r.result => SimulationStatus::Success,
-        Ok(_) => SimulationStatus::Violation,
-        Err(_) => SimulationStatus::Error,
-    };
-
-    let errors = result
-        .as_ref()
-        .err()
-        .map_or_else(Vec::new, |e| vec![e.clone()]);
-
-    let best_traces = result.as_ref().ok().map_or_else(Vec::new, |r| {
-        r.best_traces
-            .iter()
-            .cloned()
-            .map(|t| SimulationTrace {
-                // TODO: Fetch seed from the random generator state
-                seed: 0,
-                states: t.clone().to_itf(source.clone()),
-                result: !t.violation,
-            })
-            .collect()
-    });
-
-    Outcome {
-        status,
-        errors,
-        best_traces,
-        trace_statistics: result
-            .as_ref()
-            .ok()
-            .map_or_else(TraceStatistics::default, |r| r.trace_statistics.clone()),
-        samples: result.as_ref().map_or(0, |r| r.samples),
-        // TODO: This simulator is not tracking witnesses yet
-        witnessing_traces: vec![],
-    }
+/// Serves JSON-RPC requests from the `quint` typescript tool until it exits.
+fn serve() -> eyre::Result<()> {
+    Server::new(io::BufReader::new(io::stdin()), io::stdout()).run()?;
+    Ok(())
 }
@@ -0,0 +1,202 @@
+//! Exhaustive explicit-state model checking for Quint models.
+//!
+//! While the simulator follows random paths, the model checker explores the
+//! whole state space in breadth-first order, using the same compiled `init`,
+//! `step` and `invariant`. Nondeterminism is enumerated with
+//! [`for_each_choice`] instead of being decided by the random number
+//! generator, so every `oneOf` pick and every `actionAny` branch is explored.
+//!
+//! Visited states are hashed to avoid exploring them more than once, and each
+//! state remembers its parent, so a violation can be reported as the shortest
+//! trace that reaches it.
+
+use crate::{
+    choices::for_each_choice,
+    evaluator::{CompiledExpr, Env, Interpreter},
+    ir::QuintError,
+    itf::Trace,
+    simulator::ParsedQuint,
+    value::Value,
+};
+use fxhash::FxHashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+
+/// Model checking output.
+pub struct ModelCheckResult {
+    /// Whether the invariant holds in all explored states
+    pub result: bool,
+    /// The shortest trace to a state violating the invariant, if any
+    pub counterexample: Option<Trace>,
+    pub statistics: ModelCheckStatistics,
+}
+
+/// Statistics about the explored state space.
+#[derive(Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCheckStatistics {
+    /// The number of distinct states found
+    pub distinct_states: usize,
+    /// The number of transitions taken, including the ones leading to
+    /// already visited states
+    pub transitions: usize,
+    /// The depth of the deepest state found, where initial states have depth 0
+    pub max_depth: usize,
+    /// Whether the whole state space was explored, that is, the depth bound
+    /// was never reached
+    pub complete: bool,
+}
+
+/// A visited state, with the index of its parent in the list of visited states.
+struct VisitedState {
+    state: Value,
+    parent: Option<usize>,
+    depth: usize,
+}
+
+impl ParsedQuint {
+    /// Explore all states reachable from `init` through `step`, checking that
+    /// the `invariant` and the named `invariants` hold in each of them.
+    ///
+    /// States are not expanded beyond `max_depth` steps, if given. Otherwise,
+    /// exploration only stops when there are no new states, or when a
+    /// violation is found.
+    pub fn check(&self, max_depth: Option<usize>) -> Result<ModelCheckResult, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        let mut env = Env::new(interpreter.var_storage.clone());
+
+        let init = interpreter.compile(&self.init);
+        let step = interpreter.compile(&self.step);
+        let invariant = self.compile_invariants(&mut interpreter);
+
+        let mut checker = Checker {
+            env: &mut env,
+            invariant,
+            visited: Vec::new(),
+            seen: FxHashMap::default(),
+            queue: VecDeque::new(),
+            statistics: ModelCheckStatistics {
+                complete: true,
+                ..Default::default()
+            },
+        };
+
+        let violation = checker.explore_from(&init, None)?;
+        if let Some(index) = violation {
+            return Ok(checker.violation(index));
+        }
+
+        while let Some(index) = checker.queue.pop_front() {
+            if max_depth.is_some_and(|max| checker.visited[index].depth >= max) {
+                checker.statistics.complete = false;
+                continue;
+            }
+
+            if let Some(violation) = checker.explore_from(&step, Some(index))? {
+                return Ok(checker.violation(violation));
+            }
+        }
+
+        Ok(ModelCheckResult {
+            result: true,
+            counterexample: None,
+            statistics: checker.statistics,
+        })
+    }
+}
+
+/// The state of an ongoing breadth-first exploration.
+struct Checker<'a> {
+    env: &'a mut Env,
+    invariant: CompiledExpr,
+    visited: Vec<VisitedState>,
+    seen: FxHashMap<Value, usize>,
+    queue: VecDeque<usize>,
+    statistics: ModelCheckStatistics,
+}
+
+impl Checker<'_> {
+    /// Evaluate `action` from the visited state at `parent` (or from the empty
+    /// state, for `init`) with every possible sequence of choices, and visit
+    /// the resulting states. Returns the index of the first state violating the
+    /// invariant, if any.
+    fn explore_from(
+        &mut self,
+        action: &CompiledExpr,
+        parent: Option<usize>,
+    ) -> Result<Option<usize>, QuintError> {
+        let from = parent.map(|index| self.visited[index].state.clone());
+        let depth = parent.map_or(0, |index| self.visited[index].depth + 1);
+        let mut successors = Vec::new();
+
+        for_each_choice(self.env, |env| {
+            match &from {
+                Some(state) => env.var_storage.borrow_mut().set_vars_from_record(state),
+                None => env.var_storage.borrow_mut().clear_next_vars(),
+            }
+
+            if action.execute(env)?.as_bool() {
+                env.shift();
+                successors.push(env.var_storage.borrow().as_record());
+            }
+            Ok(ControlFlow::Continue(()))
+        })?;
+
+        for state in successors {
+            self.statistics.transitions += 1;
+            if self.seen.contains_key(&state) {
+                continue;
+            }
+
+            let index = self.visited.len();
+            self.seen.insert(state.clone(), index);
+            self.visited.push(VisitedState {
+                state: state.clone(),
+                parent,
+                depth,
+            });
+            self.statistics.distinct_states += 1;
+            self.statistics.max_depth = self.statistics.max_depth.max(depth);
+
+            self.env
+                .var_storage
+                .borrow_mut()
+                .set_vars_from_record(&state);
+            if !self.invariant.execute(self.env)?.as_bool() {
+                return Ok(Some(index));
+            }
+
+            self.queue.push_back(index);
+        }
+
+        Ok(None)
+    }
+
+    /// Build the result for a violation found at the visited state `index`,
+    /// following the parents back to an initial state.
+    fn violation(self, index: usize) -> ModelCheckResult {
+        let mut states = Vec::new();
+        let mut current = Some(index);
+        while let Some(i) = current {
+            states.push(self.visited[i].state.clone());
+            current = self.visited[i].parent;
+        }
+        states.reverse();
+
+        ModelCheckResult {
+            result: false,
+            counterexample: Some(Trace {
+                states,
+                violation: true,
+                deadlock: false,
+                loop_index: None,
+                choices: Vec::new(),
+                seed: None,
+                sample: None,
+                violated_invariant: None,
+            }),
+            statistics: self.statistics,
+        }
+    }
+}
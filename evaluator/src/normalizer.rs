@@ -10,6 +10,9 @@ impl Value {
     pub fn normalize(self) -> Value {
         match self {
             Value::Int(_) | Value::Bool(_) | Value::Str(_) => self,
+            // Infinite sets can't be enumerated, and are already in a canonical form
+            Value::IntSet | Value::NatSet | Value::AllLists(_) => self,
+            _ if self.is_infinite() => self,
             Value::Set(_)
             | Value::Interval(_, _)
             | Value::CrossProduct(_)
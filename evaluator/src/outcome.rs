@@ -0,0 +1,141 @@
+//! The results of simulations in the JSON format read by the `quint`
+//! typescript tool, written by the `simulate-from-stdin` command and the
+//! JSON-RPC [`server`](crate::server).
+
+use crate::coverage::CoverageReport;
+use crate::ir::QuintError;
+use crate::itf::{ItfContext, Trace};
+use crate::simulator::{ParsedQuint, SimulationResult, TraceStatistics};
+use crate::types::var_types;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulationStatus {
+    #[serde(rename = "ok")]
+    Success,
+    Violation,
+    Deadlock,
+    Error,
+}
+
+/// The result of a simulation, in the format expected by the `quint` typescript
+/// tool
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub status: SimulationStatus,
+    pub errors: Vec<QuintError>,
+    pub best_traces: Vec<SimulationTrace>,
+    pub trace_statistics: TraceStatistics,
+    pub witnessing_traces: Vec<usize>,
+    pub samples: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<CoverageReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub invariants: Vec<InvariantOutcome>,
+}
+
+/// The status of one of the named invariants
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantOutcome {
+    pub name: String,
+    pub status: SimulationStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counterexample: Option<SimulationTrace>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationTrace {
+    pub seed: usize,
+    pub states: itf::Trace<itf::Value>,
+    pub result: bool,
+}
+
+/// Converts the result of a simulation into an `Outcome` struct.
+///
+/// The status is determined based on whether the simulation result indicates success, violation, deadlock, or error.
+/// Errors are collected into a vector if any are present.
+/// Best traces are converted to the intermediate trace format (ITF).
+pub fn to_outcome(
+    source: String,
+    parsed: &ParsedQuint,
+    result: Result<SimulationResult, QuintError>,
+) -> Outcome {
+    let status = match &result {
+        Ok(r) if r.result => SimulationStatus::Success,
+        Ok(r) if r.deadlock => SimulationStatus::Deadlock,
+        Ok(_) => SimulationStatus::Violation,
+        Err(_) => SimulationStatus::Error,
+    };
+
+    let mut errors = result
+        .as_ref()
+        .err()
+        .map_or_else(Vec::new, |e| vec![e.clone()]);
+
+    let context = ItfContext {
+        source,
+        var_types: var_types(&parsed.table),
+        params: result
+            .as_ref()
+            .map_or_else(|_| Vec::new(), |r| r.params.clone()),
+    };
+    // Traces that cannot be written are reported as errors
+    let mut to_simulation_trace = |t: Trace| {
+        let seed = t.seed.unwrap_or(0) as usize;
+        let result = !t.violation;
+        t.to_itf(&context)
+            .map(|states| SimulationTrace {
+                seed,
+                result,
+                states,
+            })
+            .map_err(|e| errors.push(e))
+            .ok()
+    };
+
+    let best_traces = result.as_ref().ok().map_or_else(Vec::new, |r| {
+        r.best_traces
+            .iter()
+            .cloned()
+            .filter_map(&mut to_simulation_trace)
+            .collect()
+    });
+
+    let invariants = result.as_ref().ok().map_or_else(Vec::new, |r| {
+        r.invariants
+            .iter()
+            .map(|inv| InvariantOutcome {
+                name: inv.name.clone(),
+                status: if inv.violated() {
+                    SimulationStatus::Violation
+                } else {
+                    SimulationStatus::Success
+                },
+                counterexample: inv
+                    .counterexample
+                    .clone()
+                    .and_then(&mut to_simulation_trace),
+            })
+            .collect()
+    });
+
+    Outcome {
+        status,
+        errors,
+        best_traces,
+        trace_statistics: result
+            .as_ref()
+            .ok()
+            .map_or_else(TraceStatistics::default, |r| r.trace_statistics.clone()),
+        samples: result.as_ref().map_or(0, |r| r.samples),
+        witnessing_traces: result
+            .as_ref()
+            .map_or_else(|_| Vec::new(), |r| r.witnessing_traces.clone()),
+        coverage: result.as_ref().ok().and_then(|r| r.coverage.clone()),
+        invariants,
+    }
+}
@@ -1,8 +1,41 @@
 //! Picking values out of sets without enumerating the elements.
 
-use crate::value::{powerset_at_index, ImmutableMap, Value};
+use crate::evaluator::Env;
+use crate::ir::QuintError;
+use crate::value::{powerset_at_index, ImmutableMap, ImmutableSet, ImmutableVec, Value};
 use std::convert::TryInto;
 
+/// Bounds used to sample elements from infinite sets, as we can't pick from
+/// all of their elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamplingBounds {
+    /// The smallest integer picked from `Int`
+    pub min_int: i64,
+    /// The largest integer picked from `Int` and `Nat`
+    pub max_int: i64,
+    /// The maximum length of lists picked from `allLists(S)`, and the maximum
+    /// size of sets picked from `powerset(S)` when `S` is infinite
+    pub max_size: usize,
+}
+
+impl Default for SamplingBounds {
+    fn default() -> Self {
+        Self {
+            min_int: -(1 << 31),
+            max_int: (1 << 31) - 1,
+            max_size: 10,
+        }
+    }
+}
+
+impl SamplingBounds {
+    /// Check that there are integers to pick from `Int`, and that the bounds
+    /// can be used for sampling.
+    pub fn validate(&self) -> Result<(), QuintError> {
+        int_choices(self.min_int, self.max_int).map(|_| ())
+    }
+}
+
 impl Value {
     /// Pick a value from the set, using the given indexes, without enumerating
     /// the elements (and thus avoiding expensive computations).
@@ -58,6 +91,88 @@ impl Value {
         }
     }
 
+    /// Pick a value from the set, making the necessary nondeterministic
+    /// choices with the environment.
+    ///
+    /// Infinite sets are sampled within the environment's [`SamplingBounds`],
+    /// since the [`bounds`](Value::bounds) of a choice have to be finite. This
+    /// also means that we can't go through all choices for them, so they are
+    /// rejected when choices are being enumerated.
+    pub fn pick_with(&self, env: &mut Env) -> Result<Value, QuintError> {
+        if env.is_exhaustive() && self.is_infinite() {
+            return Err(QuintError::new(
+                "QNT501",
+                &format!("Cannot enumerate all choices from the infinite set {self}"),
+            ));
+        }
+
+        match self {
+            Value::IntSet => pick_int(env, env.sampling.min_int, env.sampling.max_int),
+            Value::NatSet if env.sampling.max_int < 0 => Err(QuintError::new(
+                "QNT522",
+                &format!(
+                    "Cannot pick from Nat, as the largest integer to pick ({}) is negative",
+                    env.sampling.max_int
+                ),
+            )),
+            Value::NatSet => pick_int(env, env.sampling.min_int.max(0), env.sampling.max_int),
+            Value::AllLists(set) => {
+                let max_length = if set.is_infinite() || set.cardinality() > 0 {
+                    env.sampling.max_size
+                } else {
+                    0
+                };
+                let length = env.next_choice(max_length + 1);
+                let elems = (0..length)
+                    .map(|_| set.pick_with(env))
+                    .collect::<Result<ImmutableVec<_>, _>>()?;
+                Ok(Value::List(elems))
+            }
+            Value::PowerSet(base) if base.is_infinite() => {
+                let size = env.next_choice(env.sampling.max_size + 1);
+                let elems = (0..size)
+                    .map(|_| base.pick_with(env))
+                    .collect::<Result<ImmutableSet<_>, _>>()?;
+                Ok(Value::Set(elems))
+            }
+            Value::CrossProduct(sets) if self.is_infinite() => {
+                let elems = sets
+                    .iter()
+                    .map(|set| set.pick_with(env))
+                    .collect::<Result<ImmutableVec<_>, _>>()?;
+                Ok(Value::Tuple(elems))
+            }
+            Value::MapSet(domain, _) if domain.is_infinite() => Err(QuintError::new(
+                "QNT501",
+                &format!("Cannot pick a map with an infinite domain from {self}"),
+            )),
+            Value::MapSet(domain, range) if range.is_infinite() => {
+                let pairs = domain
+                    .as_set()
+                    .iter()
+                    .map(|key| Ok((key.clone(), range.pick_with(env)?)))
+                    .collect::<Result<ImmutableMap<_, _>, QuintError>>()?;
+                Ok(Value::Map(pairs))
+            }
+            _ => {
+                // The ranges in which to generate each choice
+                let bounds = self.bounds();
+                // The choice made for each bound
+                let mut positions = Vec::with_capacity(bounds.len());
+
+                for bound in bounds {
+                    if bound == 0 {
+                        return Err(QuintError::new("QNT509", "Applied oneOf on an empty set"));
+                    }
+
+                    positions.push(env.next_choice(bound))
+                }
+
+                Ok(self.pick(&mut positions.into_iter()))
+            }
+        }
+    }
+
     // Some sets require multiple random numbers in order to pick an element efficiently.
     // For example, a cross product will require one random number per set, and return a tuple like
     // (set1.pick(r1), set2.pick(r2), ..., setn.pick(rn)). The `bounds` function will return the list of
@@ -76,3 +191,55 @@ impl Value {
         }
     }
 }
+
+/// Pick an integer in `[min, max]`, where smaller choices give integers
+/// closer to zero (`0, 1, -1, 2, -2, ...`). This way, lowering a recorded
+/// choice gives a simpler value, which is what shrinking relies on.
+fn pick_int(env: &mut Env, min: i64, max: i64) -> Result<Value, QuintError> {
+    let index = env.next_choice(int_choices(min, max)?) as i128;
+    // Computed in a wider type, so the bounds can be anywhere in `i64`
+    let (min, max) = (i128::from(min), i128::from(max));
+    let value = if min > 0 {
+        min + index
+    } else if max < 0 {
+        max - index
+    } else {
+        // Alternate between both signs while both sides have integers left, and
+        // continue on the longer side after that
+        let paired = (-min).min(max);
+        if index <= 2 * paired {
+            let magnitude = (index + 1) / 2;
+            if index % 2 == 1 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        } else {
+            let magnitude = index - paired;
+            if max > -min {
+                magnitude
+            } else {
+                -magnitude
+            }
+        }
+    };
+    // The value is within `[min, max]`
+    Ok(Value::Int(value as i64))
+}
+
+/// The number of integers in `[min, max]`, which must not be empty and must
+/// fit in the choices of the random generator.
+fn int_choices(min: i64, max: i64) -> Result<usize, QuintError> {
+    if min > max {
+        return Err(QuintError::new(
+            "QNT522",
+            &format!("Cannot pick an integer between {min} and {max}: the range is empty"),
+        ));
+    }
+    usize::try_from(i128::from(max) - i128::from(min) + 1).map_err(|_| {
+        QuintError::new(
+            "QNT522",
+            &format!("Cannot pick an integer between {min} and {max}: the range is too large"),
+        )
+    })
+}
@@ -0,0 +1,261 @@
+//! Profiling of evaluation time per definition.
+//!
+//! When profiling is enabled in the [`Interpreter`](crate::evaluator::Interpreter),
+//! the body of each top-level definition (and, optionally, each built-in
+//! operator) is wrapped to measure its wall time. Calls are tracked in a stack,
+//! so we know both the inclusive time of a definition (including the
+//! definitions it calls) and its exclusive time (without them). Time spent in
+//! recursive calls is only counted once in the inclusive time.
+//!
+//! Definitions that are cached (see `Cache` in the evaluator) are only
+//! evaluated on cache misses, so their cache hits and misses are counted too.
+//!
+//! The exclusive time of each stack of calls is also recorded, so the whole
+//! profile can be exported in the folded-stack format read by flamegraph tools
+//! (see [`ProfileReport::folded_stacks`]).
+
+use crate::evaluator::{CompiledExpr, CompiledExprWithArgs};
+use fxhash::FxHashMap;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// What to profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profiling {
+    #[default]
+    Disabled,
+    /// Profile top-level definitions
+    Definitions,
+    /// Profile top-level definitions and every built-in operator
+    Builtins,
+}
+
+/// Which cache a definition uses, if any.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheKind {
+    /// Cached until the state changes
+    ForState,
+    /// Cached forever
+    Forever,
+}
+
+/// Cache hits and misses for a cached definition.
+pub struct CacheCounter {
+    kind: CacheKind,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+impl CacheCounter {
+    pub fn hit(&self) {
+        self.hits.set(self.hits.get() + 1);
+    }
+
+    pub fn miss(&self) {
+        self.misses.set(self.misses.get() + 1);
+    }
+}
+
+struct Frame {
+    name: Rc<str>,
+    path: String,
+    start: Instant,
+    children: Duration,
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: usize,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+#[derive(Default)]
+struct ProfileData {
+    stack: Vec<Frame>,
+    stats: FxHashMap<Rc<str>, Stats>,
+    folded: FxHashMap<String, Duration>,
+    caches: FxHashMap<Rc<str>, Rc<CacheCounter>>,
+}
+
+/// The measurements taken during evaluation. Clones share the measurements.
+#[derive(Clone, Default)]
+pub struct Profiler {
+    data: Rc<RefCell<ProfileData>>,
+}
+
+impl Profiler {
+    /// Measure every evaluation of `expr` under the given name.
+    pub fn wrap(&self, name: &str, expr: CompiledExpr) -> CompiledExpr {
+        let profiler = self.clone();
+        let name: Rc<str> = name.into();
+        CompiledExpr::new(move |env| {
+            profiler.enter(&name);
+            let result = expr.execute(env);
+            profiler.exit();
+            result
+        })
+    }
+
+    /// Measure every application of `op` under the given name.
+    pub fn wrap_with_args(&self, name: &str, op: CompiledExprWithArgs) -> CompiledExprWithArgs {
+        let profiler = self.clone();
+        let name: Rc<str> = name.into();
+        CompiledExprWithArgs::new(move |env, args| {
+            profiler.enter(&name);
+            let result = op.execute(env, args);
+            profiler.exit();
+            result
+        })
+    }
+
+    /// The cache counter for the definition with the given name.
+    pub fn cache_counter(&self, name: &str, kind: CacheKind) -> Rc<CacheCounter> {
+        self.data
+            .borrow_mut()
+            .caches
+            .entry(name.into())
+            .or_insert_with(|| {
+                Rc::new(CacheCounter {
+                    kind,
+                    hits: Cell::new(0),
+                    misses: Cell::new(0),
+                })
+            })
+            .clone()
+    }
+
+    fn enter(&self, name: &Rc<str>) {
+        let mut data = self.data.borrow_mut();
+        let path = match data.stack.last() {
+            Some(parent) => format!("{};{name}", parent.path),
+            None => name.to_string(),
+        };
+        data.stack.push(Frame {
+            name: name.clone(),
+            path,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn exit(&self) {
+        let mut data = self.data.borrow_mut();
+        let frame = data.stack.pop().expect("exit without a matching enter");
+        let elapsed = frame.start.elapsed();
+        let exclusive = elapsed.saturating_sub(frame.children);
+
+        let recursive = data.stack.iter().any(|f| f.name == frame.name);
+        if let Some(parent) = data.stack.last_mut() {
+            parent.children += elapsed;
+        }
+
+        let stats = data.stats.entry(frame.name).or_default();
+        stats.calls += 1;
+        stats.exclusive += exclusive;
+        if !recursive {
+            stats.inclusive += elapsed;
+        }
+        *data.folded.entry(frame.path).or_default() += exclusive;
+    }
+
+    /// A snapshot of the measurements so far.
+    pub fn report(&self) -> ProfileReport {
+        let data = self.data.borrow();
+
+        let mut names = data.stats.keys().collect::<Vec<_>>();
+        names.extend(data.caches.keys().filter(|n| !data.stats.contains_key(*n)));
+
+        let mut entries = names
+            .into_iter()
+            .map(|name| {
+                let stats = data.stats.get(name);
+                let cache = data.caches.get(name);
+                ProfileEntry {
+                    name: name.to_string(),
+                    calls: stats.map_or(0, |s| s.calls),
+                    inclusive_ns: stats.map_or(0, |s| s.inclusive.as_nanos()),
+                    exclusive_ns: stats.map_or(0, |s| s.exclusive.as_nanos()),
+                    cache: cache.map(|c| c.kind),
+                    cache_hits: cache.map_or(0, |c| c.hits.get()),
+                    cache_misses: cache.map_or(0, |c| c.misses.get()),
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| {
+            b.exclusive_ns
+                .cmp(&a.exclusive_ns)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut folded = data
+            .folded
+            .iter()
+            .map(|(path, time)| (path.clone(), time.as_nanos()))
+            .collect::<Vec<_>>();
+        folded.sort();
+
+        ProfileReport { entries, folded }
+    }
+}
+
+/// The measurements for a definition or built-in operator, with times in
+/// nanoseconds.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileEntry {
+    pub name: String,
+    /// How many times it was evaluated (not counting cache hits)
+    pub calls: usize,
+    pub inclusive_ns: u128,
+    pub exclusive_ns: u128,
+    /// The cache used for the definition, if any
+    pub cache: Option<CacheKind>,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+}
+
+impl ProfileEntry {
+    /// The fraction of cache lookups that were hits, if it is cached.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+}
+
+/// The profile of an evaluation, with the most expensive entries (by exclusive
+/// time) first.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileReport {
+    pub entries: Vec<ProfileEntry>,
+    /// The exclusive time of each stack of calls, with names separated by `;`
+    pub folded: Vec<(String, u128)>,
+}
+
+impl ProfileReport {
+    /// The profile in the folded-stack format, with one stack per line
+    /// followed by its exclusive time in nanoseconds. This is the input format
+    /// of `flamegraph.pl` and `inferno-flamegraph`.
+    pub fn folded_stacks(&self) -> String {
+        let mut output = String::new();
+        for (path, time) in &self.folded {
+            writeln!(output, "{path} {time}").expect("writing to a string cannot fail");
+        }
+        output
+    }
+
+    /// The overall hits and misses for definitions using the given cache.
+    pub fn cache_totals(&self, kind: CacheKind) -> (usize, usize) {
+        self.entries
+            .iter()
+            .filter(|e| e.cache == Some(kind))
+            .fold((0, 0), |(hits, misses), e| {
+                (hits + e.cache_hits, misses + e.cache_misses)
+            })
+    }
+}
@@ -49,3 +49,13 @@ impl Rand {
         self.counter
     }
 }
+
+/// Parse a seed given by the user, either in decimal or in hexadecimal with a
+/// `0x` prefix (as seeds are shown in traces).
+pub fn parse_seed(value: &str) -> Result<u64, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|e| format!("invalid seed {value}: {e}"))
+}
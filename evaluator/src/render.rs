@@ -0,0 +1,180 @@
+//! Rendering of traces in the terminal, with values in Quint syntax.
+//!
+//! The first state is shown in full. Every following state only shows what
+//! changed since the previous one (see [`Value::diff`]), down to the innermost
+//! record field, map key or list index that differs (e.g.
+//! `balances.get("alice")`). Sets show the elements added (`+`) and removed
+//! (`-`).
+
+use crate::itf::Trace;
+use crate::value::{Difference, DifferenceKind, PathSegment, Value};
+use colored::Colorize;
+use itertools::Itertools;
+use std::fmt;
+
+/// A value written in Quint syntax. Elements of sets and maps, and the fields
+/// of records, are sorted by their text, so the output is stable.
+pub struct QuintSyntax<'a>(pub &'a Value);
+
+impl fmt::Display for QuintSyntax<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            value @ (Value::Set(_)
+            | Value::Interval(_, _)
+            | Value::CrossProduct(_)
+            | Value::PowerSet(_)
+            | Value::MapSet(_, _))
+                if !value.is_infinite() =>
+            {
+                let elems = value
+                    .as_set()
+                    .iter()
+                    .map(|v| QuintSyntax(v).to_string())
+                    .sorted();
+                write!(f, "Set({})", elems.format(", "))
+            }
+            Value::Tuple(elems) => write!(f, "({})", elems.iter().map(QuintSyntax).format(", ")),
+            Value::List(elems) => write!(f, "[{}]", elems.iter().map(QuintSyntax).format(", ")),
+            Value::Record(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Value::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .sorted_by_key(|(name, _)| name.to_string())
+                    .map(|(name, value)| format!("{name}: {}", QuintSyntax(value)));
+                write!(f, "{{ {} }}", fields.format(", "))
+            }
+            Value::Map(map) => {
+                let pairs = map
+                    .iter()
+                    .map(|(key, value)| format!("{} -> {}", QuintSyntax(key), QuintSyntax(value)))
+                    .sorted();
+                write!(f, "Map({})", pairs.format(", "))
+            }
+            Value::Variant(label, value) => match &**value {
+                Value::Tuple(elems) if elems.is_empty() => write!(f, "{label}"),
+                value => write!(f, "{label}({})", QuintSyntax(value)),
+            },
+            // Scalars, infinite sets and lambdas are written as usual
+            value => write!(f, "{value}"),
+        }
+    }
+}
+
+/// The Quint expression accessing the part of a value at `path`, starting
+/// from `root` (e.g. `balances.get("alice")`).
+pub fn quint_path(root: &str, path: &[PathSegment]) -> String {
+    let mut expr = root.to_string();
+    for segment in path {
+        match segment {
+            PathSegment::Field(name) => expr.push_str(&format!(".{name}")),
+            PathSegment::Key(key) => expr.push_str(&format!(".get({})", QuintSyntax(key))),
+            PathSegment::Index(i) => expr.push_str(&format!("[{i}]")),
+            PathSegment::Element(i) => expr.push_str(&format!("._{}", i + 1)),
+        }
+    }
+    expr
+}
+
+/// Render a trace for the terminal (see the module documentation). The action
+/// taken to reach each state is shown if the trace has metadata for
+/// model-based testing.
+pub fn render_trace(trace: &Trace) -> String {
+    let mut output = String::new();
+    let mut previous: Option<&Value> = None;
+    for (i, state) in trace.states.iter().enumerate() {
+        let fields = state.as_record_map();
+        output.push_str(&format!("State {i}").bold().to_string());
+        if let Some(Value::Str(action)) = fields.get("mbt::actionTaken") {
+            if !action.is_empty() {
+                output.push_str(&format!(" ({action})"));
+            }
+        }
+        output.push('\n');
+
+        let names = fields
+            .keys()
+            .chain(previous.iter().flat_map(|p| p.as_record_map().keys()))
+            .filter(|name| !name.starts_with("mbt::"))
+            .unique()
+            .sorted();
+        let mut lines = Vec::new();
+        for name in names {
+            let new = fields.get(name);
+            match previous {
+                None => {
+                    if let Some(value) = new {
+                        lines.push(format!("{name} = {}", QuintSyntax(value)));
+                    }
+                }
+                Some(previous) => {
+                    // Variables missing from a state are added or removed as a whole
+                    let whole = |kind| Difference {
+                        path: Vec::new(),
+                        kind,
+                    };
+                    let differences = match (previous.as_record_map().get(name), new) {
+                        (Some(old), Some(new)) => old.diff(new).differences,
+                        (None, Some(new)) => vec![whole(DifferenceKind::Added(new.clone()))],
+                        (Some(old), None) => vec![whole(DifferenceKind::Removed(old.clone()))],
+                        (None, None) => Vec::new(),
+                    };
+                    lines.extend(differences.iter().map(|d| render_difference(name, d)));
+                }
+            }
+        }
+        if lines.is_empty() {
+            lines.push("(no changes)".dimmed().to_string());
+        }
+        for line in lines {
+            output.push_str(&format!("  {line}\n"));
+        }
+
+        previous = Some(state);
+    }
+
+    if let Some(index) = trace.loop_index {
+        let line = format!("Loops back to state {index}");
+        output.push_str(&format!("{}\n", line.bold()));
+    }
+    if trace.deadlock {
+        output.push_str(&format!("{}\n", "Deadlock reached".red()));
+    } else if let Some(invariant) = &trace.violated_invariant {
+        let line = format!("Invariant {invariant} violated");
+        output.push_str(&format!("{}\n", line.red()));
+    }
+    output
+}
+
+fn render_difference(name: &str, difference: &Difference) -> String {
+    let path = quint_path(name, &difference.path).yellow();
+    match &difference.kind {
+        DifferenceKind::Changed { old, new } => format!(
+            "{path} = {} {}",
+            QuintSyntax(new).to_string().green(),
+            format!("(was {})", QuintSyntax(old)).dimmed()
+        ),
+        DifferenceKind::Added(new) => format!(
+            "{path} = {} {}",
+            QuintSyntax(new).to_string().green(),
+            "(added)".dimmed()
+        ),
+        DifferenceKind::Removed(old) => format!(
+            "{path} {}",
+            format!("removed (was {})", QuintSyntax(old)).dimmed()
+        ),
+        DifferenceKind::ElementAdded(elem) => {
+            format!(
+                "{path} {} {}",
+                "+".green(),
+                QuintSyntax(elem).to_string().green()
+            )
+        }
+        DifferenceKind::ElementRemoved(elem) => {
+            format!(
+                "{path} {} {}",
+                "-".red(),
+                QuintSyntax(elem).to_string().red()
+            )
+        }
+    }
+}
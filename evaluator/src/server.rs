@@ -0,0 +1,395 @@
+//! A long-lived JSON-RPC server for the `quint` typescript tool, started by
+//! the `serve` command.
+//!
+//! Instead of spawning a process for each simulation, the tool starts the
+//! server once, sends the output of `quint compile` (as `compiled`) and the
+//! name of the `source` file with a `load` request, and then any number of
+//! these requests about it:
+//!  - `simulate`: the same input as `simulate-from-stdin`, without the lookup
+//!    table, answered with an [`Outcome`](crate::outcome::Outcome);
+//!  - `evaluate`: an expression to evaluate, answered with its value;
+//!  - `test`: which `run` definitions of the main module to run as tests, and
+//!    how (see [`TestParams`]), answered with their results.
+//!
+//! While handling a request, the server sends `progress` notifications about
+//! it (see [`Progress`]). A `$/cancelRequest` notification stops a request,
+//! even an in-flight simulation (before its next step), and the request
+//! fails with the `RequestCancelled` (-32800) error code.
+//!
+//! Messages are framed as in the Language Server Protocol (see the
+//! [`transport`](crate::transport) module). The server stops on the `exit`
+//! notification or at the end of the input.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+
+use crate::evaluator;
+use crate::ir::{QuintEx, QuintOutput};
+use crate::itf::ItfContext;
+use crate::outcome::to_outcome;
+use crate::rand::Rand;
+use crate::render::QuintSyntax;
+use crate::simulator::{
+    CancellationToken, NamedInvariant, ParsedQuint, ProgressUpdate, SimulationConfig, StopCondition,
+};
+use crate::tester;
+use crate::transport::{read_message, write_message};
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const NOT_LOADED: i64 = -32002;
+const REQUEST_CANCELLED: i64 = -32800;
+
+type RequestResult = Result<Json, (i64, String)>;
+
+/// The cancellation tokens of the requests received and not answered yet, by
+/// their ids
+type Pending = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
+/// The params of a `simulate` request
+#[derive(Deserialize)]
+pub struct SimulateParams {
+    pub init: QuintEx,
+    pub step: QuintEx,
+    pub invariant: QuintEx,
+    #[serde(default)]
+    pub invariants: Vec<NamedInvariant>,
+    #[serde(default)]
+    pub temporal: Option<QuintEx>,
+    pub nruns: usize,
+    pub nsteps: usize,
+    pub ntraces: usize,
+    #[serde(default)]
+    pub mbt: bool,
+    #[serde(default)]
+    pub deadlock: bool,
+    #[serde(default)]
+    pub shrink: bool,
+    #[serde(default)]
+    pub coverage: bool,
+    #[serde(default)]
+    pub find_all_violations: bool,
+    /// Expressions to count the runs they hold in
+    #[serde(default)]
+    pub witnesses: Vec<QuintEx>,
+    /// The seed for the first run (default: random)
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SimulateParams {
+    pub fn config(&self) -> SimulationConfig {
+        SimulationConfig {
+            steps: self.nsteps,
+            samples: self.nruns,
+            traces: self.ntraces,
+            seed: self.seed,
+            witnesses: self.witnesses.clone(),
+            stop: StopCondition::from_find_all_violations(self.find_all_violations),
+            check_deadlocks: self.deadlock,
+            store_metadata: self.mbt,
+            collect_coverage: self.coverage,
+            shrink: self.shrink,
+            ..SimulationConfig::default()
+        }
+    }
+}
+
+/// The params of a `test` request
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestParams {
+    /// Only run the tests whose names match this regular expression
+    #[serde(rename = "match", default)]
+    pub filter: Option<String>,
+    #[serde(default = "default_max_samples")]
+    pub max_samples: usize,
+    /// The seed for the first sample of each test (default: random)
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_max_samples() -> usize {
+    10_000
+}
+
+/// The params of a `progress` notification, besides the id of the `request`
+/// it is about.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Progress {
+    /// A simulation reached a new percentage of its samples
+    Simulation {
+        current: usize,
+        total: usize,
+        percentage: u32,
+    },
+    /// A test finished
+    Test { name: String, passed: bool },
+}
+
+/// The specification all requests are about
+struct Spec {
+    source: String,
+    output: QuintOutput,
+}
+
+#[derive(Deserialize)]
+struct LoadParams {
+    source: String,
+    /// The output of `quint compile`
+    compiled: QuintOutput,
+}
+
+pub struct Server<W> {
+    messages: Receiver<io::Result<(Json, CancellationToken)>>,
+    output: Rc<RefCell<W>>,
+    spec: Option<Spec>,
+    pending: Pending,
+}
+
+impl<W: Write + 'static> Server<W> {
+    /// Start reading messages from `input`, in another thread, so requests
+    /// can be cancelled while others are handled.
+    pub fn new(input: impl BufRead + Send + 'static, output: W) -> Self {
+        let pending = Pending::default();
+        Self {
+            messages: read_messages(input, pending.clone()),
+            output: Rc::new(RefCell::new(output)),
+            spec: None,
+            pending,
+        }
+    }
+
+    /// Serve requests until the `exit` notification or the end of the input.
+    pub fn run(&mut self) -> io::Result<()> {
+        while let Ok(message) = self.messages.recv() {
+            let (mut message, token) = message?;
+            let method = message["method"].as_str().unwrap_or_default().to_string();
+            if method == "exit" {
+                break;
+            }
+            let Some(id) = message.get("id").cloned() else {
+                // Other notifications are ignored
+                continue;
+            };
+
+            let params = message["params"].take();
+            let result = if token.is_cancelled() {
+                Err(cancelled())
+            } else {
+                self.request(&method, params, &id, &token)
+            };
+            self.pending.lock().unwrap().remove(&id.to_string());
+            let result = if token.is_cancelled() {
+                Err(cancelled())
+            } else {
+                result
+            };
+
+            let response = match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err((code, message)) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": code, "message": message},
+                }),
+            };
+            write_message(&mut *self.output.borrow_mut(), &response)?;
+        }
+        Ok(())
+    }
+
+    fn request(
+        &mut self,
+        method: &str,
+        params: Json,
+        id: &Json,
+        token: &CancellationToken,
+    ) -> RequestResult {
+        match method {
+            "load" => {
+                let params: LoadParams = parse_params(params)?;
+                let tests = tester::find_runs(&params.compiled, None)
+                    .iter()
+                    .map(|def| def.name.to_string())
+                    .collect::<Vec<_>>();
+                let main = params.compiled.main.to_string();
+                self.spec = Some(Spec {
+                    source: params.source,
+                    output: params.compiled,
+                });
+                Ok(json!({"main": main, "tests": tests}))
+            }
+            "simulate" => self.simulate(parse_params(params)?, id, token),
+            "evaluate" => self.evaluate(parse_params(params)?),
+            "test" => self.test(parse_params(params)?, id, token),
+            "shutdown" => Ok(Json::Null),
+            _ => Err((METHOD_NOT_FOUND, format!("Unsupported request {method}"))),
+        }
+    }
+
+    fn spec(&self) -> Result<&Spec, (i64, String)> {
+        self.spec
+            .as_ref()
+            .ok_or_else(|| (NOT_LOADED, "No specification was loaded".to_string()))
+    }
+
+    fn simulate(
+        &self,
+        params: SimulateParams,
+        id: &Json,
+        token: &CancellationToken,
+    ) -> RequestResult {
+        let spec = self.spec()?;
+        let config = params.config().cancellation(token.clone());
+        let parsed = ParsedQuint {
+            init: params.init,
+            step: params.step,
+            invariant: params.invariant,
+            invariants: params.invariants,
+            temporal: params.temporal,
+            table: spec.output.table.clone(),
+        };
+
+        // Only report when the percentage changes, not on every sample
+        let output = self.output.clone();
+        let request = id.clone();
+        let mut reported = None;
+        let progress_callback = Box::new(move |update: ProgressUpdate| {
+            let percentage = update.percentage();
+            if reported.replace(percentage) != Some(percentage) {
+                let progress = Progress::Simulation {
+                    current: update.current,
+                    total: update.total,
+                    percentage,
+                };
+                // Progress is best-effort: a failed write shows up in the response
+                let _ = notify_progress(&mut *output.borrow_mut(), &request, progress);
+            }
+        });
+
+        let result = parsed.simulate_with(&config, Some(progress_callback));
+        let outcome = to_outcome(spec.source.clone(), &parsed, result);
+        serde_json::to_value(outcome).map_err(|e| (INTERNAL_ERROR, e.to_string()))
+    }
+
+    fn evaluate(&self, params: EvaluateParams) -> RequestResult {
+        let spec = self.spec()?;
+        Ok(match evaluator::run(&spec.output.table, &params.expr) {
+            Ok(value) => json!({
+                "status": "ok",
+                "value": value.to_itf(),
+                "text": QuintSyntax(&value).to_string(),
+            }),
+            Err(error) => json!({"status": "error", "errors": [error]}),
+        })
+    }
+
+    fn test(&self, params: TestParams, id: &Json, token: &CancellationToken) -> RequestResult {
+        let spec = self.spec()?;
+        let filter = params
+            .filter
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+        let seed = params.seed.unwrap_or_else(|| Rand::new().get_state());
+
+        let mut results = Vec::new();
+        for def in tester::find_runs(&spec.output, filter.as_ref()) {
+            if token.is_cancelled() {
+                return Err(cancelled());
+            }
+            let result = tester::run_test(&spec.output.table, def, params.max_samples, seed);
+            let progress = Progress::Test {
+                name: result.name.clone(),
+                passed: result.passed,
+            };
+            let _ = notify_progress(&mut *self.output.borrow_mut(), id, progress);
+
+            let context = ItfContext::from_source(spec.source.clone());
+            let trace = result.trace.and_then(|trace| trace.to_itf(&context).ok());
+            results.push(json!({
+                "name": result.name,
+                "status": if result.passed { "passed" } else { "failed" },
+                "seed": result.seed,
+                "samples": result.samples,
+                "errors": result.error.into_iter().collect::<Vec<_>>(),
+                "trace": trace,
+            }));
+        }
+        Ok(results.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct EvaluateParams {
+    expr: QuintEx,
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Json) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+fn cancelled() -> (i64, String) {
+    (REQUEST_CANCELLED, "The request was cancelled".to_string())
+}
+
+fn notify_progress(output: &mut impl Write, request: &Json, progress: Progress) -> io::Result<()> {
+    let mut params = serde_json::to_value(progress)?;
+    params["request"] = request.clone();
+    let notification = json!({"jsonrpc": "2.0", "method": "progress", "params": params});
+    write_message(output, &notification)
+}
+
+/// Read messages in a new thread, sending them through the returned channel
+/// with the cancellation tokens of requests. `$/cancelRequest` notifications
+/// are handled right away, cancelling pending requests.
+fn read_messages<R: BufRead + Send + 'static>(
+    mut input: R,
+    pending: Pending,
+) -> Receiver<io::Result<(Json, CancellationToken)>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                let _ = sender.send(Err(e));
+                break;
+            }
+        };
+
+        if message["method"] == "$/cancelRequest" {
+            let id = message["params"]["id"].to_string();
+            if let Some(token) = pending.lock().unwrap().get(&id) {
+                token.cancel();
+            }
+            continue;
+        }
+
+        let token = CancellationToken::default();
+        if let Some(id) = message.get("id") {
+            pending
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), token.clone());
+        }
+        if sender.send(Ok((message, token))).is_err() {
+            break;
+        }
+    });
+    receiver
+}
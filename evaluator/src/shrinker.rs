@@ -0,0 +1,176 @@
+//! Shrinking of counterexamples found by simulation.
+//!
+//! Random simulation tends to find long traces with arbitrary values, where
+//! most steps have nothing to do with the violation. Since every
+//! nondeterministic choice made in a run is recorded in the [`Trace`] (see
+//! [`Env::record_choices`]), the run can be replayed with modified choices
+//! using a [`ChoiceSequence`]. Shrinking greedily tries simpler choices and
+//! keeps the ones that still lead to a violation of the invariant:
+//!  - dropping a whole transition;
+//!  - lowering a single choice, which picks an earlier `actionAny` branch, an
+//!    earlier set element, a shorter list, or an integer closer to zero (see
+//!    [`Value::pick_with`](crate::value::Value::pick_with)).
+//!
+//! A candidate is only kept if it is strictly smaller than the current trace,
+//! first by number of states and then by the sum of its choices, so shrinking
+//! always terminates.
+
+use crate::{
+    choices::ChoiceSequence,
+    evaluator::{CompiledExpr, Env, Interpreter},
+    ir::QuintError,
+    itf::Trace,
+    picker::SamplingBounds,
+    simulator::ParsedQuint,
+    value::Value,
+};
+
+impl ParsedQuint {
+    /// Minimize a trace violating the invariants, by replaying its recorded
+    /// choices with simpler ones (see the module documentation). The same
+    /// `sampling` bounds used to find the trace should be given, so choices
+    /// are interpreted the same way.
+    ///
+    /// If the trace has no recorded choices, or replaying them does not lead
+    /// to a violation, it is returned unchanged.
+    pub fn shrink(&self, trace: &Trace, sampling: &SamplingBounds) -> Result<Trace, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        interpreter.var_storage.borrow_mut().store_metadata = trace
+            .states
+            .first()
+            .is_some_and(|state| state.as_record_map().contains_key("mbt::actionTaken"));
+        let mut env = Env::new(interpreter.var_storage.clone());
+        env.sampling = sampling.clone();
+
+        let mut shrinker = Shrinker {
+            init: interpreter.compile(&self.init),
+            step: interpreter.compile(&self.step),
+            invariant: self.compile_invariants(&mut interpreter),
+            env: &mut env,
+        };
+
+        if trace.choices.is_empty() {
+            return Ok(trace.clone());
+        }
+        let Some(mut best) = shrinker.replay(&trace.choices)? else {
+            return Ok(trace.clone());
+        };
+
+        while let Some(smaller) = shrinker.find_smaller(&best)? {
+            best = smaller;
+        }
+
+        // The shrunk trace comes from different choices, so it cannot be
+        // reproduced from the original seed
+        Ok(Trace {
+            violated_invariant: trace.violated_invariant.clone(),
+            ..best.into_trace()
+        })
+    }
+}
+
+/// A replayed run that violates the invariant in its last state.
+struct Candidate {
+    states: Vec<Value>,
+    choices: Vec<Vec<usize>>,
+}
+
+impl Candidate {
+    /// The measure shrinking minimizes.
+    fn size(&self) -> (usize, usize) {
+        let choices = self.choices.iter().flatten().sum();
+        (self.states.len(), choices)
+    }
+
+    fn into_trace(self) -> Trace {
+        Trace {
+            states: self.states,
+            violation: true,
+            deadlock: false,
+            loop_index: None,
+            choices: self.choices,
+            seed: None,
+            sample: None,
+            violated_invariant: None,
+        }
+    }
+}
+
+struct Shrinker<'a> {
+    env: &'a mut Env,
+    init: CompiledExpr,
+    step: CompiledExpr,
+    invariant: CompiledExpr,
+}
+
+impl Shrinker<'_> {
+    /// Try the simpler variations of `current`'s choices, returning the first
+    /// one that still violates the invariant and is smaller.
+    fn find_smaller(&mut self, current: &Candidate) -> Result<Option<Candidate>, QuintError> {
+        for choices in variations(&current.choices) {
+            if let Some(candidate) = self.replay(&choices)? {
+                if candidate.size() < current.size() {
+                    return Ok(Some(candidate));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Run `init` and then `step` with the given choices for each transition,
+    /// stopping at the first state violating the invariant. Returns `None` if
+    /// some transition is disabled or the invariant is never violated.
+    fn replay(&mut self, choices: &[Vec<usize>]) -> Result<Option<Candidate>, QuintError> {
+        self.env.var_storage.borrow_mut().clear_next_vars();
+
+        let mut states = Vec::with_capacity(choices.len());
+        let mut taken = Vec::with_capacity(choices.len());
+        for (i, transition) in choices.iter().enumerate() {
+            let action = if i == 0 { &self.init } else { &self.step };
+
+            self.env.choices = Some(ChoiceSequence::replay(transition.clone()));
+            let enabled = action.execute(self.env);
+            let sequence = self.env.choices.take().expect("choices were just set");
+            if !enabled?.as_bool() {
+                return Ok(None);
+            }
+
+            self.env.shift();
+            states.push(self.env.var_storage.borrow().as_record());
+            taken.push(sequence.taken().iter().map(|c| c.value).collect());
+
+            if !self.invariant.execute(self.env)?.as_bool() {
+                return Ok(Some(Candidate {
+                    states,
+                    choices: taken,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The simpler variations of a sequence of choices to try, in order: without
+/// each transition (other than `init`), and then with each choice lowered.
+fn variations(choices: &[Vec<usize>]) -> impl Iterator<Item = Vec<Vec<usize>>> + '_ {
+    let dropped = (1..choices.len()).map(move |i| {
+        let mut variation = choices.to_vec();
+        variation.remove(i);
+        variation
+    });
+
+    let lowered = choices.iter().enumerate().flat_map(move |(i, transition)| {
+        transition.iter().enumerate().flat_map(move |(j, &value)| {
+            let mut lower = vec![0, value / 2, value.saturating_sub(1)];
+            lower.dedup();
+            lower.into_iter().filter(move |&v| v < value).map(move |v| {
+                let mut variation = choices.to_vec();
+                variation[i][j] = v;
+                variation
+            })
+        })
+    });
+
+    dropped.chain(lowered)
+}
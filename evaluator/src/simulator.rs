@@ -1,11 +1,22 @@
 //! Simulation for Quint models.
 
 use crate::{
-    evaluator::{Env, Interpreter},
-    ir::{LookupTable, QuintError, QuintEx},
+    choices::for_each_choice,
+    coverage::CoverageReport,
+    evaluator::{CompiledExpr, Env, Interpreter},
+    ir::{LookupTable, QuintError, QuintEx, QuintName},
     itf::Trace,
+    picker::SamplingBounds,
+    profiler::{ProfileReport, Profiling},
+    temporal,
+    value::Value,
 };
+use fxhash::FxHashMap;
 use serde::{Deserialize, Serialize};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Simulation input that depends on the typescript Quint tool.
 #[derive(Serialize, Deserialize)]
@@ -13,17 +24,60 @@ pub struct ParsedQuint {
     pub init: QuintEx,
     pub step: QuintEx,
     pub invariant: QuintEx,
+    /// Invariants checked along with `invariant`, whose results are reported
+    /// separately (see [`InvariantResult`])
+    #[serde(default)]
+    pub invariants: Vec<NamedInvariant>,
+    /// A temporal property to check on each simulated trace, if any
+    #[serde(default)]
+    pub temporal: Option<QuintEx>,
     pub table: LookupTable,
 }
 
+/// An invariant with the name of its definition.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NamedInvariant {
+    pub name: String,
+    pub expr: QuintEx,
+}
+
+/// The status of one of the named invariants after simulation.
+#[derive(Clone)]
+pub struct InvariantResult {
+    pub name: String,
+    /// The first trace found violating the invariant, if any
+    pub counterexample: Option<Trace>,
+}
+
+impl InvariantResult {
+    pub fn violated(&self) -> bool {
+        self.counterexample.is_some()
+    }
+}
+
 /// Simulation output.
 pub struct SimulationResult {
     pub result: bool,
+    /// Whether the simulation stopped because it reached a deadlock, in which
+    /// case the deadlocked trace is among the `best_traces`
+    pub deadlock: bool,
     pub best_traces: Vec<Trace>,
+    /// The status of each of the named invariants, in the order they were given
+    pub invariants: Vec<InvariantResult>,
     pub trace_statistics: TraceStatistics,
     pub samples: usize,
-    // TODO
-    // witnessing_traces
+    /// Whether the simulation was cancelled, or ran out of time, before trying
+    /// all samples. Only the `samples` completed by then are reported on.
+    pub interrupted: bool,
+    /// How much of the specification was exercised, if requested
+    pub coverage: Option<CoverageReport>,
+    /// Where evaluation time was spent, if requested
+    pub profile: Option<ProfileReport>,
+    /// The values of the constants used in the simulation, sorted by name
+    pub params: Vec<(QuintName, Value)>,
+    /// For each of the witnesses, the number of runs in which it held in some
+    /// state
+    pub witnessing_traces: Vec<usize>,
 }
 
 /// Statistics about the length of traces collected during simulation.
@@ -55,37 +109,395 @@ impl ProgressUpdate {
 /// Callback type for reporting simulation progress
 pub type ProgressCallback = Box<dyn FnMut(ProgressUpdate)>;
 
+/// A flag to stop a simulation, which can be set from another thread, and an
+/// optional deadline after which the simulation stops too. Clones share the
+/// flag. Simulation checks it before each step.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Also stop at `deadline`, or at the current deadline if it is earlier
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(self.deadline.map_or(deadline, |d| d.min(deadline))),
+            ..self
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// When to stop simulating, besides running out of samples or time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StopCondition {
+    /// At the first violation of any invariant
+    #[default]
+    FirstViolation,
+    /// Once a counterexample is found for every named invariant. A violation
+    /// of one of them only stops the run it happened in.
+    AllViolations,
+}
+
+impl StopCondition {
+    /// The condition for the `find_all_violations` option of the `run`
+    /// command and the typescript tool
+    pub fn from_find_all_violations(find_all_violations: bool) -> Self {
+        if find_all_violations {
+            Self::AllViolations
+        } else {
+            Self::FirstViolation
+        }
+    }
+}
+
+/// How to simulate a model, for [`ParsedQuint::simulate_with`].
+///
+/// The defaults are those of the `run` command. Options can be set with the
+/// methods of the same name:
+///
+/// ```ignore
+/// let config = SimulationConfig::default().steps(20).samples(1000).seed(42);
+/// let result = parsed.simulate_with(&config, None)?;
+/// ```
+#[derive(Clone)]
+pub struct SimulationConfig {
+    /// The maximum number of steps in each run
+    pub steps: usize,
+    /// The number of runs to try
+    pub samples: usize,
+    /// How many of the best traces to keep
+    pub traces: usize,
+    /// The state of the random generator to start from (default: random)
+    pub seed: Option<u64>,
+    /// Named invariants to check besides the ones in the model
+    pub invariants: Vec<NamedInvariant>,
+    /// Expressions to count the runs they hold in, in some state (see
+    /// [`SimulationResult::witnessing_traces`])
+    pub witnesses: Vec<QuintEx>,
+    pub stop: StopCondition,
+    /// Whether to report a deadlock when `step` is disabled for every choice
+    /// of nondeterministic values
+    pub check_deadlocks: bool,
+    /// Whether to record the action taken and the nondet picks in each state
+    pub store_metadata: bool,
+    pub collect_coverage: bool,
+    pub profiling: Profiling,
+    pub sampling: SamplingBounds,
+    /// Whether to minimize the traces violating an invariant
+    pub shrink: bool,
+    /// How many threads to simulate in. Only one is supported for now, and
+    /// other values are rejected by [`ParsedQuint::simulate_with`].
+    pub threads: usize,
+    /// How long to simulate for, from the start of the simulation
+    pub max_duration: Option<Duration>,
+    pub cancellation: CancellationToken,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            steps: 10,
+            samples: 10_000,
+            traces: 1,
+            seed: None,
+            invariants: Vec::new(),
+            witnesses: Vec::new(),
+            stop: StopCondition::default(),
+            check_deadlocks: false,
+            store_metadata: false,
+            collect_coverage: false,
+            profiling: Profiling::Disabled,
+            sampling: SamplingBounds::default(),
+            shrink: false,
+            threads: 1,
+            max_duration: None,
+            cancellation: CancellationToken::default(),
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn steps(self, steps: usize) -> Self {
+        Self { steps, ..self }
+    }
+
+    pub fn samples(self, samples: usize) -> Self {
+        Self { samples, ..self }
+    }
+
+    pub fn traces(self, traces: usize) -> Self {
+        Self { traces, ..self }
+    }
+
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+
+    pub fn invariant(mut self, invariant: NamedInvariant) -> Self {
+        self.invariants.push(invariant);
+        self
+    }
+
+    pub fn witness(mut self, witness: QuintEx) -> Self {
+        self.witnesses.push(witness);
+        self
+    }
+
+    pub fn stop(self, stop: StopCondition) -> Self {
+        Self { stop, ..self }
+    }
+
+    pub fn check_deadlocks(self, check_deadlocks: bool) -> Self {
+        Self {
+            check_deadlocks,
+            ..self
+        }
+    }
+
+    pub fn store_metadata(self, store_metadata: bool) -> Self {
+        Self {
+            store_metadata,
+            ..self
+        }
+    }
+
+    pub fn collect_coverage(self, collect_coverage: bool) -> Self {
+        Self {
+            collect_coverage,
+            ..self
+        }
+    }
+
+    pub fn profiling(self, profiling: Profiling) -> Self {
+        Self { profiling, ..self }
+    }
+
+    pub fn sampling(self, sampling: SamplingBounds) -> Self {
+        Self { sampling, ..self }
+    }
+
+    pub fn shrink(self, shrink: bool) -> Self {
+        Self { shrink, ..self }
+    }
+
+    pub fn threads(self, threads: usize) -> Self {
+        Self { threads, ..self }
+    }
+
+    pub fn max_duration(self, max_duration: Duration) -> Self {
+        Self {
+            max_duration: Some(max_duration),
+            ..self
+        }
+    }
+
+    /// Also stop at `deadline`, or at an earlier deadline already set
+    pub fn deadline(self, deadline: Instant) -> Self {
+        Self {
+            cancellation: self.cancellation.with_deadline(deadline),
+            ..self
+        }
+    }
+
+    /// Stop when `cancellation` is cancelled, or at its deadline. This
+    /// replaces deadlines set before with [`SimulationConfig::deadline`].
+    pub fn cancellation(self, cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+}
+
 impl ParsedQuint {
     /// Simulate a Quint model for a given number of steps and samples, storing
     /// up to `n_traces` traces of the greatest quality.
     ///
+    /// This is [`ParsedQuint::simulate_with`] for a configuration with these
+    /// options, and the defaults for the others.
+    pub fn simulate(
+        &self,
+        steps: usize,
+        samples: usize,
+        n_traces: usize,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<SimulationResult, QuintError> {
+        let config = SimulationConfig {
+            steps,
+            samples,
+            traces: n_traces,
+            ..SimulationConfig::default()
+        };
+        self.simulate_with(&config, progress_callback)
+    }
+
+    /// Simulate a Quint model as set in `config`, storing up to
+    /// [`traces`](SimulationConfig::traces) traces of the greatest quality.
+    ///
     /// Start evaluating `init` and check that it satisfies the `invariant`.
     /// Then, evaluate `step` `steps` times, checking that `invariant` holds every time.
     ///
-    /// Repeat this `samples` times, and return the best `n_traces` traces
+    /// Repeat this `samples` times, and return the best traces.
     ///
     /// If `init` or `invariant` return false at any given point, simulation stops.
     /// If `step` returns false, we continue, as that just means we failed to progress
     /// in a specific setting.
-    pub fn simulate(
+    ///
+    /// If there is a `temporal` property, it is checked on each trace when its
+    /// run ends. Runs also end when they reach a state they have visited before,
+    /// in which case the trace is a lasso (see the [`temporal`] module).
+    ///
+    /// Elements of infinite sets are picked within the `sampling` bounds.
+    ///
+    /// If `check_deadlocks` is set and `step` returns false, every possible
+    /// choice of nondeterministic values is tried for the current state. If
+    /// `step` is disabled for all of them, simulation stops, reporting a
+    /// deadlock with the trace that leads to it.
+    ///
+    /// Each of the named `invariants`, of the model and of the configuration,
+    /// is also checked in every state. A violation of any of them stops
+    /// simulation too, unless the [`StopCondition`] is `AllViolations`. In
+    /// that case, only the run is stopped, and simulation goes on until a
+    /// counterexample is found for every named invariant (or samples run out).
+    /// Invariants are not checked again once they are violated, and the first
+    /// counterexample for each one is reported in
+    /// [`SimulationResult::invariants`].
+    ///
+    /// If `collect_coverage` is set, the result includes a report of which
+    /// actions were enabled and which definitions and branches were evaluated
+    /// during simulation (see the [`coverage`](crate::coverage) module).
+    ///
+    /// If `profiling` is enabled, the result includes the time spent evaluating
+    /// each definition (see the [`profiler`](crate::profiler) module).
+    ///
+    /// If `store_metadata` is set, each state in the traces also records the
+    /// action taken and the nondet picks that led to it (see
+    /// [`Storage::as_record`](crate::storage::Storage::as_record)).
+    ///
+    /// Simulation stops as soon as the `cancellation` token is cancelled, or
+    /// its deadline or the `max_duration` passes. The run in progress is then
+    /// dropped, and the result of the samples completed before is returned,
+    /// marked as [`interrupted`](SimulationResult::interrupted).
+    pub fn simulate_with(
         &self,
-        steps: usize,
-        samples: usize,
-        n_traces: usize,
+        config: &SimulationConfig,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<SimulationResult, QuintError> {
+        config.sampling.validate()?;
+        if config.threads != 1 {
+            return Err(QuintError::new(
+                "QNT523",
+                &format!(
+                    "Cannot simulate in {} threads, only one is supported for now",
+                    config.threads
+                ),
+            ));
+        }
+
+        if !config.invariants.is_empty() {
+            // Check them as part of the model, so shrinking checks them too
+            let parsed = ParsedQuint {
+                init: self.init.clone(),
+                step: self.step.clone(),
+                invariant: self.invariant.clone(),
+                invariants: self
+                    .invariants
+                    .iter()
+                    .chain(&config.invariants)
+                    .cloned()
+                    .collect(),
+                temporal: self.temporal.clone(),
+                table: self.table.clone(),
+            };
+            let config = SimulationConfig {
+                invariants: Vec::new(),
+                ..config.clone()
+            };
+            return parsed.simulate_with(&config, progress_callback);
+        }
+
+        let mut cancellation = config.cancellation.clone();
+        if let Some(max_duration) = config.max_duration {
+            cancellation = cancellation.with_deadline(Instant::now() + max_duration);
+        }
+
+        let mut result = self.simulate_samples(config, progress_callback, &cancellation)?;
+        if config.shrink {
+            for trace in result.best_traces.iter_mut().filter(|t| t.violation) {
+                *trace = self.shrink(trace, &config.sampling)?;
+            }
+        }
+        Ok(result)
+    }
+
+    fn simulate_samples(
+        &self,
+        config: &SimulationConfig,
         mut progress_callback: Option<ProgressCallback>,
+        cancellation: &CancellationToken,
     ) -> Result<SimulationResult, QuintError> {
+        let SimulationConfig {
+            steps,
+            samples,
+            traces: n_traces,
+            ..
+        } = *config;
+        let find_all_violations = config.stop == StopCondition::AllViolations;
+
         let mut interpreter = Interpreter::new(&self.table);
-        let mut env = Env::new(interpreter.var_storage.clone());
+        interpreter.var_storage.borrow_mut().store_metadata = config.store_metadata;
+        let mut env = match config.seed {
+            Some(seed) => Env::with_rand_state(interpreter.var_storage.clone(), seed),
+            None => Env::new(interpreter.var_storage.clone()),
+        };
+        env.sampling = config.sampling.clone();
+        let coverage = config
+            .collect_coverage
+            .then(|| interpreter.enable_coverage());
+        let profiler = interpreter.enable_profiling(config.profiling);
 
         let init = interpreter.compile(&self.init);
         let step = interpreter.compile(&self.step);
         let invariant = interpreter.compile(&self.invariant);
+        let invariants = self
+            .invariants
+            .iter()
+            .map(|inv| interpreter.compile(&inv.expr))
+            .collect::<Vec<_>>();
+        let temporal = self.temporal.as_ref().map(|t| interpreter.compile(t));
+        let witnesses = config
+            .witnesses
+            .iter()
+            .map(|w| interpreter.compile(w))
+            .collect::<Vec<_>>();
+        let mut witnessing_traces = vec![0; witnesses.len()];
 
         // Have one extra space as we insert first and then pop if we have too many traces
         let mut best_traces = Vec::with_capacity(n_traces + 1);
         let mut trace_lengths = Vec::with_capacity(n_traces + 1);
+        let mut invariant_results = self
+            .invariants
+            .iter()
+            .map(|inv| InvariantResult {
+                name: inv.name.clone(),
+                counterexample: None,
+            })
+            .collect::<Vec<_>>();
 
-        for sample_number in 1..=samples {
+        let mut stopped = None;
+        'samples: for sample_number in 1..=samples {
             if let Some(callback) = &mut progress_callback {
                 callback(ProgressUpdate {
                     current: sample_number,
@@ -93,43 +505,141 @@ impl ParsedQuint {
                 });
             }
 
+            // The state of the generator at the start of the run reproduces it
+            let seed = env.rand.get_state();
             let mut trace = Vec::with_capacity(steps + 1);
+            let mut choices = Vec::with_capacity(steps + 1);
+            // States visited in this run, to find lassos for temporal properties.
+            // States never hold lambdas, the only values with interior mutability.
+            #[allow(clippy::mutable_key_type)]
+            let mut visited = FxHashMap::default();
+            let mut loop_index = None;
+            // Which witnesses held in this run
+            let mut witnessed = vec![false; witnesses.len()];
 
+            // Forget what a step disabled in the previous run left behind
+            interpreter.var_storage.borrow_mut().clear_next_vars();
+            env.record_choices();
             if !init.execute(&mut env)?.as_bool() {
                 trace_lengths.push(0);
-                return Ok(SimulationResult {
-                    result: false,
-                    best_traces,
-                    trace_statistics: get_trace_statistics(&trace_lengths),
-                    samples: sample_number,
+                stopped = Some(Stopped::Violation {
+                    sample: sample_number,
+                    deadlock: false,
                 });
+                break 'samples;
             }
 
             for step_number in 1..=(steps + 1) {
+                if cancellation.is_cancelled() {
+                    // The dropped run is not counted for witnesses either
+                    for (count, _) in witnessing_traces
+                        .iter_mut()
+                        .zip(&witnessed)
+                        .filter(|(_, w)| **w)
+                    {
+                        *count -= 1;
+                    }
+                    stopped = Some(Stopped::Interrupted {
+                        completed: sample_number - 1,
+                    });
+                    break 'samples;
+                }
+
                 interpreter.shift();
 
+                if temporal.is_some() {
+                    let state = interpreter.var_storage.borrow().vars_record();
+                    if let Some(index) = visited.get(&state) {
+                        loop_index = Some(*index);
+                        break;
+                    }
+                    visited.insert(state, trace.len());
+                }
+
+                choices.push(env.take_recorded_choices());
                 trace.push(interpreter.var_storage.borrow().as_record());
 
-                if !invariant.execute(&mut env)?.as_bool() {
+                for (i, witness) in witnesses.iter().enumerate() {
+                    if !witnessed[i] && witness.execute(&mut env)?.as_bool() {
+                        witnessed[i] = true;
+                        witnessing_traces[i] += 1;
+                    }
+                }
+
+                let holds = invariant.execute(&mut env)?.as_bool();
+                let mut violated = Vec::new();
+                for (i, named) in invariants.iter().enumerate() {
+                    if !invariant_results[i].violated() && !named.execute(&mut env)?.as_bool() {
+                        violated.push(i);
+                    }
+                }
+
+                if !holds || !violated.is_empty() {
                     trace_lengths.push(trace.len());
                     // Found a counterexample
-                    collect_trace(
-                        &mut best_traces,
-                        n_traces,
-                        Trace {
-                            states: trace,
-                            violation: false,
-                        },
-                    );
-                    return Ok(SimulationResult {
-                        result: false,
-                        best_traces,
-                        trace_statistics: get_trace_statistics(&trace_lengths),
-                        samples: sample_number,
+                    let counterexample = Trace {
+                        states: trace,
+                        violation: true,
+                        deadlock: false,
+                        loop_index: None,
+                        choices,
+                        seed: Some(seed),
+                        sample: Some(sample_number),
+                        // A violation of `invariant` is reported without a
+                        // name, as it is not known here
+                        violated_invariant: violated
+                            .first()
+                            .filter(|_| holds)
+                            .map(|i| self.invariants[*i].name.as_str().into()),
+                    };
+                    for i in violated {
+                        invariant_results[i].counterexample = Some(Trace {
+                            violated_invariant: Some(self.invariants[i].name.as_str().into()),
+                            ..counterexample.clone()
+                        });
+                    }
+                    collect_trace(&mut best_traces, n_traces, counterexample);
+
+                    let all_violated = invariant_results.iter().all(|r| r.violated());
+                    if holds && find_all_violations && !all_violated {
+                        continue 'samples;
+                    }
+                    stopped = Some(Stopped::Violation {
+                        sample: sample_number,
+                        deadlock: false,
                     });
+                    break 'samples;
                 }
 
-                if step_number != steps + 1 && !step.execute(&mut env)?.as_bool() {
+                if step_number == steps + 1 {
+                    break;
+                }
+
+                env.record_choices();
+                if !step.execute(&mut env)?.as_bool() {
+                    if config.check_deadlocks && is_deadlocked(&mut env, &step)? {
+                        trace_lengths.push(trace.len());
+                        collect_trace(
+                            &mut best_traces,
+                            n_traces,
+                            Trace {
+                                states: trace,
+                                violation: true,
+                                deadlock: true,
+                                loop_index: None,
+                                choices,
+                                seed: Some(seed),
+                                sample: Some(sample_number),
+                                violated_invariant: None,
+                            },
+                        );
+                        stopped = Some(Stopped::Violation {
+                            sample: sample_number,
+                            deadlock: true,
+                        });
+                        break 'samples;
+                    }
+
                     // The run cannot be extended. In some cases, this may indicate a deadlock.
                     // Since we are doing random simulation, it is very likely
                     // that we have not generated good values for extending
@@ -140,25 +650,109 @@ impl ParsedQuint {
                     break;
                 }
             }
+
+            let violation = match &temporal {
+                Some(property) => !temporal::check_trace(&mut env, property, &trace, loop_index)?,
+                None => false,
+            };
+
             trace_lengths.push(trace.len());
             collect_trace(
                 &mut best_traces,
                 n_traces,
                 Trace {
                     states: trace,
-                    violation: false,
+                    violation,
+                    deadlock: false,
+                    loop_index,
+                    choices,
+                    seed: Some(seed),
+                    sample: Some(sample_number),
+                    violated_invariant: None,
                 },
             );
+
+            if violation {
+                stopped = Some(Stopped::Violation {
+                    sample: sample_number,
+                    deadlock: false,
+                });
+                break 'samples;
+            }
         }
+
+        let all_hold = invariant_results.iter().all(|r| !r.violated());
+        let (result, deadlock, samples, interrupted) = match stopped {
+            None => (all_hold, false, samples, false),
+            Some(Stopped::Violation { sample, deadlock }) => (false, deadlock, sample, false),
+            Some(Stopped::Interrupted { completed }) => (all_hold, false, completed, true),
+        };
         Ok(SimulationResult {
-            result: true,
+            result,
+            deadlock,
             best_traces,
+            invariants: invariant_results,
             trace_statistics: get_trace_statistics(&trace_lengths),
             samples,
+            interrupted,
+            coverage: coverage.as_ref().map(|c| c.report()),
+            profile: profiler.as_ref().map(|p| p.report()),
+            params: interpreter.const_values(),
+            witnessing_traces,
+        })
+    }
+}
+
+/// Why a simulation stopped before trying every sample
+enum Stopped {
+    /// A violation or a deadlock was found in the given sample, or `init`
+    /// was disabled in it
+    Violation { sample: usize, deadlock: bool },
+    /// Cancelled, or out of time, after completing some samples
+    Interrupted { completed: usize },
+}
+
+impl ParsedQuint {
+    /// Compile `invariant` and the named `invariants` into a single
+    /// expression, which holds when all of them hold.
+    pub fn compile_invariants(&self, interpreter: &mut Interpreter) -> CompiledExpr {
+        let invariant = interpreter.compile(&self.invariant);
+        if self.invariants.is_empty() {
+            return invariant;
+        }
+
+        let named = self
+            .invariants
+            .iter()
+            .map(|inv| interpreter.compile(&inv.expr))
+            .collect::<Vec<_>>();
+        CompiledExpr::new(move |env| {
+            for inv in std::iter::once(&invariant).chain(&named) {
+                if !inv.execute(env)?.as_bool() {
+                    return Ok(Value::Bool(false));
+                }
+            }
+            Ok(Value::Bool(true))
         })
     }
 }
 
+/// Check whether `step` is disabled in the current state for every possible
+/// choice of nondeterministic values.
+fn is_deadlocked(env: &mut Env, step: &CompiledExpr) -> Result<bool, QuintError> {
+    let mut deadlocked = true;
+    for_each_choice(env, |env| {
+        env.var_storage.borrow_mut().clear_next_vars();
+        if step.execute(env)?.as_bool() {
+            deadlocked = false;
+            return Ok(ControlFlow::Break(()));
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    env.var_storage.borrow_mut().clear_next_vars();
+    Ok(deadlocked)
+}
+
 /// Get statistics about the lengths of traces collected during simulation.
 fn get_trace_statistics(trace_lengths: &[usize]) -> TraceStatistics {
     if trace_lengths.is_empty() {
@@ -2,7 +2,7 @@
 //! state machines' state.
 
 use crate::ir::QuintName;
-use crate::value::{ImmutableMap, Value};
+use crate::value::{ImmutableMap, ImmutableVec, Value};
 use std::{cell::RefCell, rc::Rc};
 
 /// Variable registers are like the regular registers (ref cells) except that
@@ -29,9 +29,9 @@ pub struct VariableRegister {
 #[derive(Clone)]
 pub struct Snapshot {
     pub next_vars: ImmutableMap<QuintName, VariableRegister>,
-    // TODO:
-    // nondet_picks
-    // action_taken
+    pub next_nondet_picks: ImmutableMap<QuintName, Option<Value>>,
+    pub next_action_taken: Option<QuintName>,
+    pub next_action_depth: usize,
 }
 
 #[derive(Default, Clone)]
@@ -42,9 +42,22 @@ pub struct Storage {
     pub next_vars: ImmutableMap<QuintName, Rc<RefCell<VariableRegister>>>,
     // A list of caches to clear after every step, used to cache values during a single state only.
     pub caches_to_clear: Vec<Rc<RefCell<Option<Value>>>>,
-    // TODO:
-    // nondet_picks
-    // action_taken
+    // Whether to record nondet picks and the action taken, and include them in
+    // the state records (for model-based testing, i.e. `--mbt`)
+    pub store_metadata: bool,
+    // The values picked by each `nondet` binding in the transition that led to
+    // the current state. Bindings that were not evaluated have `None`.
+    pub nondet_picks: ImmutableMap<QuintName, Option<Value>>,
+    // The name of the action taken in the transition that led to the current state
+    pub action_taken: Option<QuintName>,
+    // Same as `nondet_picks` and `action_taken`, but for the transition being
+    // evaluated. These are moved to the fields above in `shift_vars()`.
+    pub next_nondet_picks: ImmutableMap<QuintName, Option<Value>>,
+    pub next_action_taken: Option<QuintName>,
+    // How many actions `next_action_taken` is nested in (see `enter_action()`)
+    next_action_depth: usize,
+    // How many actions are being evaluated, one inside the other
+    action_depth: usize,
 }
 
 impl Storage {
@@ -58,18 +71,187 @@ impl Storage {
                 current.value = next.value.take();
             }
         }
+
+        // The metadata follows the values: what was recorded for the next
+        // state now describes the current state.
+        let cleared_picks = self.cleared_picks();
+        self.nondet_picks = std::mem::replace(&mut self.next_nondet_picks, cleared_picks);
+        self.action_taken = self.next_action_taken.take();
+
         self.clear_caches();
     }
 
+    /// Register the name of a `nondet` binding, so it shows up in the state
+    /// records (with `None`) even when it is not evaluated in a transition.
+    pub fn add_nondet_name(&mut self, name: QuintName) {
+        self.nondet_picks.insert(name.clone(), None);
+        self.next_nondet_picks.insert(name, None);
+    }
+
+    /// Record the value picked by a `nondet` binding in the transition being
+    /// evaluated. Does nothing unless `store_metadata` is enabled.
+    pub fn record_nondet_pick(&mut self, name: &QuintName, value: &Value) {
+        if self.store_metadata {
+            self.next_nondet_picks
+                .insert(name.clone(), Some(value.clone()));
+        }
+    }
+
+    /// Start evaluating a named action, returning how many actions it is
+    /// nested in. The definition of the transition itself (e.g. `step`) is
+    /// at depth 0.
+    pub fn enter_action(&mut self) -> usize {
+        self.action_depth += 1;
+        self.action_depth - 1
+    }
+
+    /// Finish evaluating a named action (see [`Storage::enter_action`]).
+    pub fn exit_action(&mut self) {
+        self.action_depth -= 1;
+    }
+
+    /// Record the name of an action taken in the transition being evaluated,
+    /// at the given `depth` (see [`Storage::enter_action`]). The outermost
+    /// action below the definition of the transition is kept, so for
+    /// `step = A`, where `A` calls other actions, `A` is recorded. The
+    /// transition itself is only recorded if no action below it was. Does
+    /// nothing unless `store_metadata` is enabled.
+    pub fn record_action_taken(&mut self, name: &QuintName, depth: usize) {
+        if !self.store_metadata {
+            return;
+        }
+        let outer = depth > 0 && depth < self.next_action_depth;
+        if self.next_action_taken.is_none() || outer {
+            self.next_action_taken = Some(name.clone());
+            self.next_action_depth = depth;
+        }
+    }
+
     /// Build a record with the current state variables' values, to be used in traces.
+    ///
+    /// If `store_metadata` is enabled, the record also includes the
+    /// `mbt::actionTaken` and `mbt::nondetPicks` fields, where each nondet pick
+    /// is wrapped in a `Some`/`None` variant.
     pub fn as_record(&self) -> Value {
-        let map = self.vars.values().filter_map(|register| {
-            let reg = register.borrow().clone();
-            reg.value.map(|v| (reg.name, v))
-        });
+        let Value::Record(mut map) = self.vars_record() else {
+            unreachable!("vars_record always builds a record")
+        };
+
+        if self.store_metadata {
+            let action_taken = self.action_taken.clone().unwrap_or_default();
+            let nondet_picks = self
+                .nondet_picks
+                .iter()
+                .map(|(name, pick)| {
+                    let variant = match pick {
+                        Some(value) => Value::Variant("Some".into(), Rc::new(value.clone())),
+                        None => Value::Variant(
+                            "None".into(),
+                            Rc::new(Value::Tuple(ImmutableVec::new())),
+                        ),
+                    };
+                    (name.clone(), variant)
+                })
+                .collect();
+
+            map.insert("mbt::actionTaken".into(), Value::Str(action_taken));
+            map.insert("mbt::nondetPicks".into(), Value::Record(nondet_picks));
+        }
+
+        Value::Record(map)
+    }
 
-        // TODO: add nondet picks and action taken
-        Value::Record(ImmutableMap::from_iter(map))
+    /// Build a record with the current state variables' values only, without
+    /// any metadata.
+    pub fn vars_record(&self) -> Value {
+        Value::Record(
+            self.vars
+                .values()
+                .filter_map(|register| {
+                    let reg = register.borrow().clone();
+                    reg.value.map(|v| (reg.name, v))
+                })
+                .collect(),
+        )
+    }
+
+    /// Build a record with the next state variables' values. Variables that
+    /// were not assigned keep their current values.
+    pub fn next_state_record(&self) -> Value {
+        Value::Record(
+            self.vars
+                .iter()
+                .filter_map(|(key, register)| {
+                    let current = register.borrow();
+                    let value = self
+                        .next_vars
+                        .get(key)
+                        .and_then(|next| next.borrow().value.clone())
+                        .or_else(|| current.value.clone());
+                    value.map(|v| (current.name.clone(), v))
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether the values assigned to the next state variables are the same as
+    /// the ones in `record`. Variables that were not assigned are not compared.
+    pub fn next_vars_match(&self, record: &Value) -> bool {
+        let fields = record.as_record_map();
+        self.next_vars.values().all(|register| {
+            let register = register.borrow();
+            register
+                .value
+                .as_ref()
+                .is_none_or(|value| fields.get(&register.name) == Some(value))
+        })
+    }
+
+    /// Set the current state variables from a record built by
+    /// [`Storage::as_record`], clearing the next state and the caches. This is
+    /// used to go back to a state that was visited before.
+    ///
+    /// Variables that are not in the record are left unset, and fields that
+    /// are not variables (i.e. metadata) are ignored.
+    pub fn set_vars_from_record(&mut self, record: &Value) {
+        let fields = record.as_record_map();
+        for register in self.vars.values() {
+            let mut register = register.borrow_mut();
+            register.value = fields.get(&register.name).cloned();
+        }
+        self.clear_next_vars();
+        self.clear_caches();
+    }
+
+    /// Whether any variable was assigned in the next state.
+    pub fn next_vars_assigned(&self) -> bool {
+        self.next_vars
+            .values()
+            .any(|register| register.borrow().value.is_some())
+    }
+
+    /// Unset all the values in the next state, along with the nondet picks
+    /// and the action taken recorded for it.
+    pub fn clear_next_vars(&mut self) {
+        for register in self.next_vars.values() {
+            register.borrow_mut().value = None;
+        }
+        self.next_nondet_picks = self.cleared_picks();
+        self.next_action_taken = None;
+    }
+
+    /// Assign the current values to the next state variables that were not
+    /// assigned yet, so they stay unchanged.
+    pub fn keep_unassigned_vars(&mut self) {
+        for (key, register_for_next) in self.next_vars.iter() {
+            let mut next = register_for_next.borrow_mut();
+            if next.value.is_none() {
+                next.value = self
+                    .vars
+                    .get(key)
+                    .and_then(|current| current.borrow().value.clone());
+            }
+        }
     }
 
     pub fn take_snapshot(&self) -> Snapshot {
@@ -79,6 +261,9 @@ impl Storage {
                 .iter()
                 .map(|(k, v)| (k.clone(), v.borrow().clone()))
                 .collect(),
+            next_nondet_picks: self.next_nondet_picks.clone(),
+            next_action_taken: self.next_action_taken.clone(),
+            next_action_depth: self.next_action_depth,
         }
     }
 
@@ -88,6 +273,17 @@ impl Storage {
                 v.borrow_mut().value = next.value.clone();
             }
         });
+        self.next_nondet_picks = snapshot.next_nondet_picks.clone();
+        self.next_action_taken = snapshot.next_action_taken.clone();
+        self.next_action_depth = snapshot.next_action_depth;
+    }
+
+    /// The nondet bindings, all without a picked value
+    fn cleared_picks(&self) -> ImmutableMap<QuintName, Option<Value>> {
+        self.next_nondet_picks
+            .keys()
+            .map(|name| (name.clone(), None))
+            .collect()
     }
 
     fn clear_caches(&mut self) {
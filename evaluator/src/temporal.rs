@@ -0,0 +1,243 @@
+//! Checking of temporal properties over traces.
+//!
+//! Temporal properties (definitions with the `temporal` qualifier) talk about
+//! whole behaviors instead of single states, so they cannot be evaluated like
+//! invariants. Instead, we evaluate them over a trace produced by simulation,
+//! which is either:
+//!  - a lasso, when the run reached a state it had visited before. This
+//!    represents an infinite behavior that repeats the states from
+//!    `loop_index` onwards forever, so liveness and fairness can be decided
+//!    exactly;
+//!  - a finite trace, when the run ended for any other reason. Safety is
+//!    checked over the states we have, while liveness is assumed to hold, as
+//!    no finite prefix can violate it: `eventually` is true if its argument
+//!    holds in some state, or otherwise because the trace could still
+//!    continue, and fairness always holds.
+//!
+//! Note that the latter means that temporal operators under a negation (like
+//! `not(eventually(p))`) can be reported as violated on finite traces that
+//! don't satisfy them yet.
+//!
+//! The temporal operators are regular (lazy) built-ins that read the
+//! [`TemporalContext`] in the environment, so they can be nested under any
+//! other operator (i.e. `forall`). Their arguments are evaluated at each
+//! relevant position of the trace, and actions are checked against the
+//! transition to the next state, trying every possible nondeterministic
+//! choice (see [`for_each_choice`]).
+
+use crate::{
+    choices::for_each_choice,
+    evaluator::{CompiledExpr, Env, EvalResult},
+    ir::QuintError,
+    value::Value,
+};
+use std::ops::{ControlFlow, Range};
+use std::rc::Rc;
+
+/// The trace a temporal property is being evaluated on, and the position in
+/// it where the expression currently being evaluated holds or not.
+#[derive(Clone)]
+pub struct TemporalContext {
+    states: Rc<Vec<Value>>,
+    loop_index: Option<usize>,
+    position: usize,
+}
+
+impl TemporalContext {
+    /// The position after `position`, if any. The last state of a lasso is
+    /// followed by the state at `loop_index`.
+    fn successor(&self, position: usize) -> Option<usize> {
+        if position + 1 < self.states.len() {
+            Some(position + 1)
+        } else {
+            self.loop_index
+        }
+    }
+
+    /// The positions that can be reached from `position`, including itself.
+    fn future(&self, position: usize) -> Range<usize> {
+        match self.loop_index {
+            Some(loop_index) if position >= loop_index => loop_index..self.states.len(),
+            _ => position..self.states.len(),
+        }
+    }
+}
+
+/// Check whether the temporal `property` holds on the behavior given by
+/// `states`, which is a lasso if `loop_index` is set (see the module
+/// documentation).
+///
+/// The state variables are left with the values of some state of the trace.
+pub fn check_trace(
+    env: &mut Env,
+    property: &CompiledExpr,
+    states: &[Value],
+    loop_index: Option<usize>,
+) -> Result<bool, QuintError> {
+    let previous = env.temporal.replace(TemporalContext {
+        states: Rc::new(states.to_vec()),
+        loop_index,
+        position: 0,
+    });
+    let result = holds_at(env, 0, property);
+    env.temporal = previous;
+    result
+}
+
+/// `always(p)`: `p` holds in every position from the current one.
+pub fn always(env: &mut Env, arg: &CompiledExpr) -> EvalResult {
+    let ctx = context(env)?;
+    for position in ctx.future(ctx.position) {
+        if !holds_at(env, position, arg)? {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+/// `eventually(p)`: `p` holds in some position from the current one, or the
+/// trace is finite and could still get there.
+pub fn eventually(env: &mut Env, arg: &CompiledExpr) -> EvalResult {
+    let ctx = context(env)?;
+    for position in ctx.future(ctx.position) {
+        if holds_at(env, position, arg)? {
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(ctx.loop_index.is_none()))
+}
+
+/// `weakFair(A, vars)` and `strongFair(A, vars)`: if `A` is enabled (in a
+/// way that changes `vars`) forever (for weak fairness) or infinitely often
+/// (for strong fairness), then it is taken infinitely often.
+///
+/// In a lasso, the states that repeat infinitely often are the ones in the
+/// loop, from any position. In a finite trace, fairness always holds.
+pub fn fair(env: &mut Env, action: &CompiledExpr, vars: &CompiledExpr, strong: bool) -> EvalResult {
+    let ctx = context(env)?;
+    let Some(loop_index) = ctx.loop_index else {
+        return Ok(Value::Bool(true));
+    };
+
+    let mut enabled_count = 0;
+    for position in loop_index..ctx.states.len() {
+        if is_taken(env, position, action, vars)? {
+            return Ok(Value::Bool(true));
+        }
+        if is_enabled(env, position, action, vars)? {
+            enabled_count += 1;
+        }
+    }
+
+    let violated = if strong {
+        enabled_count > 0
+    } else {
+        enabled_count == ctx.states.len() - loop_index
+    };
+    Ok(Value::Bool(!violated))
+}
+
+fn context(env: &Env) -> Result<TemporalContext, QuintError> {
+    env.temporal.clone().ok_or_else(|| {
+        QuintError::new(
+            "QNT501",
+            "Temporal operators can only be evaluated when checking a temporal property",
+        )
+    })
+}
+
+/// Move to `position`, evaluate `f`, and move back to where we were.
+fn at<T>(
+    env: &mut Env,
+    position: usize,
+    f: impl FnOnce(&mut Env, &TemporalContext) -> Result<T, QuintError>,
+) -> Result<T, QuintError> {
+    let mut ctx = context(env)?;
+    let previous = ctx.position;
+
+    set_position(env, &mut ctx, position);
+    let result = f(env, &ctx);
+    set_position(env, &mut ctx, previous);
+
+    result
+}
+
+fn set_position(env: &mut Env, ctx: &mut TemporalContext, position: usize) {
+    ctx.position = position;
+    env.var_storage
+        .borrow_mut()
+        .set_vars_from_record(&ctx.states[position]);
+    env.temporal = Some(ctx.clone());
+}
+
+/// Whether `expr` holds at `position`. If it is an action, it also has to
+/// lead to the next state in the trace (if there is one).
+fn holds_at(env: &mut Env, position: usize, expr: &CompiledExpr) -> Result<bool, QuintError> {
+    at(env, position, |env, ctx| {
+        let next = ctx.successor(position).map(|s| &ctx.states[s]);
+        let mut holds = false;
+        for_each_choice(env, |env| {
+            env.var_storage.borrow_mut().clear_next_vars();
+            if expr.execute(env)?.as_bool()
+                && next.is_none_or(|next| env.var_storage.borrow().next_vars_match(next))
+            {
+                holds = true;
+                return Ok(ControlFlow::Break(()));
+            }
+            Ok(ControlFlow::Continue(()))
+        })?;
+        env.var_storage.borrow_mut().clear_next_vars();
+        Ok(holds)
+    })
+}
+
+/// Whether `<A>_vars` is the transition from `position` to the next state.
+fn is_taken(
+    env: &mut Env,
+    position: usize,
+    action: &CompiledExpr,
+    vars: &CompiledExpr,
+) -> Result<bool, QuintError> {
+    let ctx = context(env)?;
+    let Some(next) = ctx.successor(position) else {
+        return Ok(false);
+    };
+
+    let before = at(env, position, |env, _| vars.execute(env))?;
+    let after = at(env, next, |env, _| vars.execute(env))?;
+    Ok(before != after && holds_at(env, position, action)?)
+}
+
+/// Whether `<A>_vars` can be taken from `position`, with any choice of
+/// nondeterministic values.
+fn is_enabled(
+    env: &mut Env,
+    position: usize,
+    action: &CompiledExpr,
+    vars: &CompiledExpr,
+) -> Result<bool, QuintError> {
+    at(env, position, |env, ctx| {
+        let current = &ctx.states[position];
+        let before = vars.execute(env)?;
+        let mut enabled = false;
+        for_each_choice(env, |env| {
+            env.var_storage.borrow_mut().clear_next_vars();
+            if !action.execute(env)?.as_bool() {
+                return Ok(ControlFlow::Continue(()));
+            }
+
+            // Evaluate `vars` in the resulting state, and go back
+            let next = env.var_storage.borrow().next_state_record();
+            env.var_storage.borrow_mut().set_vars_from_record(&next);
+            let after = vars.execute(env);
+            env.var_storage.borrow_mut().set_vars_from_record(current);
+
+            if after? != before {
+                enabled = true;
+                return Ok(ControlFlow::Break(()));
+            }
+            Ok(ControlFlow::Continue(()))
+        })?;
+        Ok(enabled)
+    })
+}
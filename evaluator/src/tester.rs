@@ -0,0 +1,196 @@
+//! Running the `run` definitions of a module as tests.
+//!
+//! A `run` is an action built with `then`, `reps` and `expect` that describes
+//! a specific scenario. It passes if it evaluates to true, and fails if it
+//! evaluates to false or results in an error (i.e. from a failed `expect` or
+//! an action in the middle of a `then` chain being disabled). Since runs can
+//! have nondeterminism, each one is evaluated up to `max_samples` times, each
+//! with its own seed, stopping at the first failure so it can be reproduced
+//! with that seed.
+
+use crate::{
+    evaluator::{Env, Interpreter},
+    ir::{LookupTable, OpDef, OpQualifier, QuintDeclaration, QuintError, QuintOutput},
+    itf::Trace,
+    rand::Rand,
+    value::Value,
+};
+use regex::Regex;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Which runs to execute, and how.
+pub struct TestOptions {
+    /// Only run the definitions whose names match this
+    pub filter: Option<Regex>,
+    /// How many times to evaluate each run, if it keeps passing
+    pub max_samples: usize,
+    /// The seed for the first sample of each run, or a random one
+    pub seed: Option<u64>,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            max_samples: 10_000,
+            seed: None,
+        }
+    }
+}
+
+/// The outcome of a run.
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    /// The seed of the failing sample, or of the first sample if all passed.
+    /// Running again with this seed reproduces the failure.
+    pub seed: u64,
+    /// The number of samples evaluated
+    pub samples: usize,
+    /// The error the run failed with, if it failed with an error instead of
+    /// evaluating to false
+    pub error: Option<QuintError>,
+    /// The states reached by the failing sample, or by the last sample if all
+    /// passed (see [`TraceCollector`](crate::evaluator::TraceCollector))
+    pub trace: Option<Trace>,
+    pub duration: Duration,
+}
+
+/// The `run` definitions in the main module, in the order they are declared,
+/// whose names match the `filter`.
+pub fn find_runs<'a>(output: &'a QuintOutput, filter: Option<&Regex>) -> Vec<&'a OpDef> {
+    output
+        .modules
+        .iter()
+        .filter(|module| module.name == output.main)
+        .flat_map(|module| &module.declarations)
+        .filter_map(|declaration| match declaration {
+            QuintDeclaration::QuintOpDef(def) if def.qualifier == OpQualifier::Run => Some(def),
+            _ => None,
+        })
+        .filter(|def| filter.is_none_or(|f| f.is_match(&def.name)))
+        .collect()
+}
+
+/// Execute all runs of the main module selected by the options.
+pub fn run_tests(output: &QuintOutput, options: &TestOptions) -> Vec<TestResult> {
+    let seed = options.seed.unwrap_or_else(|| Rand::new().get_state());
+    find_runs(output, options.filter.as_ref())
+        .into_iter()
+        .map(|def| run_test(&output.table, def, options.max_samples, seed))
+        .collect()
+}
+
+/// Evaluate a run up to `max_samples` times, starting from the given seed,
+/// until it fails.
+pub fn run_test(table: &LookupTable, def: &OpDef, max_samples: usize, seed: u64) -> TestResult {
+    let start = Instant::now();
+    let mut interpreter = Interpreter::new(table);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), seed);
+    let run = interpreter.compile(&def.expr);
+
+    let mut result = TestResult {
+        name: def.name.to_string(),
+        passed: true,
+        seed,
+        samples: 0,
+        error: None,
+        trace: None,
+        duration: Duration::ZERO,
+    };
+
+    for _ in 0..max_samples {
+        // Each sample starts from an empty state
+        let sample_seed = env.rand.get_state();
+        env.var_storage
+            .borrow_mut()
+            .set_vars_from_record(&Value::Record(Default::default()));
+        result.samples += 1;
+
+        env.collect_trace();
+        let outcome = run.execute(&mut env);
+        let passed = matches!(outcome, Ok(Value::Bool(true)));
+        let states = env.take_collected_trace(passed);
+        result.trace = (!states.is_empty()).then(|| Trace {
+            states,
+            violation: !passed,
+            deadlock: false,
+            loop_index: None,
+            choices: Vec::new(),
+            seed: Some(sample_seed),
+            sample: Some(result.samples),
+            violated_invariant: None,
+        });
+
+        if !passed {
+            result.passed = false;
+            result.seed = sample_seed;
+            result.error = outcome.err();
+            break;
+        }
+    }
+
+    result.duration = start.elapsed();
+    result
+}
+
+/// A JUnit XML report with one test suite, named after the module, with one
+/// test case for each run.
+pub fn to_junit_xml(suite: &str, results: &[TestResult]) -> String {
+    let mut xml = String::new();
+    write_junit_xml(&mut xml, suite, results).expect("writing to a string cannot fail");
+    xml
+}
+
+fn write_junit_xml(xml: &mut String, suite: &str, results: &[TestResult]) -> fmt::Result {
+    let failures = results.iter().filter(|r| !r.passed).count();
+    let time: Duration = results.iter().map(|r| r.duration).sum();
+
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(xml, "<testsuites>")?;
+    writeln!(
+        xml,
+        r#"  <testsuite name="{}" tests="{}" failures="{failures}" time="{:.3}">"#,
+        escape_xml(suite),
+        results.len(),
+        time.as_secs_f64()
+    )?;
+    for result in results {
+        write!(
+            xml,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+            escape_xml(&result.name),
+            escape_xml(suite),
+            result.duration.as_secs_f64()
+        )?;
+        if result.passed {
+            writeln!(xml, "/>")?;
+            continue;
+        }
+
+        let message = match &result.error {
+            Some(error) => error.to_string(),
+            None => format!("{} returns false", result.name),
+        };
+        writeln!(xml, ">")?;
+        writeln!(
+            xml,
+            r#"      <failure message="{}">Use --seed 0x{:x} to reproduce.</failure>"#,
+            escape_xml(&message),
+            result.seed
+        )?;
+        writeln!(xml, "    </testcase>")?;
+    }
+    writeln!(xml, "  </testsuite>")?;
+    writeln!(xml, "</testsuites>")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
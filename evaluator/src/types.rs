@@ -0,0 +1,386 @@
+//! Quint types, as given in the IR by the Quint compiler.
+//!
+//! Types are not needed for evaluation, but they are needed to read values
+//! back from ITF, where records and variants share the same encoding (see
+//! [`Value::from_itf_with_type`](crate::value::Value::from_itf_with_type)).
+//!
+//! In ITF traces, the types of variables are written as strings in the
+//! `var_types` metadata, using the syntax of Apalache types:
+//!  - `Int`, `Bool` and `Str`;
+//!  - `Set(T)` and `Seq(T)` for sets and lists;
+//!  - `(T1 -> T2)` for maps;
+//!  - `<<T1, T2>>` for tuples, and `{ f1: T1, f2: T2 }` for records;
+//!  - `Variant(A(T1) | B(T2))` for sum types, where `<<>>` is the type of
+//!    variants without a value;
+//!  - `(T1, T2) => T` for operators;
+//!  - the name itself for constant types and type variables.
+
+use crate::ir::{LookupDefinition, LookupTable, QuintDeclaration, QuintName, QuintVar};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QuintType {
+    Bool,
+    Int,
+    Str,
+    Const {
+        name: QuintName,
+    },
+    Var {
+        name: QuintName,
+    },
+    Set {
+        elem: Box<QuintType>,
+    },
+    List {
+        elem: Box<QuintType>,
+    },
+    Fun {
+        arg: Box<QuintType>,
+        res: Box<QuintType>,
+    },
+    Oper {
+        args: Vec<QuintType>,
+        res: Box<QuintType>,
+    },
+    Tup {
+        fields: Row,
+    },
+    Rec {
+        fields: Row,
+    },
+    Sum {
+        fields: Row,
+    },
+    App {
+        ctor: Box<QuintType>,
+        args: Vec<QuintType>,
+    },
+}
+
+/// The fields of a tuple, record or sum type. Rows may be extended by
+/// another row (`other`), which is how Quint represents row polymorphism.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Row {
+    Row {
+        fields: Vec<RowField>,
+        other: Box<Row>,
+    },
+    Empty,
+    Var {
+        name: QuintName,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RowField {
+    pub field_name: QuintName,
+    pub field_type: QuintType,
+}
+
+impl Row {
+    /// A closed row with the given fields.
+    pub fn new(fields: Vec<RowField>) -> Self {
+        Row::Row {
+            fields,
+            other: Box::new(Row::Empty),
+        }
+    }
+
+    /// All the fields in this row and the rows it is extended by.
+    pub fn fields(&self) -> Vec<&RowField> {
+        match self {
+            Row::Row { fields, other } => fields.iter().chain(other.fields()).collect(),
+            Row::Empty | Row::Var { .. } => Vec::new(),
+        }
+    }
+
+    /// The type of the field with the given name, if any.
+    pub fn field_type(&self, name: &str) -> Option<&QuintType> {
+        self.fields()
+            .into_iter()
+            .find(|field| field.field_name == name)
+            .map(|field| &field.field_type)
+    }
+}
+
+/// The declared types of the state variables in the lookup table, by name.
+pub fn var_types(table: &LookupTable) -> FxHashMap<QuintName, QuintType> {
+    table
+        .values()
+        .filter_map(|def| match def {
+            LookupDefinition::Definition(QuintDeclaration::QuintVar(QuintVar {
+                name,
+                type_annotation: Some(ty),
+                ..
+            })) => Some((name.clone(), ty.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+impl fmt::Display for QuintType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuintType::Bool => write!(f, "Bool"),
+            QuintType::Int => write!(f, "Int"),
+            QuintType::Str => write!(f, "Str"),
+            QuintType::Const { name } | QuintType::Var { name } => write!(f, "{name}"),
+            QuintType::Set { elem } => write!(f, "Set({elem})"),
+            QuintType::List { elem } => write!(f, "Seq({elem})"),
+            QuintType::Fun { arg, res } => write!(f, "({arg} -> {res})"),
+            QuintType::Oper { args, res } => {
+                write!(f, "(")?;
+                write_separated(f, args.iter(), ", ", |f, arg| write!(f, "{arg}"))?;
+                write!(f, ") => {res}")
+            }
+            QuintType::Tup { fields } => {
+                write!(f, "<<")?;
+                write_separated(f, fields.fields().into_iter(), ", ", |f, field| {
+                    write!(f, "{}", field.field_type)
+                })?;
+                write!(f, ">>")
+            }
+            QuintType::Rec { fields } if fields.fields().is_empty() => write!(f, "{{}}"),
+            QuintType::Rec { fields } => {
+                write!(f, "{{ ")?;
+                write_separated(f, fields.fields().into_iter(), ", ", |f, field| {
+                    write!(f, "{}: {}", field.field_name, field.field_type)
+                })?;
+                write!(f, " }}")
+            }
+            QuintType::Sum { fields } => {
+                write!(f, "Variant(")?;
+                write_separated(f, fields.fields().into_iter(), " | ", |f, field| {
+                    write!(f, "{}({})", field.field_name, field.field_type)
+                })?;
+                write!(f, ")")
+            }
+            QuintType::App { ctor, args } => {
+                write!(f, "{ctor}[")?;
+                write_separated(f, args.iter(), ", ", |f, arg| write!(f, "{arg}"))?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn write_separated<T>(
+    f: &mut fmt::Formatter<'_>,
+    items: impl Iterator<Item = T>,
+    separator: &str,
+    mut write_item: impl FnMut(&mut fmt::Formatter<'_>, T) -> fmt::Result,
+) -> fmt::Result {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, "{separator}")?;
+        }
+        write_item(f, item)?;
+    }
+    Ok(())
+}
+
+impl FromStr for QuintType {
+    type Err = String;
+
+    /// Parse a type written with the syntax of Apalache types (see the module
+    /// documentation).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = TypeParser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let ty = parser.parse_type()?;
+        match parser.peek() {
+            None => Ok(ty),
+            Some(token) => Err(format!("Unexpected `{token}` in type `{s}`")),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(ident);
+            }
+            '<' | '>' | '-' | '=' => {
+                let two = match (c, chars.peek()) {
+                    ('<', Some('<')) | ('>', Some('>')) | ('-', Some('>')) | ('=', Some('>')) => {
+                        let next = chars.next().unwrap();
+                        format!("{c}{next}")
+                    }
+                    _ => return Err(format!("Unexpected `{c}` in type `{s}`")),
+                };
+                tokens.push(two);
+            }
+            '(' | ')' | '{' | '}' | '[' | ']' | ',' | ':' | '|' => tokens.push(c.to_string()),
+            c => return Err(format!("Unexpected `{c}` in type `{s}`")),
+        }
+    }
+    Ok(tokens)
+}
+
+struct TypeParser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl TypeParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "Unexpected end of type".to_string())?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("Expected `{expected}`, found `{token}`")),
+        }
+    }
+
+    /// Parse items separated by `separator` until the `close` token, which is
+    /// consumed.
+    fn parse_until<T>(
+        &mut self,
+        separator: &str,
+        close: &str,
+        mut parse_item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        if self.peek() == Some(close) {
+            self.next()?;
+            return Ok(items);
+        }
+        loop {
+            items.push(parse_item(self)?);
+            match self.next()? {
+                token if token == separator => {}
+                token if token == close => return Ok(items),
+                token => {
+                    return Err(format!(
+                        "Expected `{separator}` or `{close}`, found `{token}`"
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<QuintType, String> {
+        let token = self.next()?;
+        let ty = match token.as_str() {
+            "Bool" => QuintType::Bool,
+            "Int" => QuintType::Int,
+            "Str" => QuintType::Str,
+            "Set" | "Seq" => {
+                self.expect("(")?;
+                let elem = Box::new(self.parse_type()?);
+                self.expect(")")?;
+                if token == "Set" {
+                    QuintType::Set { elem }
+                } else {
+                    QuintType::List { elem }
+                }
+            }
+            "Variant" => {
+                self.expect("(")?;
+                let fields = self.parse_until("|", ")", |parser| {
+                    let field_name = parser.next()?.as_str().into();
+                    parser.expect("(")?;
+                    let field_type = parser.parse_type()?;
+                    parser.expect(")")?;
+                    Ok(RowField {
+                        field_name,
+                        field_type,
+                    })
+                })?;
+                QuintType::Sum {
+                    fields: Row::new(fields),
+                }
+            }
+            "<<" => {
+                let fields = self.parse_until(",", ">>", Self::parse_type)?;
+                QuintType::Tup {
+                    fields: Row::new(
+                        fields
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, field_type)| RowField {
+                                field_name: i.to_string().into(),
+                                field_type,
+                            })
+                            .collect(),
+                    ),
+                }
+            }
+            "{" => {
+                let fields = self.parse_until(",", "}", |parser| {
+                    let field_name = parser.next()?.as_str().into();
+                    parser.expect(":")?;
+                    let field_type = parser.parse_type()?;
+                    Ok(RowField {
+                        field_name,
+                        field_type,
+                    })
+                })?;
+                QuintType::Rec {
+                    fields: Row::new(fields),
+                }
+            }
+            "(" => {
+                let mut types = self.parse_until(",", ")", Self::parse_type)?;
+                if self.peek() == Some("=>") {
+                    self.next()?;
+                    let res = Box::new(self.parse_type()?);
+                    QuintType::Oper { args: types, res }
+                } else if types.len() == 1 {
+                    // A map, which is always parenthesized, or a parenthesized type
+                    types.remove(0)
+                } else {
+                    return Err("Expected `=>` after operator arguments".to_string());
+                }
+            }
+            name if name.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                QuintType::Const { name: name.into() }
+            }
+            token => return Err(format!("Unexpected `{token}` in type")),
+        };
+
+        // Maps are parsed here, so they can be written without parentheses
+        if self.peek() == Some("->") {
+            self.next()?;
+            let res = Box::new(self.parse_type()?);
+            return Ok(QuintType::Fun {
+                arg: Box::new(ty),
+                res,
+            });
+        }
+        Ok(ty)
+    }
+}
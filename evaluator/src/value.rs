@@ -7,6 +7,12 @@
 //! to pick elements from them, so instead of enumerating the set, the evaluator
 //! just generates one element corresponding to that pick.
 //!
+//! Infinite sets (`Int`, `Nat`, `allLists(S)`, and the sets built from them
+//! with `powerset`, `tuples` and `setOfMaps`) are always kept in this lazy
+//! form. We can check membership in them and sample from them (see
+//! [`Value::pick_with`]), but operators that need to enumerate them fail with
+//! an error instead.
+//!
 //! All Quint's values are immutable by nature, so the `imbl` crate's data
 //! structures are used to represent those values and properly optimize
 //! operations for immutability. This has significant performance impact.
@@ -25,6 +31,7 @@ use std::borrow::Cow;
 use std::cell::RefCell;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
 /// Quint values that hold sets are immutable, use `GenericHashSet` immutable
@@ -63,6 +70,10 @@ pub enum Value {
     CrossProduct(Vec<Value>),
     PowerSet(Rc<Value>),
     MapSet(Rc<Value>, Rc<Value>),
+    // Infinite sets, which are never enumerated
+    IntSet,
+    NatSet,
+    AllLists(Rc<Value>),
 }
 
 impl Hash for Value {
@@ -126,6 +137,10 @@ impl Hash for Value {
                 a.hash(state);
                 b.hash(state);
             }
+            Value::IntSet | Value::NatSet => {}
+            Value::AllLists(value) => {
+                value.hash(state);
+            }
         }
     }
 }
@@ -151,6 +166,11 @@ impl PartialEq for Value {
             (Value::CrossProduct(a), Value::CrossProduct(b)) => *a == *b,
             (Value::PowerSet(a), Value::PowerSet(b)) => *a == *b,
             (Value::MapSet(a1, b1), Value::MapSet(a2, b2)) => a1 == a2 && b1 == b2,
+            (Value::IntSet, Value::IntSet) => true,
+            (Value::NatSet, Value::NatSet) => true,
+            (Value::AllLists(a), Value::AllLists(b)) => *a == *b,
+            // Infinite sets can't be enumerated, and are never equal to finite ones
+            (a, b) if a.is_infinite() || b.is_infinite() => false,
             // To compare two sets represented in different ways, we need to enumerate them both
             (a, b) if a.is_set() && b.is_set() => a.as_set() == b.as_set(),
             _ => false,
@@ -197,15 +217,18 @@ impl Value {
                     && sets.iter().zip(elems).all(|(set, elem)| set.contains(elem))
             }
             (Value::PowerSet(base), Value::Set(elems)) => {
-                let base_elems = base.as_set();
-                elems.len() <= base_elems.len()
-                    && elems.iter().all(|elem| base_elems.contains(elem))
+                elems.iter().all(|elem| base.contains(elem))
             }
             (Value::MapSet(domain, range), Value::Map(map)) => {
                 let map_domain = Value::Set(map.keys().cloned().collect::<ImmutableSet<_>>());
                 // Check if domains are equal and all map values are in the range set
                 map_domain == **domain && map.values().all(|v| range.contains(v))
             }
+            (Value::IntSet, Value::Int(_)) => true,
+            (Value::NatSet, Value::Int(n)) => *n >= 0,
+            (Value::AllLists(set), Value::List(elems)) => {
+                elems.iter().all(|elem| set.contains(elem))
+            }
             _ => panic!("contains not implemented for {self:?}"),
         }
     }
@@ -230,6 +253,16 @@ impl Value {
                 Value::MapSet(subset_domain, subset_range),
                 Value::MapSet(superset_domain, superset_range),
             ) => subset_domain == superset_domain && subset_range.subseteq(superset_range),
+            // All sets of integers are subsets of `Int`, as the input is type-checked
+            (_, Value::IntSet) => true,
+            (Value::NatSet, Value::NatSet) => true,
+            (Value::AllLists(subset), Value::AllLists(superset)) => subset.subseteq(superset),
+            // An infinite set can't be a subset of a finite one, and finite
+            // subsets can be checked element by element
+            (subset, _) if subset.is_infinite() => false,
+            (subset, superset) if superset.is_infinite() => {
+                subset.as_set().iter().all(|elem| superset.contains(elem))
+            }
             // Fall back to the native implementation (`is_subset`) if no optimization is possible
             (subset, superset) => subset.as_set().is_subset(superset.as_set().as_ref()),
         }
@@ -272,9 +305,23 @@ impl Value {
                 | Value::CrossProduct(_)
                 | Value::PowerSet(_)
                 | Value::MapSet(_, _)
+                | Value::IntSet
+                | Value::NatSet
+                | Value::AllLists(_)
         )
     }
 
+    /// Checks whether a value is an infinite set, which cannot be enumerated.
+    pub fn is_infinite(&self) -> bool {
+        match self {
+            Value::IntSet | Value::NatSet | Value::AllLists(_) => true,
+            Value::PowerSet(base) => base.is_infinite(),
+            Value::CrossProduct(sets) => sets.iter().any(|set| set.is_infinite()),
+            Value::MapSet(domain, range) => domain.is_infinite() || range.is_infinite(),
+            _ => false,
+        }
+    }
+
     /// Enumerate the value as a set. Panics if the wrong type is given,
     /// which should never happen as input expressions are type-checked.
     ///
@@ -348,6 +395,9 @@ impl Value {
 
                 Cow::Owned(result_set)
             }
+            Value::IntSet | Value::NatSet | Value::AllLists(_) => {
+                panic!("Cannot enumerate infinite set {self}")
+            }
             _ => panic!("Expected set"),
         }
     }
@@ -446,6 +496,16 @@ impl fmt::Display for Value {
             Value::Int(n) => write!(f, "{n}"),
             Value::Bool(b) => write!(f, "{b}"),
             Value::Str(s) => write!(f, "{s:?}"),
+            Value::IntSet => write!(f, "Int"),
+            Value::NatSet => write!(f, "Nat"),
+            Value::AllLists(set) => write!(f, "allLists({set:#})"),
+            Value::PowerSet(base) if base.is_infinite() => write!(f, "powerset({base:#})"),
+            Value::CrossProduct(sets) if self.is_infinite() => {
+                write!(f, "tuples({:#})", sets.iter().format(", "))
+            }
+            Value::MapSet(domain, range) if self.is_infinite() => {
+                write!(f, "setOfMaps({domain:#}, {range:#})")
+            }
             Value::Set(_)
             | Value::Interval(_, _)
             | Value::CrossProduct(_)
@@ -512,3 +572,267 @@ impl fmt::Display for Value {
         }
     }
 }
+
+/// A step from a value into one of its parts, locating a [`Difference`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A record field
+    Field(QuintName),
+    /// A map key
+    Key(Value),
+    /// A list index
+    Index(usize),
+    /// A tuple element, starting at 0
+    Element(usize),
+}
+
+/// What differs at some path between two values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// The value was replaced by another one
+    Changed { old: Value, new: Value },
+    /// A record field, map key or list element that is only in the new value
+    Added(Value),
+    /// A record field, map key or list element that is only in the old value
+    Removed(Value),
+    /// An element of the new set that is not in the old one
+    ElementAdded(Value),
+    /// An element of the old set that is not in the new one
+    ElementRemoved(Value),
+}
+
+/// A difference between two values. For set elements, the path is the path of
+/// the set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub path: Vec<PathSegment>,
+    pub kind: DifferenceKind,
+}
+
+/// The differences between two values (see [`Value::diff`]).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ValueDiff {
+    pub differences: Vec<Difference>,
+}
+
+impl ValueDiff {
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl Value {
+    /// The structural differences from `self` to `other`, at the innermost
+    /// paths that differ: record fields, map keys, list indices and tuple
+    /// elements are compared recursively, and sets report the elements added
+    /// and removed. Everything else (e.g. variants, or values of different
+    /// kinds) is reported as changed as a whole.
+    ///
+    /// For lists, common prefixes and suffixes are skipped, so a removed
+    /// element is reported once instead of shifting all the following ones.
+    /// The indices of removed elements are the ones in `self`, and those of
+    /// added elements are the ones in `other`.
+    ///
+    /// Lazy sets are never enumerated to compare them, except for small
+    /// intervals. Intervals are compared by their bounds, reporting the
+    /// integers added and removed, or the change in bounds if there are too
+    /// many of them. Other lazy sets (powersets, products, sets of maps and
+    /// large intervals) are compared structurally with lazy sets of the same
+    /// kind, and reported as changed as a whole if they differ, or if they
+    /// are compared with sets of other kinds.
+    /// Differences are sorted by path, so the result is stable.
+    pub fn diff(&self, other: &Value) -> ValueDiff {
+        let mut differences = Vec::new();
+        diff_into(self, other, &mut Vec::new(), &mut differences);
+        ValueDiff { differences }
+    }
+}
+
+fn diff_into(old: &Value, new: &Value, path: &mut Vec<PathSegment>, out: &mut Vec<Difference>) {
+    match (old, new) {
+        (Value::Record(old), Value::Record(new)) => {
+            let names = old.keys().chain(new.keys()).unique().sorted();
+            for name in names {
+                path.push(PathSegment::Field(name.clone()));
+                diff_entry(old.get(name), new.get(name), path, out);
+                path.pop();
+            }
+        }
+        (Value::Map(old), Value::Map(new)) => {
+            if old == new {
+                return;
+            }
+            let keys = old
+                .keys()
+                .chain(new.keys())
+                .unique()
+                .sorted_by_cached_key(|key| key.to_string());
+            for key in keys {
+                path.push(PathSegment::Key(key.clone()));
+                diff_entry(old.get(key), new.get(key), path, out);
+                path.pop();
+            }
+        }
+        (Value::Tuple(old), Value::Tuple(new)) if old.len() == new.len() => {
+            for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                path.push(PathSegment::Element(i));
+                diff_into(old, new, path, out);
+                path.pop();
+            }
+        }
+        (Value::List(old), Value::List(new)) => {
+            let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+            let max_suffix = old.len().min(new.len()) - prefix;
+            let suffix = old
+                .iter()
+                .rev()
+                .zip(new.iter().rev())
+                .take(max_suffix)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let old_middle = prefix..old.len() - suffix;
+            let new_middle = prefix..new.len() - suffix;
+
+            // Elements in the same position are compared, and the rest of the
+            // longer list are added or removed
+            for i in prefix..old_middle.end.max(new_middle.end) {
+                path.push(PathSegment::Index(i));
+                match (old_middle.contains(&i), new_middle.contains(&i)) {
+                    (true, true) => diff_into(&old[i], &new[i], path, out),
+                    (true, false) => push(out, path, DifferenceKind::Removed(old[i].clone())),
+                    (false, true) => push(out, path, DifferenceKind::Added(new[i].clone())),
+                    (false, false) => {}
+                }
+                path.pop();
+            }
+        }
+        (Value::Interval(old_start, old_end), Value::Interval(new_start, new_end)) => {
+            let removed = outside_interval(*old_start, *old_end, *new_start, *new_end);
+            let added = outside_interval(*new_start, *new_end, *old_start, *old_end);
+            let count = removed.iter().chain(&added).map(interval_len).sum::<i128>();
+            if count > MAX_ENUMERATED as i128 {
+                // Report the change in bounds instead of each integer
+                push(
+                    out,
+                    path,
+                    DifferenceKind::Changed {
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                );
+                return;
+            }
+            for n in removed.into_iter().flatten() {
+                push(out, path, DifferenceKind::ElementRemoved(Value::Int(n)));
+            }
+            for n in added.into_iter().flatten() {
+                push(out, path, DifferenceKind::ElementAdded(Value::Int(n)));
+            }
+        }
+        (old, new) if old.is_set() && new.is_set() => {
+            let enumerable = |set: &Value| match set {
+                Value::Set(_) => true,
+                Value::Interval(start, end) => {
+                    interval_len(&(*start..=*end)) <= MAX_ENUMERATED as i128
+                }
+                _ => false,
+            };
+            if !enumerable(old) || !enumerable(new) {
+                // Only lazy sets of the same kind can be compared without
+                // enumerating them
+                if std::mem::discriminant(old) == std::mem::discriminant(new) && old == new {
+                    return;
+                }
+                push(
+                    out,
+                    path,
+                    DifferenceKind::Changed {
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                );
+                return;
+            }
+
+            if old == new {
+                return;
+            }
+            let (old, new) = (old.as_set(), new.as_set());
+            let sorted = |elems: &mut dyn Iterator<Item = &Value>| {
+                elems
+                    .cloned()
+                    .sorted_by_cached_key(|elem| elem.to_string())
+                    .collect::<Vec<_>>()
+            };
+            for elem in sorted(&mut old.iter().filter(|elem| !new.contains(*elem))) {
+                push(out, path, DifferenceKind::ElementRemoved(elem));
+            }
+            for elem in sorted(&mut new.iter().filter(|elem| !old.contains(*elem))) {
+                push(out, path, DifferenceKind::ElementAdded(elem));
+            }
+        }
+        (old, new) => {
+            if old != new {
+                push(
+                    out,
+                    path,
+                    DifferenceKind::Changed {
+                        old: old.clone(),
+                        new: new.clone(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// The most integers that are enumerated to diff intervals (see
+/// [`Value::diff`])
+const MAX_ENUMERATED: usize = 1000;
+
+/// The ranges of integers in `start..=end` that are not in
+/// `other_start..=other_end`, without going through the ones that are.
+fn outside_interval(
+    start: i64,
+    end: i64,
+    other_start: i64,
+    other_end: i64,
+) -> Vec<RangeInclusive<i64>> {
+    if other_start > other_end {
+        return vec![start..=end];
+    }
+    other_start
+        .checked_sub(1)
+        .map(|prev| start..=end.min(prev))
+        .into_iter()
+        .chain(other_end.checked_add(1).map(|next| start.max(next)..=end))
+        .collect()
+}
+
+/// How many integers are in `range`, which may be empty
+fn interval_len(range: &RangeInclusive<i64>) -> i128 {
+    (*range.end() as i128 - *range.start() as i128 + 1).max(0)
+}
+
+fn push(out: &mut Vec<Difference>, path: &[PathSegment], kind: DifferenceKind) {
+    out.push(Difference {
+        path: path.to_vec(),
+        kind,
+    });
+}
+
+/// Compare an entry of a record or map, which may be missing on either side.
+fn diff_entry(
+    old: Option<&Value>,
+    new: Option<&Value>,
+    path: &mut Vec<PathSegment>,
+    out: &mut Vec<Difference>,
+) {
+    let kind = match (old, new) {
+        (Some(old), Some(new)) => return diff_into(old, new, path, out),
+        (Some(old), None) => DifferenceKind::Removed(old.clone()),
+        (None, Some(new)) => DifferenceKind::Added(new.clone()),
+        (None, None) => return,
+    };
+    push(out, path, kind);
+}
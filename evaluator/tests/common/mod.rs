@@ -0,0 +1,117 @@
+//! Builders for Quint IR expressions in JSON, shared by the integration tests
+//! that add definitions or properties to a parsed fixture.
+
+// Each test crate only uses some of the builders
+#![allow(dead_code)]
+
+use quint_evaluator::ir::{LookupDefinition, LookupTable, QuintId};
+use serde_json::{json, Value};
+
+/// Builds IR expressions over the lookup table of a parsed fixture. Every node
+/// gets a fresh id, and names are resolved by looking up the definition with
+/// that name in the table, so tests don't depend on the ids in the fixture.
+pub struct IrBuilder<'a> {
+    table: &'a mut LookupTable,
+    next_id: QuintId,
+}
+
+impl<'a> IrBuilder<'a> {
+    pub fn new(table: &'a mut LookupTable) -> Self {
+        let next_id = table
+            .iter()
+            .map(|(id, def)| (*id).max(max_id(&serde_json::to_value(def).unwrap())))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        Self { table, next_id }
+    }
+
+    pub fn fresh_id(&mut self) -> QuintId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// A reference to the definition called `name`, which is added to the
+    /// table under the id of the reference.
+    pub fn name(&mut self, name: &str) -> Value {
+        let mut defs = self
+            .table
+            .values()
+            .filter(|def| def.name().as_str() == name)
+            .map(|def| (def.id(), def.clone()))
+            .collect::<Vec<_>>();
+        defs.sort_by_key(|(id, _)| *id);
+        defs.dedup_by_key(|(id, _)| *id);
+        let def = match defs.as_slice() {
+            [(_, def)] => def.clone(),
+            [] => panic!("no definition named {name} in the table"),
+            _ => panic!("more than one definition named {name} in the table"),
+        };
+
+        let id = self.fresh_id();
+        self.table.insert(id, def);
+        json!({ "id": id, "kind": "name", "name": name })
+    }
+
+    pub fn app(&mut self, opcode: &str, args: Vec<Value>) -> Value {
+        json!({ "id": self.fresh_id(), "kind": "app", "opcode": opcode, "args": args })
+    }
+
+    pub fn bool(&mut self, value: bool) -> Value {
+        json!({ "id": self.fresh_id(), "kind": "bool", "value": value })
+    }
+
+    pub fn int(&mut self, value: i64) -> Value {
+        json!({ "id": self.fresh_id(), "kind": "int", "value": value })
+    }
+
+    /// `param => body`, as an operator argument
+    pub fn lambda(&mut self, param: &str, body: Value) -> Value {
+        json!({
+            "id": self.fresh_id(),
+            "kind": "lambda",
+            "params": [{ "id": self.fresh_id(), "name": param }],
+            "qualifier": "def",
+            "expr": body,
+        })
+    }
+
+    /// A definition, added to the table so that [`IrBuilder::name`] finds it
+    pub fn def(&mut self, name: &str, qualifier: &str, expr: Value) -> Value {
+        let def = json!({
+            "id": self.fresh_id(),
+            "kind": "def",
+            "name": name,
+            "qualifier": qualifier,
+            "expr": expr,
+        });
+        let id = def["id"].as_u64().unwrap();
+        self.table.insert(
+            id,
+            LookupDefinition::Definition(serde_json::from_value(def.clone()).unwrap()),
+        );
+        def
+    }
+
+    /// `opdef; expr`, where `opdef` is built with [`IrBuilder::def`]
+    pub fn let_in(&mut self, opdef: Value, expr: Value) -> Value {
+        json!({ "id": self.fresh_id(), "kind": "let", "opdef": opdef, "expr": expr })
+    }
+}
+
+/// The largest id in a JSON-encoded IR node
+fn max_id(node: &Value) -> QuintId {
+    match node {
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| match (key.as_str(), value) {
+                ("id", Value::Number(id)) => id.as_u64().unwrap_or(0),
+                _ => max_id(value),
+            })
+            .max()
+            .unwrap_or(0),
+        Value::Array(values) => values.iter().map(max_id).max().unwrap_or(0),
+        _ => 0,
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::itf::{ItfContext, Trace};
+use quint_evaluator::simulator::{ParsedQuint, SimulationConfig};
+use quint_evaluator::value::{Difference, DifferenceKind, PathSegment, Value};
+
+fn tictactoe() -> ParsedQuint {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap()
+}
+
+/// A trace simulated from the spec, with or without the metadata for
+/// model-based testing.
+fn simulated_trace(parsed: &ParsedQuint, store_metadata: bool) -> Trace {
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(1)
+                .traces(1)
+                .store_metadata(store_metadata),
+            None,
+        )
+        .unwrap();
+    result.best_traces[0].clone()
+}
+
+#[test]
+fn simulated_traces_conform() {
+    let parsed = tictactoe();
+
+    for store_metadata in [true, false] {
+        let trace = simulated_trace(&parsed, store_metadata);
+        let states = trace.states.len();
+        let itf_trace = trace
+            .to_itf(&ItfContext::from_source("tictactoe.qnt".to_string()))
+            .unwrap();
+
+        let result = parsed.check_conformance(&itf_trace).unwrap();
+        assert!(result.conforms());
+        assert_eq!(result.conforming_states, states);
+    }
+}
+
+#[test]
+fn reports_first_non_conforming_state() {
+    let parsed = tictactoe();
+    let mut trace = simulated_trace(&parsed, true);
+
+    // Keep the turn unchanged after the first move, which no move does
+    let Value::Record(fields) = &trace.states[1] else {
+        unreachable!()
+    };
+    let mut fields = fields.clone();
+    let next_turn = fields["nextTurn"].clone();
+    let expected_turn = trace.states[0].as_record_map()["nextTurn"].clone();
+    fields.insert("nextTurn".into(), expected_turn.clone());
+    trace.states[1] = Value::Record(fields);
+
+    let result = parsed.check_conformance_of_states(&trace.states).unwrap();
+    assert!(!result.conforms());
+    assert_eq!(result.conforming_states, 1);
+
+    let failure = result.failure.unwrap();
+    assert_eq!(failure.index, 1);
+    assert!(failure.action.is_some());
+    assert!(failure.closest.is_some());
+    assert_eq!(
+        failure.diff.differences,
+        [Difference {
+            path: vec![PathSegment::Field("nextTurn".into())],
+            kind: DifferenceKind::Changed {
+                old: expected_turn,
+                new: next_turn,
+            },
+        }]
+    );
+}
+
+#[test]
+fn reports_paths_to_differences() {
+    let parsed = tictactoe();
+    let mut trace = simulated_trace(&parsed, true);
+
+    // Pass the turn to O without X taking a square
+    let Value::Record(fields) = &trace.states[1] else {
+        unreachable!()
+    };
+    let mut fields = fields.clone();
+    fields.insert(
+        "board".into(),
+        trace.states[0].as_record_map()["board"].clone(),
+    );
+    trace.states[1] = Value::Record(fields);
+
+    let result = parsed.check_conformance_of_states(&trace.states).unwrap();
+    let failure = result.failure.unwrap();
+    assert_eq!(failure.index, 1);
+    // Only the square that X took differs, inside the board
+    let [difference] = failure.diff.differences.as_slice() else {
+        panic!("expected a single difference: {:?}", failure.diff)
+    };
+    assert!(matches!(
+        difference.path.as_slice(),
+        [PathSegment::Field(name), PathSegment::Key(_), PathSegment::Key(_)] if name == "board"
+    ));
+}
+
+#[test]
+fn reports_non_conforming_initial_state() {
+    let parsed = tictactoe();
+    let trace = simulated_trace(&parsed, false);
+
+    // The second state has a move on the board already
+    let result = parsed
+        .check_conformance_of_states(&trace.states[1..])
+        .unwrap();
+    assert_eq!(result.conforming_states, 0);
+    assert_eq!(result.failure.unwrap().index, 0);
+}
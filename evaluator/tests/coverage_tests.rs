@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use quint_evaluator::coverage::CoverageKind;
+use quint_evaluator::helpers;
+use quint_evaluator::simulator::SimulationConfig;
+
+#[test]
+fn tictactoe_coverage() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(100)
+                .traces(0)
+                .collect_coverage(true),
+            None,
+        )
+        .unwrap();
+    let coverage = result.coverage.unwrap();
+
+    // Both players get to move
+    let enabled = |label: &str| {
+        coverage
+            .points
+            .iter()
+            .any(|p| p.kind == CoverageKind::Action && p.label == label && p.hits > 0)
+    };
+    assert!(enabled("MoveX"));
+    assert!(enabled("MoveO"));
+    assert!(coverage.actions_never_enabled.is_empty());
+
+    // Only the invariant being checked runs, along with everything it uses
+    let never_run = coverage
+        .definitions_never_run
+        .iter()
+        .map(|p| p.label.as_str())
+        .collect::<Vec<_>>();
+    assert!(never_run.contains(&"XHasNotWon"));
+    assert!(never_run.contains(&"XMustEventuallyWin"));
+    assert!(!never_run.contains(&"OHasNotWon"));
+    assert!(!never_run.contains(&"won"));
+    assert!(!never_run.contains(&"q::step"));
+
+    // Cases of `matchVariant` are instrumented as branches
+    let (taken, total) = coverage.ratio(CoverageKind::Branch);
+    assert!(coverage
+        .points
+        .iter()
+        .any(|p| p.kind == CoverageKind::Branch && p.label == "match case Occupied"));
+    assert_eq!(total - taken, coverage.branches_never_taken.len());
+}
+
+#[test]
+fn coverage_is_opt_in() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed.simulate(10, 10, 0, None).unwrap();
+
+    assert!(result.coverage.is_none());
+}
@@ -0,0 +1,188 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+use quint_evaluator::debugger::{
+    Breakpoint, CommandLineFrontend, DebugCommand, DebugFrontend, Debugger, Pause, PauseReason,
+};
+use quint_evaluator::helpers;
+use quint_evaluator::ir::{QuintId, QuintName};
+use quint_evaluator::simulator::ParsedQuint;
+
+fn tictactoe() -> ParsedQuint {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap()
+}
+
+/// What a frontend saw in a pause
+#[derive(Debug, Clone)]
+struct Seen {
+    reason: PauseReason,
+    id: QuintId,
+    depth: usize,
+    branch: Option<(usize, usize)>,
+    stack: Vec<QuintName>,
+    vars: Vec<QuintName>,
+    params: Vec<QuintName>,
+}
+
+/// Replies with the given commands, then aborts
+struct Scripted {
+    commands: Vec<DebugCommand>,
+    seen: Rc<RefCell<Vec<Seen>>>,
+}
+
+impl DebugFrontend for Scripted {
+    fn paused(&mut self, pause: &Pause, _breakpoints: &mut Vec<Breakpoint>) -> DebugCommand {
+        self.seen.borrow_mut().push(Seen {
+            reason: pause.reason,
+            id: pause.id,
+            depth: pause.depth,
+            branch: pause.branch,
+            stack: pause.stack.to_vec(),
+            vars: pause
+                .vars
+                .iter()
+                .filter(|(_, value)| value.is_some())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            params: pause.params.iter().map(|(name, _)| name.clone()).collect(),
+        });
+        if self.commands.is_empty() {
+            DebugCommand::Abort
+        } else {
+            self.commands.remove(0)
+        }
+    }
+}
+
+/// Output that can be read after the frontend is moved into the debugger
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn debug(breakpoints: Vec<Breakpoint>, commands: Vec<DebugCommand>) -> Vec<Seen> {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let frontend = Scripted {
+        commands,
+        seen: seen.clone(),
+    };
+    let _ = tictactoe().debug(3, 7, Debugger::new(frontend, breakpoints));
+    seen.take()
+}
+
+#[test]
+fn pauses_at_definition_breakpoints() {
+    let seen = debug(
+        vec![Breakpoint::Definition("MoveX".into())],
+        vec![DebugCommand::Continue],
+    );
+
+    // X only moves in the first and third steps
+    assert_eq!(seen.len(), 2);
+    for pause in &seen {
+        assert_eq!(pause.reason, PauseReason::Breakpoint);
+        assert_eq!(pause.stack.last(), Some(&"MoveX".into()));
+        // The state is set after init
+        assert_eq!(pause.vars, vec!["board", "nextTurn"]);
+    }
+}
+
+#[test]
+fn pauses_at_id_breakpoints() {
+    let seen = debug(vec![Breakpoint::Definition("MoveX".into())], Vec::new());
+    let id = seen[0].id;
+
+    let seen = debug(vec![Breakpoint::Id(id)], vec![DebugCommand::Continue]);
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].id, id);
+}
+
+#[test]
+fn steps_in_over_and_out() {
+    let seen = debug(
+        Vec::new(),
+        vec![
+            DebugCommand::StepIn,
+            DebugCommand::StepIn,
+            DebugCommand::StepOver,
+            DebugCommand::StepOut,
+        ],
+    );
+
+    // Paused at the start, without breakpoints
+    assert_eq!(seen[0].reason, PauseReason::Step);
+    assert_eq!(seen[0].depth, 1);
+    assert!(seen[0].vars.is_empty());
+    // Stepping in goes one expression deeper
+    assert_eq!(seen[1].depth, 2);
+    assert_eq!(seen[2].depth, 3);
+    // Stepping over stays at most at the same depth
+    assert!(seen[3].depth <= 3);
+    // Stepping out leaves the enclosing expression
+    assert!(seen[4].depth < seen[3].depth);
+}
+
+#[test]
+fn shows_lambda_parameters_in_scope() {
+    let seen = debug(Vec::new(), vec![DebugCommand::StepIn; 2000]);
+    let with_params = seen
+        .iter()
+        .find(|pause| !pause.params.is_empty())
+        .expect("no lambda parameters were bound");
+
+    // Parameters are only shown within their lambda
+    assert!(seen
+        .iter()
+        .filter(|pause| pause.depth < with_params.depth - 1)
+        .all(|pause| pause.params.iter().all(|p| !with_params.params.contains(p))));
+}
+
+#[test]
+fn tells_which_actionany_branch_is_tried() {
+    let seen = debug(Vec::new(), vec![DebugCommand::StepIn; 2000]);
+    let branches = seen
+        .iter()
+        .filter_map(|pause| pause.branch)
+        .collect::<Vec<_>>();
+
+    assert!(!branches.is_empty(), "no actionAny branch was tried");
+    assert!(branches.iter().all(|(index, count)| index < count));
+}
+
+#[test]
+fn runs_commands_from_the_terminal() {
+    let input = b"bt\nv\nb 424242\nl\nd MoveX\nc\n";
+    let output = SharedOutput::default();
+    let frontend = CommandLineFrontend::new(&input[..], output.clone());
+    let debugger = Debugger::new(frontend, vec![Breakpoint::Definition("MoveX".into())]);
+    let states = tictactoe().debug(3, 7, debugger).unwrap();
+    assert_eq!(states.len(), 4);
+
+    let output = String::from_utf8(output.0.take()).unwrap();
+    // The breakpoint was deleted, so there is only one pause
+    assert_eq!(output.matches("Breakpoint at #").count(), 1, "{output}");
+    assert!(output.contains(" in MoveX\n"));
+    assert!(output.contains("  0: MoveX\n"));
+    assert!(output.contains("vars:\n  board = Map("));
+    assert!(output.contains("next vars:\n  board (not set)\n"));
+    assert!(output.contains("Breakpoint set on #424242\n"));
+    assert!(output.contains("(debug)   MoveX\n  #424242\n"));
+}
+
+#[test]
+fn aborts_when_input_ends() {
+    let frontend = CommandLineFrontend::new(&b""[..], SharedOutput::default());
+    let result = tictactoe().debug(3, 7, Debugger::new(frontend, Vec::new()));
+    assert_eq!(result.unwrap_err().code, "QNT521");
+}
@@ -1248,3 +1248,109 @@ fn run_q_debug_single_arg() -> Result<(), Box<dyn std::error::Error>> {
          run run1 = (n' = 1).then(n' = q::debug(n + 1))",
     )
 }
+
+#[test]
+fn run_enabled() -> Result<(), Box<dyn std::error::Error>> {
+    assert_var_after_run(
+        "n",
+        "0",
+        "run1",
+        "var n: int\n
+         run run1 = (n' = 0).then(all {
+           assert(enabled(n' = n + 1)),
+           assert(not(enabled(all { n > 0, n' = n + 1 }))),
+           n' = n,
+         })",
+    )
+}
+
+#[test]
+fn run_or_keep() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "var n: int\n
+                 action incrementPositive = all { n > 0, n' = n + 1 }\n
+                 run run1 = (n' = 0).then(incrementPositive.orKeep(n))\n
+                 run run2 = (n' = 1).then(incrementPositive.orKeep(n))";
+    assert_var_after_run("n", "0", "run1", input)?;
+    assert_var_after_run("n", "2", "run2", input)
+}
+
+#[test]
+fn run_must_change() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "var n: int\n
+                 run run1 = (n' = 0).then((n' = n + 1).mustChange(n))\n
+                 run run2 = (n' = 0).then((n' = n).mustChange(n))";
+    assert_var_after_run("n", "1", "run1", input)?;
+    assert_eq!(eval_run("run2", input), Ok(Value::Bool(false)));
+
+    Ok(())
+}
+
+#[test]
+fn choose_some() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string(
+        "Set(1, 2, 3).chooseSome() == Set(3, 2, 1).chooseSome()",
+        "true",
+    )?;
+    assert_from_string("Set(1, 2, 3).contains(Set(1, 2, 3).chooseSome())", "true")?;
+    assert_from_string("1.to(10).chooseSome().in(1.to(10))", "true")?;
+    assert_from_string("Set().chooseSome()", "undefined")
+}
+
+#[test]
+fn infinite_sets_membership() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string("Int.contains(-5)", "true")?;
+    assert_from_string("Nat.contains(5)", "true")?;
+    assert_from_string("Nat.contains(-5)", "false")?;
+    assert_from_string("allLists(Set(1, 2)).contains([1, 2, 1])", "true")?;
+    assert_from_string("allLists(Set(1, 2)).contains([3])", "false")?;
+    assert_from_string("Nat.powerset().contains(Set(1, 100))", "true")?;
+    assert_from_string("Nat.powerset().contains(Set(-1))", "false")?;
+    assert_from_string("tuples(Int, Nat).contains((-1, 1))", "true")?;
+    assert_from_string("setOfMaps(Set(1), Nat).contains(Map(1 -> 2))", "true")
+}
+
+#[test]
+fn infinite_sets_subseteq() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string("Set(-1, 2).subseteq(Int)", "true")?;
+    assert_from_string("Set(-1, 2).subseteq(Nat)", "false")?;
+    assert_from_string("1.to(10).subseteq(Nat)", "true")?;
+    assert_from_string("Nat.subseteq(Int)", "true")?;
+    assert_from_string("Int.subseteq(Nat)", "false")?;
+    assert_from_string("Nat.subseteq(1.to(10))", "false")?;
+    assert_from_string("allLists(Set(1)).subseteq(allLists(Set(1, 2)))", "true")
+}
+
+#[test]
+fn infinite_sets_cannot_be_enumerated() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string("Int.isFinite()", "false")?;
+    assert_from_string("Nat.powerset().isFinite()", "false")?;
+    assert_from_string("Set(1, 2).isFinite()", "true")?;
+    assert_from_string("Int == Int", "true")?;
+    assert_from_string("Nat == Set(1, 2)", "false")?;
+    assert_from_string("Int.size()", "undefined")?;
+    assert_from_string("Nat.map(x => x + 1)", "undefined")?;
+    assert_from_string("Int.exists(x => x > 0)", "undefined")?;
+    assert_from_string("Set(Nat, Set(1)).flatten()", "undefined")?;
+    assert_from_string("allLists(Set(1)).union(Set([2]))", "undefined")
+}
+
+#[test]
+fn run_one_of_infinite_sets() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "var n: int\n
+                 var l: List[int]\n
+                 run run1 = {
+                   nondet x = Nat.oneOf()
+                   nondet xs = allLists(1.to(3)).oneOf()
+                   all {
+                     assert(x >= 0),
+                     assert(xs.foldl(true, (acc, e) => acc and 1 <= e and e <= 3)),
+                     n' = x,
+                     l' = xs,
+                   }
+                 }";
+    let result = eval_run("run1", input);
+
+    assert_eq!(result, Ok(Value::Bool(true)));
+
+    Ok(())
+}
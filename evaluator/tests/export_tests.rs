@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use quint_evaluator::export::OutFormat;
+use quint_evaluator::helpers;
+use quint_evaluator::itf::{ItfContext, Trace};
+use quint_evaluator::simulator::SimulationConfig;
+use quint_evaluator::value::Value;
+
+fn simulated_trace(store_metadata: bool) -> Trace {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(3)
+                .samples(1)
+                .traces(1)
+                .store_metadata(store_metadata),
+            None,
+        )
+        .unwrap();
+    result.best_traces[0].clone()
+}
+
+fn export(format: OutFormat, trace: &Trace) -> String {
+    format
+        .exporter()
+        .export(trace, &ItfContext::default())
+        .unwrap()
+}
+
+#[test]
+fn exports_tla_text() {
+    let trace = simulated_trace(true);
+    let text = export(OutFormat::Tla, &trace);
+
+    assert!(text.starts_with("State 1: <init>\n/\\ board = ("));
+    assert!(text.contains("/\\ nextTurn = Variant(\"X\", <<>>)\n"));
+    assert!(text.contains("State 2: <MoveX>\n"));
+    // Metadata is only used for the labels
+    assert!(!text.contains("mbt::"));
+    assert_eq!(text.matches("State ").count(), trace.states.len());
+}
+
+#[test]
+fn exports_one_json_object_per_state() {
+    let trace = simulated_trace(false);
+    let jsonl = export(OutFormat::Jsonl, &trace);
+
+    let lines = jsonl.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), trace.states.len());
+    for (i, line) in lines.into_iter().enumerate() {
+        let state: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(state["#meta"]["index"], i);
+        assert!(state.get("board").is_some());
+    }
+}
+
+#[test]
+fn exports_graphviz_with_actions() {
+    let trace = simulated_trace(true);
+    let dot = export(OutFormat::Dot, &trace);
+
+    assert!(dot.starts_with("digraph trace {"));
+    assert!(dot.contains("s0 -> s1 [label=\""));
+    assert_eq!(dot.matches(" -> ").count(), trace.states.len() - 1);
+
+    // Without metadata, there are no labels
+    let dot = export(OutFormat::Dot, &simulated_trace(false));
+    assert!(dot.contains("s0 -> s1;"));
+}
+
+#[test]
+fn writes_values_in_tla_syntax() {
+    let state = Value::Record(
+        [
+            (
+                "s".into(),
+                Value::Set([Value::Int(2), Value::Int(1)].into_iter().collect()),
+            ),
+            (
+                "t".into(),
+                Value::Tuple(vec![Value::Bool(true), Value::Str("a".into())].into()),
+            ),
+            ("m".into(), Value::Map(Default::default())),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    let trace = Trace {
+        states: vec![state],
+        violation: true,
+        deadlock: false,
+        loop_index: Some(0),
+        choices: Vec::new(),
+        seed: None,
+        sample: None,
+        violated_invariant: Some("Inv".into()),
+    };
+
+    assert_eq!(
+        export(OutFormat::Tla, &trace),
+        "Error: Invariant Inv is violated.\n\
+         Error: The behavior up to this point is:\n\
+         State 1: <Initial predicate>\n\
+         /\\ m = <<>>\n\
+         /\\ s = {1, 2}\n\
+         /\\ t = <<TRUE, \"a\">>\n\
+         \n\
+         State 2: Back to state 1\n"
+    );
+}
+
+#[test]
+fn parses_formats() {
+    assert_eq!("jsonl".parse::<OutFormat>(), Ok(OutFormat::Jsonl));
+    assert_eq!(OutFormat::default().exporter().extension(), "itf.json");
+    assert!("csv".parse::<OutFormat>().is_err());
+}
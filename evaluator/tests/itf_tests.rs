@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use fxhash::FxHashMap;
+use quint_evaluator::helpers;
+use quint_evaluator::itf::{ItfContext, Trace};
+use quint_evaluator::simulator::SimulationConfig;
+use quint_evaluator::types::{var_types, QuintType};
+use quint_evaluator::value::Value;
+use serde_json::json;
+
+fn itf_value(value: serde_json::Value) -> itf::Value {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn simulated_traces_round_trip() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(1)
+                .traces(1)
+                .store_metadata(true),
+            None,
+        )
+        .unwrap();
+    let trace = result.best_traces[0].clone();
+
+    let itf_trace = trace
+        .clone()
+        .to_itf(&ItfContext::from_source("tictactoe.qnt".to_string()))
+        .unwrap();
+    let read = Trace::from_itf(&itf_trace, &var_types(&parsed.table)).unwrap();
+    assert_eq!(read.states, trace.states);
+    assert_eq!(read.violation, trace.violation);
+}
+
+#[test]
+fn writes_types_and_run_metadata() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "NotStalemate", None).unwrap();
+    // Stalemates are rare, this seed finds one early
+    let config = SimulationConfig::default().samples(1000).seed(8);
+    let result = parsed.simulate_with(&config, None).unwrap();
+    assert!(!result.result);
+    let mut trace = result.best_traces[0].clone();
+    trace.violated_invariant = Some("NotStalemate".into());
+
+    let context = ItfContext {
+        source: "tictactoe.qnt".to_string(),
+        var_types: var_types(&parsed.table),
+        params: vec![("N".into(), Value::Int(3))],
+    };
+    let itf_trace = trace.clone().to_itf(&context).unwrap();
+    let meta = &itf_trace.meta;
+    assert_eq!(meta.var_types["nextTurn"], "Variant(X(<<>>) | O(<<>>))");
+    assert!(meta.var_types["board"].starts_with("(Int -> (Int -> Variant("));
+    assert_eq!(meta.other["seed"], format!("0x{:x}", trace.seed.unwrap()));
+    assert_eq!(meta.other["sample"], result.samples.to_string());
+    assert_eq!(meta.other["invariant"], "NotStalemate");
+    assert_eq!(itf_trace.params, vec!["N".to_string()]);
+    assert!(itf_trace
+        .states
+        .iter()
+        .all(|state| matches!(&state.value, itf::Value::Record(r) if r.contains_key("N"))));
+
+    // Parameters are not read back as state variables
+    let read = Trace::from_itf(&itf_trace, &FxHashMap::default()).unwrap();
+    assert_eq!(read.states, trace.states);
+    assert_eq!(read.seed, trace.seed);
+    assert_eq!(read.sample, trace.sample);
+    assert_eq!(read.violated_invariant, trace.violated_invariant);
+}
+
+#[test]
+fn cannot_write_empty_traces() {
+    let trace = Trace {
+        states: Vec::new(),
+        violation: false,
+        deadlock: false,
+        loop_index: None,
+        choices: Vec::new(),
+        seed: None,
+        sample: None,
+        violated_invariant: None,
+    };
+    let error = trace.to_itf(&ItfContext::default()).unwrap_err();
+    assert_eq!(error.code, "QNT520");
+}
+
+#[test]
+fn types_tell_records_and_variants_apart() {
+    let value = itf_value(json!({ "tag": "A", "value": 1 }));
+
+    // Without a type, this looks like a variant
+    assert!(matches!(
+        Value::from_itf(&value).unwrap(),
+        Value::Variant(..)
+    ));
+
+    let record: QuintType = "{ tag: Str, value: Int }".parse().unwrap();
+    let converted = Value::from_itf_with_type(&value, &record).unwrap();
+    assert_eq!(converted.as_record_map()["value"], Value::Int(1));
+
+    let variant: QuintType = "Variant(A(Int) | B(<<>>))".parse().unwrap();
+    let converted = Value::from_itf_with_type(&value, &variant).unwrap();
+    assert!(matches!(converted, Value::Variant(label, _) if label == "A"));
+
+    // The tag must be one of the sum type's
+    let value = itf_value(json!({ "tag": "C", "value": 1 }));
+    assert!(Value::from_itf_with_type(&value, &variant).is_err());
+}
+
+#[test]
+fn converts_collections() {
+    let value = itf_value(json!({
+        "#map": [[{ "#bigint": "1" }, { "#set": [{ "#tup": ["a", true] }] }]]
+    }));
+    let ty: QuintType = "(Int -> Set(<<Str, Bool>>))".parse().unwrap();
+    let converted = Value::from_itf_with_type(&value, &ty).unwrap();
+    assert_eq!(converted, Value::from_itf(&value).unwrap());
+    // Integers are written back as numbers when they fit
+    let written = itf_value(json!({ "#map": [[1, { "#set": [{ "#tup": ["a", true] }] }]] }));
+    assert_eq!(converted.to_itf(), written);
+
+    let lists = itf_value(json!([[1, 2], []]));
+    let ty: QuintType = "Seq(Seq(Int))".parse().unwrap();
+    assert_eq!(
+        Value::from_itf_with_type(&lists, &ty).unwrap().to_itf(),
+        lists
+    );
+
+    // Values must have the given type
+    assert!(Value::from_itf_with_type(&lists, &QuintType::Int).is_err());
+}
+
+#[test]
+fn rejects_integers_out_of_range() {
+    let value = itf_value(json!({ "#bigint": "100000000000000000000" }));
+    let error = Value::from_itf(&value).unwrap_err();
+    assert_eq!(error.code, "QNT520");
+}
+
+#[test]
+fn prints_and_parses_types() {
+    for ty in [
+        "Int",
+        "Set(Seq(Str))",
+        "(Int -> (Int -> Variant(Occupied(Variant(O(<<>>) | X(<<>>))) | Empty(<<>>))))",
+        "<<Int, Bool>>",
+        "{ a: Int, b: Set(PROC) }",
+        "{}",
+        "(Int, Str) => Bool",
+    ] {
+        let parsed: QuintType = ty.parse().unwrap();
+        assert_eq!(parsed.to_string(), ty);
+    }
+
+    // Maps don't need to be parenthesized
+    let map: QuintType = "Int -> Str".parse().unwrap();
+    assert_eq!(map.to_string(), "(Int -> Str)");
+
+    assert!("Set(Int".parse::<QuintType>().is_err());
+    assert!("<<Int>> Int".parse::<QuintType>().is_err());
+}
@@ -0,0 +1,63 @@
+use std::fs::File;
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::ir::QuintOutput;
+
+#[test]
+fn tictactoe_ok() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed.check(None).unwrap();
+
+    // Should explore everything without finding a violation
+    assert!(result.result);
+    assert!(result.counterexample.is_none());
+    assert!(result.statistics.complete);
+    assert!(result.statistics.distinct_states > 1);
+    assert!(result.statistics.transitions >= result.statistics.distinct_states);
+}
+
+#[test]
+fn tictactoe_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "XHasNotWon", None).unwrap();
+    let result = parsed.check(None).unwrap();
+
+    assert!(!result.result);
+    let trace = result.counterexample.unwrap();
+    assert!(trace.violation);
+    // X needs at least three moves to win, with two moves from O in between,
+    // and breadth-first search finds the shortest counterexample
+    assert_eq!(trace.states.len(), 6);
+}
+
+#[test]
+fn tictactoe_bounded() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed.check(Some(2)).unwrap();
+
+    assert!(result.result);
+    assert!(!result.statistics.complete);
+    assert_eq!(result.statistics.max_depth, 2);
+}
+
+#[test]
+fn tictactoe_named_invariant_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let output: QuintOutput = serde_json::from_reader(File::open(file_path).unwrap()).unwrap();
+
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    parsed.invariants = output.find_invariants(&["XHasNotWon"]).unwrap();
+    let result = parsed.check(None).unwrap();
+
+    // The named invariants are checked along with `inv`
+    assert!(!result.result);
+    assert_eq!(result.counterexample.unwrap().states.len(), 6);
+}
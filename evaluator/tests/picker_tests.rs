@@ -1,7 +1,13 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use quint_evaluator::{
+    choices::ChoiceSequence,
     evaluator::{Env, Interpreter},
     helpers,
-    value::Value,
+    picker::SamplingBounds,
+    storage::Storage,
+    value::{ImmutableSet, ImmutableVec, Value},
 };
 
 macro_rules! run_test {
@@ -55,3 +61,141 @@ fn interval_pick_test() -> Result<(), Box<dyn std::error::Error>> {
 
     run_test!(quint_content, Value::Int(5))
 }
+
+/// An environment with a fixed seed and small sampling bounds, to pick from
+/// infinite sets without going through the parser.
+fn sampling_env() -> Env {
+    let mut env = Env::with_rand_state(Rc::new(RefCell::new(Storage::default())), 0x42);
+    env.sampling = SamplingBounds {
+        min_int: -5,
+        max_int: 5,
+        max_size: 3,
+    };
+    env
+}
+
+#[test]
+fn infinite_int_and_nat_pick_test() {
+    let mut env = sampling_env();
+
+    for _ in 0..100 {
+        let int = Value::IntSet.pick_with(&mut env).unwrap().as_int();
+        assert!((-5..=5).contains(&int));
+
+        let nat = Value::NatSet.pick_with(&mut env).unwrap().as_int();
+        assert!((0..=5).contains(&nat));
+    }
+}
+
+#[test]
+fn all_lists_pick_test() {
+    let mut env = sampling_env();
+    let set = Value::Set(ImmutableSet::from(vec![Value::Int(1), Value::Int(2)]));
+    let all_lists = Value::AllLists(Rc::new(set.clone()));
+
+    for _ in 0..100 {
+        let list = all_lists.pick_with(&mut env).unwrap();
+        assert!(list.as_list().len() <= 3);
+        assert!(all_lists.contains(&list));
+    }
+
+    // The only list with elements from the empty set is the empty list
+    let empty_lists = Value::AllLists(Rc::new(Value::Set(ImmutableSet::default())));
+    assert_eq!(
+        empty_lists.pick_with(&mut env).unwrap(),
+        Value::List(ImmutableVec::default())
+    );
+}
+
+#[test]
+fn infinite_powerset_tuples_and_maps_pick_test() {
+    let mut env = sampling_env();
+    let nat_powerset = Value::PowerSet(Rc::new(Value::NatSet));
+    let tuples = Value::CrossProduct(vec![Value::IntSet, Value::Interval(1, 2)]);
+    let maps = Value::MapSet(Rc::new(Value::Interval(1, 2)), Rc::new(Value::NatSet));
+
+    for _ in 0..100 {
+        let set = nat_powerset.pick_with(&mut env).unwrap();
+        assert!(set.cardinality() <= 3);
+        assert!(nat_powerset.contains(&set));
+
+        let tuple = tuples.pick_with(&mut env).unwrap();
+        assert!(tuples.contains(&tuple));
+
+        let map = maps.pick_with(&mut env).unwrap();
+        assert!(maps.contains(&map));
+    }
+
+    let maps_from_nat = Value::MapSet(Rc::new(Value::NatSet), Rc::new(Value::Interval(1, 2)));
+    assert_eq!(
+        maps_from_nat.pick_with(&mut env).unwrap_err().code,
+        "QNT501"
+    );
+}
+
+#[test]
+fn infinite_set_pick_with_exhaustive_choices_test() {
+    let mut env = sampling_env();
+    env.choices = Some(ChoiceSequence::exhaustive());
+
+    assert_eq!(
+        Value::IntSet.pick_with(&mut env).unwrap_err().code,
+        "QNT501"
+    );
+    assert_eq!(
+        Value::Interval(1, 3).pick_with(&mut env).unwrap(),
+        Value::Int(1)
+    );
+}
+
+#[test]
+fn empty_int_range_is_rejected_test() {
+    let bounds = SamplingBounds {
+        min_int: 1,
+        max_int: 0,
+        max_size: 3,
+    };
+    assert_eq!(bounds.validate().unwrap_err().code, "QNT522");
+
+    let mut env = sampling_env();
+    env.sampling = bounds;
+    assert_eq!(
+        Value::IntSet.pick_with(&mut env).unwrap_err().code,
+        "QNT522"
+    );
+}
+
+#[test]
+fn nat_pick_with_negative_max_int_test() {
+    let mut env = sampling_env();
+    env.sampling.max_int = -1;
+    assert!(env.sampling.validate().is_ok());
+
+    assert!(Value::IntSet.pick_with(&mut env).unwrap().as_int() < 0);
+    assert_eq!(
+        Value::NatSet.pick_with(&mut env).unwrap_err().code,
+        "QNT522"
+    );
+}
+
+#[test]
+fn int_pick_at_the_extremes_test() {
+    // One more integer than there are choices
+    let bounds = SamplingBounds {
+        min_int: i64::MIN,
+        max_int: i64::MAX,
+        max_size: 3,
+    };
+    assert_eq!(bounds.validate().unwrap_err().code, "QNT522");
+
+    let mut env = sampling_env();
+    for (min_int, max_int) in [(i64::MIN, i64::MAX - 1), (i64::MIN, 0), (0, i64::MAX)] {
+        env.sampling.min_int = min_int;
+        env.sampling.max_int = max_int;
+        assert!(env.sampling.validate().is_ok());
+        for _ in 0..100 {
+            let int = Value::IntSet.pick_with(&mut env).unwrap().as_int();
+            assert!((min_int..=max_int).contains(&int));
+        }
+    }
+}
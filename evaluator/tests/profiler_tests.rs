@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::profiler::{CacheKind, ProfileReport, Profiling};
+use quint_evaluator::simulator::SimulationConfig;
+
+fn profile_tictactoe(profiling: Profiling) -> Option<ProfileReport> {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(20)
+                .traces(0)
+                .profiling(profiling),
+            None,
+        )
+        .unwrap();
+    result.profile
+}
+
+#[test]
+fn profiles_definitions() {
+    let profile = profile_tictactoe(Profiling::Definitions).unwrap();
+    let entry = |name: &str| profile.entries.iter().find(|e| e.name == name);
+
+    let step = entry("q::step").unwrap();
+    assert!(step.calls > 0);
+    assert!(step.inclusive_ns >= step.exclusive_ns);
+    assert!(entry("MoveX").is_some_and(|e| e.calls > 0));
+
+    // Built-in operators are not measured
+    assert!(entry("forall").is_none());
+
+    // Pure values are evaluated only once and then cached forever
+    let patterns = entry("winningPatterns").unwrap();
+    assert_eq!(patterns.cache, Some(CacheKind::Forever));
+    assert_eq!(patterns.cache_misses, 1);
+    assert!(patterns.cache_hit_rate().unwrap() > 0.0);
+
+    let (hits, misses) = profile.cache_totals(CacheKind::ForState);
+    assert!(hits > 0 && misses > 0);
+}
+
+#[test]
+fn profiles_builtins() {
+    let profile = profile_tictactoe(Profiling::Builtins).unwrap();
+
+    assert!(profile.entries.iter().any(|e| e.name == "forall"));
+    assert!(profile
+        .folded
+        .iter()
+        .any(|(path, _)| path.starts_with("q::step;step;actionAny")));
+}
+
+#[test]
+fn exports_folded_stacks() {
+    let profile = profile_tictactoe(Profiling::Definitions).unwrap();
+    let folded = profile.folded_stacks();
+
+    assert_eq!(folded.lines().count(), profile.folded.len());
+    for line in folded.lines() {
+        let (path, time) = line.rsplit_once(' ').unwrap();
+        assert!(["q::init", "q::step", "inv"].contains(&path.split(';').next().unwrap()));
+        assert!(time.parse::<u128>().is_ok());
+    }
+}
+
+#[test]
+fn profiling_is_opt_in() {
+    assert!(profile_tictactoe(Profiling::Disabled).is_none());
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::itf::Trace;
+use quint_evaluator::render::{quint_path, render_trace, QuintSyntax};
+use quint_evaluator::simulator::SimulationConfig;
+use quint_evaluator::value::{PathSegment, Value};
+
+fn record(fields: Vec<(&str, Value)>) -> Value {
+    Value::Record(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.into(), value))
+            .collect(),
+    )
+}
+
+fn balances(entries: Vec<(&str, i64)>) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(name, n)| (Value::Str(name.into()), Value::Int(n)))
+            .collect(),
+    )
+}
+
+#[test]
+fn writes_values_in_quint_syntax() {
+    let value = record(vec![
+        ("balances", balances(vec![("bob", 2), ("alice", 1)])),
+        ("log", Value::List(vec![Value::Int(1)].into())),
+        (
+            "pair",
+            Value::Tuple(vec![Value::Bool(true), Value::Int(0)].into()),
+        ),
+        (
+            "status",
+            Value::Variant("Done".into(), Value::Tuple(Default::default()).into()),
+        ),
+    ]);
+
+    assert_eq!(
+        QuintSyntax(&value).to_string(),
+        "{ balances: Map(\"alice\" -> 1, \"bob\" -> 2), log: [1], pair: (true, 0), status: Done }"
+    );
+}
+
+#[test]
+fn writes_paths_in_quint_syntax() {
+    let path = vec![
+        PathSegment::Field("balances".into()),
+        PathSegment::Key(Value::Str("alice".into())),
+        PathSegment::Index(0),
+        PathSegment::Element(1),
+    ];
+    assert_eq!(quint_path("s", &path), "s.balances.get(\"alice\")[0]._2");
+}
+
+#[test]
+fn renders_only_changed_variables() {
+    colored::control::set_override(false);
+
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "XHasNotWon", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(1000)
+                .traces(1)
+                .store_metadata(true)
+                .seed(8),
+            None,
+        )
+        .unwrap();
+    let trace: Trace = Trace {
+        violated_invariant: Some("XHasNotWon".into()),
+        ..result.best_traces[0].clone()
+    };
+    let rendered = render_trace(&trace);
+    let lines = rendered.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines[0], "State 0 (init)");
+    assert!(lines[1].starts_with("  board = Map(1 -> Map(1 -> Empty"));
+    assert_eq!(lines[2], "  nextTurn = X");
+    assert_eq!(lines[3], "State 1 (MoveX)");
+    // A single square and the turn change in each move
+    assert!(lines[4].starts_with("  board.get("));
+    assert!(lines[4].ends_with(" = Occupied(X) (was Empty)"));
+    assert_eq!(lines[5], "  nextTurn = O (was X)");
+    assert_eq!(lines.last(), Some(&"Invariant XHasNotWon violated"));
+    assert!(!rendered.contains("mbt::"));
+}
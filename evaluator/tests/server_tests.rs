@@ -0,0 +1,207 @@
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+
+use quint_evaluator::server::Server;
+use serde_json::{json, Value};
+
+/// Output that can be read after the server is done
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Send the messages to a server, all at once, and return what it sent back.
+/// Messages without an id are notifications.
+fn session(messages: &[(&str, Option<u64>, Value)]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for (method, id, params) in messages {
+        let mut message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        if let Some(id) = id {
+            message["id"] = json!(id);
+        }
+        let body = message.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+
+    let output = SharedOutput::default();
+    Server::new(Cursor::new(input), output.clone())
+        .run()
+        .unwrap();
+
+    let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let mut messages = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(header) = rest.strip_prefix("Content-Length: ") {
+        let (length, body) = header.split_once("\r\n\r\n").unwrap();
+        let length = length.parse::<usize>().unwrap();
+        messages.push(serde_json::from_str(&body[..length]).unwrap());
+        rest = &body[length..];
+    }
+    assert!(rest.is_empty(), "unexpected output: {rest}");
+    messages
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    messages
+        .iter()
+        .find(|m| m["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {id}"))
+}
+
+fn progress(messages: &[Value], id: u64) -> Vec<&Value> {
+    messages
+        .iter()
+        .filter(|m| m["method"] == "progress" && m["params"]["request"] == id)
+        .map(|m| &m["params"])
+        .collect()
+}
+
+/// The output of `quint compile` for tictactoe, with two extra runs, and the
+/// expressions of some of its definitions, by name
+fn tictactoe() -> (Value, impl Fn(&str) -> Value) {
+    let file = std::fs::File::open("fixtures/tictactoe.json").unwrap();
+    let mut output: Value = serde_json::from_reader(file).unwrap();
+    let declarations = output["modules"][0]["declarations"].as_array_mut().unwrap();
+    declarations.push(json!({
+        "id": 1001, "kind": "def", "name": "passes", "qualifier": "run",
+        "expr": {"id": 1002, "kind": "bool", "value": true},
+    }));
+    declarations.push(json!({
+        "id": 1003, "kind": "def", "name": "fails", "qualifier": "run",
+        "expr": {"id": 1004, "kind": "app", "opcode": "assert", "args": [
+            {"id": 1005, "kind": "bool", "value": false},
+        ]},
+    }));
+
+    let definitions = output["modules"][0]["declarations"].clone();
+    let expr = move |name: &str| {
+        definitions
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|d| d["name"] == name)
+            .unwrap_or_else(|| panic!("no definition {name}"))["expr"]
+            .clone()
+    };
+
+    (json!({"source": "tictactoe.qnt", "compiled": output}), expr)
+}
+
+#[test]
+fn simulates_evaluates_and_tests_a_loaded_spec() {
+    let (spec, expr) = tictactoe();
+    let simulation = json!({
+        "init": expr("q::init"),
+        "step": expr("q::step"),
+        "invariant": expr("inv"),
+        "nruns": 50,
+        "nsteps": 10,
+        "ntraces": 1,
+    });
+    let messages = session(&[
+        ("load", Some(1), spec),
+        ("simulate", Some(2), simulation.clone()),
+        ("evaluate", Some(3), json!({"expr": expr("corners")})),
+        // `inv` reads state variables, which are not set
+        ("evaluate", Some(4), json!({"expr": expr("inv")})),
+        ("test", Some(5), json!({"seed": 1, "maxSamples": 3})),
+        // The spec stays loaded between requests
+        ("simulate", Some(6), simulation),
+        ("shutdown", Some(7), Value::Null),
+        ("exit", None, Value::Null),
+    ]);
+
+    let loaded = &response(&messages, 1)["result"];
+    assert_eq!(loaded["main"], "tictactoe");
+    assert_eq!(loaded["tests"], json!(["passes", "fails"]));
+
+    for id in [2, 6] {
+        let outcome = &response(&messages, id)["result"];
+        assert_eq!(outcome["status"], "ok");
+        assert_eq!(outcome["samples"], 50);
+        assert_eq!(outcome["bestTraces"].as_array().unwrap().len(), 1);
+
+        // One notification for each percentage reached
+        let progress = progress(&messages, id);
+        assert_eq!(progress.len(), 50);
+        assert_eq!(progress[0]["kind"], "simulation");
+        assert_eq!(progress[49]["current"], 50);
+        assert_eq!(progress[49]["percentage"], 100);
+    }
+
+    let value = &response(&messages, 3)["result"];
+    assert_eq!(value["status"], "ok");
+    assert_eq!(value["text"], "Set((1, 1), (1, 3), (3, 1), (3, 3))");
+    assert_eq!(value["value"]["#set"].as_array().unwrap().len(), 4);
+    let error = &response(&messages, 4)["result"];
+    assert_eq!(error["status"], "error");
+    assert_eq!(error["errors"].as_array().unwrap().len(), 1);
+
+    let tests = response(&messages, 5)["result"].as_array().unwrap();
+    let statuses = tests
+        .iter()
+        .map(|t| (t["name"].as_str().unwrap(), t["status"].as_str().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(statuses, [("passes", "passed"), ("fails", "failed")]);
+    assert_eq!(tests[0]["samples"], 3);
+    assert_eq!(tests[1]["samples"], 1);
+    assert_eq!(tests[1]["errors"][0]["code"], "QNT508");
+    let progress = progress(&messages, 5);
+    assert_eq!(
+        progress,
+        [
+            &json!({"kind": "test", "name": "passes", "passed": true, "request": 5}),
+            &json!({"kind": "test", "name": "fails", "passed": false, "request": 5}),
+        ]
+    );
+
+    assert_eq!(response(&messages, 7)["result"], Value::Null);
+}
+
+#[test]
+fn cancels_in_flight_simulations() {
+    let (spec, expr) = tictactoe();
+    let messages = session(&[
+        ("load", Some(1), spec),
+        (
+            "simulate",
+            Some(2),
+            json!({
+                "init": expr("q::init"),
+                "step": expr("q::step"),
+                "invariant": expr("inv"),
+                // Far more than can run before the cancellation is read
+                "nruns": 100_000_000,
+                "nsteps": 10,
+                "ntraces": 1,
+            }),
+        ),
+        ("$/cancelRequest", None, json!({"id": 2})),
+        ("evaluate", Some(3), json!({"expr": expr("corners")})),
+    ]);
+
+    assert_eq!(response(&messages, 2)["error"]["code"], -32800);
+    assert_eq!(response(&messages, 3)["result"]["status"], "ok");
+}
+
+#[test]
+fn rejects_requests_without_a_spec() {
+    let (_, expr) = tictactoe();
+    let messages = session(&[
+        ("evaluate", Some(1), json!({"expr": expr("corners")})),
+        ("simulate", Some(2), json!({"nruns": 1})),
+        ("unknown", Some(3), json!({})),
+    ]);
+
+    assert_eq!(response(&messages, 1)["error"]["code"], -32002);
+    assert_eq!(response(&messages, 2)["error"]["code"], -32602);
+    assert_eq!(response(&messages, 3)["error"]["code"], -32601);
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::itf::Trace;
+use quint_evaluator::picker::SamplingBounds;
+use quint_evaluator::simulator::SimulationConfig;
+
+#[test]
+fn tictactoe_shrinks_to_shortest_win() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "XHasNotWon", None).unwrap();
+    let sampling = SamplingBounds::default();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(20)
+                .samples(1000)
+                .traces(1)
+                .sampling(sampling.clone())
+                .seed(8),
+            None,
+        )
+        .unwrap();
+
+    assert!(!result.result);
+    let trace = &result.best_traces[0];
+    assert_eq!(trace.choices.len(), trace.states.len());
+
+    let shrunk = parsed.shrink(trace, &sampling).unwrap();
+    assert!(shrunk.violation);
+    assert_eq!(shrunk.choices.len(), shrunk.states.len());
+    // X needs at least three moves to win, with two moves from O in between
+    assert_eq!(shrunk.states.len(), 6);
+}
+
+#[test]
+fn shrinking_keeps_mbt_metadata() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "XHasNotWon", None).unwrap();
+    let sampling = SamplingBounds::default();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(20)
+                .samples(1000)
+                .traces(1)
+                .store_metadata(true)
+                .sampling(sampling.clone())
+                .seed(8),
+            None,
+        )
+        .unwrap();
+
+    let shrunk = parsed.shrink(&result.best_traces[0], &sampling).unwrap();
+    assert!(shrunk
+        .states
+        .iter()
+        .all(|state| state.as_record_map().contains_key("mbt::actionTaken")));
+}
+
+#[test]
+fn traces_without_choices_are_unchanged() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "XHasNotWon", None).unwrap();
+    let counterexample = parsed.check(None).unwrap().counterexample.unwrap();
+    let trace = Trace {
+        choices: Vec::new(),
+        ..counterexample
+    };
+
+    let shrunk = parsed.shrink(&trace, &SamplingBounds::default()).unwrap();
+    assert_eq!(shrunk.states, trace.states);
+}
@@ -1,12 +1,25 @@
+mod common;
+
+use std::fs::File;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
+use quint_evaluator::evaluator::{Env, Interpreter};
 use quint_evaluator::helpers;
+use quint_evaluator::ir::{QuintEx, QuintName, QuintOutput};
+use quint_evaluator::itf::ItfContext;
+use quint_evaluator::picker::SamplingBounds;
+use quint_evaluator::simulator::{CancellationToken, ParsedQuint, SimulationConfig, StopCondition};
+use quint_evaluator::value::{ImmutableMap, Value};
+
+use common::IrBuilder;
 
 #[test]
 fn tictactoe_ok() {
     let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
 
-    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("inv"), None, None).unwrap();
     // Pass an invariant that should hold
     let result = parsed.simulate(10, 100, 0, None);
     assert!(result.is_ok());
@@ -19,7 +32,8 @@ fn tictactoe_violation() {
     let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
 
     let parsed =
-        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None, None)
+            .unwrap();
     // Pass an invariant that should not hold
     let result = parsed.simulate(10, 100, 0, None);
     assert!(result.is_ok());
@@ -32,9 +46,15 @@ fn tictactoe_violation() {
 fn instances_ok() {
     let file_path: &Path = Path::new("fixtures/instances.qnt");
 
-    let parsed =
-        helpers::parse_from_path(file_path, "init", "step", Some("inv"), Some("instances"))
-            .unwrap();
+    let parsed = helpers::parse_from_path(
+        file_path,
+        "init",
+        "step",
+        Some("inv"),
+        None,
+        Some("instances"),
+    )
+    .unwrap();
     // Pass an invariant that should hold
     let result = parsed.simulate(10, 100, 0, None);
     assert!(result.is_ok());
@@ -47,12 +67,550 @@ fn instances_ok() {
 fn instance_overrides_ok() {
     let file_path: &Path = Path::new("fixtures/instances.qnt");
 
-    let parsed =
-        helpers::parse_from_path(file_path, "init", "step", Some("inv2"), Some("instances"))
-            .unwrap();
+    let parsed = helpers::parse_from_path(
+        file_path,
+        "init",
+        "step",
+        Some("inv2"),
+        None,
+        Some("instances"),
+    )
+    .unwrap();
     // Pass an invariant that should hold
     let result = parsed.simulate(10, 100, 0, None);
     assert!(result.is_ok());
     // Should not find violation
     assert!(result.unwrap().result);
 }
+
+#[test]
+fn tictactoe_from_json_ok() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed.simulate(10, 100, 0, None);
+    assert!(result.is_ok());
+    assert!(result.unwrap().result);
+}
+
+#[test]
+fn tictactoe_mbt_metadata() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(10)
+                .traces(1)
+                .store_metadata(true),
+            None,
+        )
+        .unwrap();
+    let trace = &result.best_traces[0];
+
+    // The initial state is reached by `init`, while every other state was
+    // reached by one of the named branches of `step`, or by `step` itself when
+    // the anonymous branch (for game over) is taken
+    let first = trace.states[0].as_record_map();
+    assert_eq!(first["mbt::actionTaken"], Value::Str("init".into()));
+    for state in trace.states.iter().skip(1) {
+        let action = state.as_record_map()["mbt::actionTaken"].as_str();
+        assert!(
+            ["MoveX", "MoveO", "step"].contains(&action.as_str()),
+            "unexpected action: {action}"
+        );
+    }
+
+    // All nondet bindings are present, and some of them were picked
+    let picks = trace.states[1].as_record_map()["mbt::nondetPicks"].as_record_map();
+    assert!(!picks.is_empty());
+    assert!(picks.values().any(|pick| pick.as_variant().0 == "Some"));
+}
+
+#[test]
+fn mbt_metadata_names_the_outermost_action() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // action play = MoveX, where the first move by X calls `StartInCorner`,
+    // which calls `Move`
+    let mut ir = IrBuilder::new(&mut parsed.table);
+    let move_x = ir.name("MoveX");
+    ir.def("play", "action", move_x);
+    parsed.step = json_expr(ir.name("play"));
+
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(1)
+                .samples(1)
+                .store_metadata(true),
+            None,
+        )
+        .unwrap();
+    let states = &result.best_traces[0].states;
+    assert_eq!(
+        states[1].as_record_map()["mbt::actionTaken"],
+        Value::Str("MoveX".into())
+    );
+}
+
+#[test]
+fn tictactoe_without_mbt_metadata() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed.simulate(10, 10, 1, None).unwrap();
+    let state = trace_state(&result.best_traces[0].states, 1);
+
+    assert!(!state.contains_key("mbt::actionTaken"));
+    assert!(!state.contains_key("mbt::nondetPicks"));
+}
+
+#[test]
+fn disabled_steps_do_not_leak_mbt_metadata() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // Picks a value, then is disabled
+    parsed.step = json_expr(pick_x(
+        &mut IrBuilder::new(&mut parsed.table),
+        &[7],
+        "igt",
+        10,
+    ));
+
+    let config = SimulationConfig::default()
+        .samples(3)
+        .traces(3)
+        .store_metadata(true);
+    let result = parsed.simulate_with(&config, None).unwrap();
+
+    // Every run stops at its initial state, where nothing was picked
+    assert_eq!(result.best_traces.len(), 3);
+    for trace in &result.best_traces {
+        assert_eq!(trace.states.len(), 1);
+        let picks = trace.states[0].as_record_map()["mbt::nondetPicks"].as_record_map();
+        assert!(picks.contains_key("x"));
+        assert!(picks.values().all(|pick| pick.as_variant().0 == "None"));
+    }
+}
+
+#[test]
+fn tictactoe_counterexample_comes_first() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "NotStalemate", None).unwrap();
+    // Stalemates are rare, this seed finds one after a few other runs
+    let config = SimulationConfig::default().samples(1000).traces(3).seed(8);
+    let result = parsed.simulate_with(&config, None).unwrap();
+
+    assert!(!result.result);
+    assert_eq!(result.best_traces.len(), 3);
+    // The counterexample is flagged as a violation, and ranks before the
+    // longer traces of the runs that did not violate the invariant
+    let counterexample = &result.best_traces[0];
+    assert!(counterexample.violation);
+    assert!(!counterexample.deadlock);
+    for trace in &result.best_traces[1..] {
+        assert!(!trace.violation);
+        assert!(trace.states.len() > counterexample.states.len());
+    }
+
+    let itf = counterexample
+        .clone()
+        .to_itf(&ItfContext::from_source("tictactoe.qnt".to_string()))
+        .unwrap();
+    assert_eq!(itf.meta.other["status"], "violation");
+}
+
+#[test]
+fn tictactoe_deadlock() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    // Only X moves, so no step can be taken once it is O's turn
+    let parsed = helpers::parse_from_json(file_path, "q::init", "MoveX", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(10)
+                .traces(1)
+                .check_deadlocks(true),
+            None,
+        )
+        .unwrap();
+
+    assert!(!result.result);
+    assert!(result.deadlock);
+    let trace = &result.best_traces[0];
+    assert!(trace.deadlock);
+    assert_eq!(trace.states.len(), 2);
+
+    let itf = trace
+        .clone()
+        .to_itf(&ItfContext::from_source("tictactoe.qnt".to_string()))
+        .unwrap();
+    assert_eq!(itf.meta.other["status"], "deadlock");
+}
+
+#[test]
+fn tictactoe_deadlock_unchecked() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    // Without deadlock checking, runs that cannot be extended are just dropped
+    let parsed = helpers::parse_from_json(file_path, "q::init", "MoveX", "inv", None).unwrap();
+    let result = parsed.simulate(10, 10, 1, None).unwrap();
+
+    assert!(result.result);
+    assert!(!result.deadlock);
+}
+
+#[test]
+fn tictactoe_no_deadlock() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(100)
+                .traces(1)
+                .check_deadlocks(true),
+            None,
+        )
+        .unwrap();
+
+    assert!(result.result);
+    assert!(!result.deadlock);
+}
+
+#[test]
+fn tictactoe_enabled_in_invariant() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // `step` is always enabled, as it keeps the board when the game is over
+    parsed.invariant = json_expr(enabled(&mut parsed, "step"));
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+    assert!(result.result);
+    // Checking that an action is enabled doesn't take it
+    assert_eq!(result.best_traces[0].states.len(), 11);
+
+    // But `MoveX` is disabled after X moves
+    parsed.invariant = json_expr(enabled(&mut parsed, "MoveX"));
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+    assert!(!result.result);
+    assert_eq!(result.best_traces[0].states.len(), 2);
+}
+
+#[test]
+fn enabled_tries_every_choice() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // Only one of the values that can be picked enables the action, which
+    // a single random pick would rarely find
+    let values = (1..=20).collect::<Vec<_>>();
+    let mut ir = IrBuilder::new(&mut parsed.table);
+    let action = pick_x(&mut ir, &values, "eq", 20);
+    parsed.invariant = json_expr(ir.app("enabled", vec![action]));
+    let result = parsed.simulate(10, 20, 1, None).unwrap();
+    assert!(result.result);
+}
+
+/// The names of the variables assigned by `action`, taken from the initial
+/// state of tictactoe
+fn assigned_by(parsed: &ParsedQuint, action: &QuintEx) -> Vec<String> {
+    let mut interpreter = Interpreter::new(&parsed.table);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    let init = interpreter.compile(&parsed.init);
+    let action = interpreter.compile(action);
+    assert!(init.execute(&mut env).unwrap().as_bool());
+    interpreter.shift();
+    assert!(action.execute(&mut env).unwrap().as_bool());
+
+    let storage = interpreter.var_storage.borrow();
+    let mut names = storage
+        .next_vars
+        .values()
+        .filter(|register| register.borrow().value.is_some())
+        .map(|register| register.borrow().name.to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn or_keep_keeps_only_the_given_vars() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // `MoveO` is disabled in the initial state, as X goes first
+    let mut ir = IrBuilder::new(&mut parsed.table);
+    let move_o = ir.name("MoveO");
+    let board = ir.name("board");
+    let keep_board = json_expr(ir.app("orKeep", vec![move_o, board]));
+    let move_o = ir.name("MoveO");
+    let board = ir.name("board");
+    let next_turn = ir.name("nextTurn");
+    let vars = ir.app("Tup", vec![board, next_turn]);
+    let keep_all = json_expr(ir.app("orKeep", vec![move_o, vars]));
+    // val turn = nextTurn; MoveO.orKeep(turn)
+    let next_turn = ir.name("nextTurn");
+    let turn = ir.def("turn", "val", next_turn);
+    let move_o = ir.name("MoveO");
+    let turn_name = ir.name("turn");
+    let or_keep = ir.app("orKeep", vec![move_o, turn_name]);
+    let keep_turn = json_expr(ir.let_in(turn, or_keep));
+
+    assert_eq!(assigned_by(&parsed, &keep_board), ["board"]);
+    assert_eq!(assigned_by(&parsed, &keep_all), ["board", "nextTurn"]);
+    assert_eq!(assigned_by(&parsed, &keep_turn), ["nextTurn"]);
+}
+
+#[test]
+fn tictactoe_must_change_deadlocks_when_game_is_over() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    // `step` only keeps the board when the game is over, so it is disabled
+    // when it must change it
+    let mut ir = IrBuilder::new(&mut parsed.table);
+    let step = ir.name("step");
+    let board = ir.name("board");
+    parsed.step = json_expr(ir.app("mustChange", vec![step, board]));
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(20)
+                .samples(10)
+                .traces(1)
+                .check_deadlocks(true),
+            None,
+        )
+        .unwrap();
+    assert!(result.deadlock);
+}
+
+fn trace_state(states: &[Value], index: usize) -> ImmutableMap<QuintName, Value> {
+    states[index].as_record_map().clone()
+}
+
+/// `nondet x = Set(values).oneOf(); x <opcode> other`
+fn pick_x(ir: &mut IrBuilder, values: &[i64], opcode: &str, other: i64) -> serde_json::Value {
+    let values = values.iter().map(|value| ir.int(*value)).collect();
+    let set = ir.app("Set", values);
+    let one_of = ir.app("oneOf", vec![set]);
+    let x = ir.def("x", "nondet", one_of);
+    let x_name = ir.name("x");
+    let other = ir.int(other);
+    let body = ir.app(opcode, vec![x_name, other]);
+    ir.let_in(x, body)
+}
+
+/// `enabled(action)`, for an action defined in `parsed`
+fn enabled(parsed: &mut ParsedQuint, action: &str) -> serde_json::Value {
+    let mut ir = IrBuilder::new(&mut parsed.table);
+    let action = ir.name(action);
+    ir.app("enabled", vec![action])
+}
+
+fn tictactoe_with_invariants(names: &[&str]) -> ParsedQuint {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let output: QuintOutput = serde_json::from_reader(File::open(file_path).unwrap()).unwrap();
+
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    parsed.invariants = output.find_invariants(names).unwrap();
+    parsed
+}
+
+#[test]
+fn tictactoe_named_invariants_stop_at_first_violation() {
+    let parsed = tictactoe_with_invariants(&["XHasNotWon", "NotStalemate"]);
+    let config = SimulationConfig::default().samples(1000).seed(8);
+    let result = parsed.simulate_with(&config, None).unwrap();
+    assert!(!result.result);
+
+    // There is no stalemate when X wins, so only one was violated
+    let names = result
+        .invariants
+        .iter()
+        .map(|inv| inv.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["XHasNotWon", "NotStalemate"]);
+    let violated = result
+        .invariants
+        .iter()
+        .filter_map(|inv| inv.counterexample.as_ref())
+        .collect::<Vec<_>>();
+    assert_eq!(violated, [&result.best_traces[0]]);
+}
+
+#[test]
+fn tictactoe_finds_violation_of_each_invariant() {
+    let parsed = tictactoe_with_invariants(&["XHasNotWon", "NotStalemate"]);
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(1000)
+                .traces(2)
+                .stop(StopCondition::AllViolations)
+                // Stalemates are rare, this seed finds one early
+                .seed(8),
+            None,
+        )
+        .unwrap();
+    assert!(!result.result);
+    assert!(!result.deadlock);
+    // Simulation stops as soon as every invariant is violated
+    assert!(result.samples < 1000);
+
+    for inv in &result.invariants {
+        let trace = inv.counterexample.as_ref().unwrap();
+        assert!(trace.violation, "{} has a counterexample", inv.name);
+    }
+    assert_eq!(result.best_traces.len(), 2);
+}
+
+#[test]
+fn tictactoe_named_invariant_holds() {
+    let parsed = tictactoe_with_invariants(&["inv"]);
+    let result = parsed
+        .simulate_with(
+            &SimulationConfig::default()
+                .steps(10)
+                .samples(100)
+                .traces(1)
+                .stop(StopCondition::AllViolations),
+            None,
+        )
+        .unwrap();
+    assert!(result.result);
+    assert!(!result.invariants[0].violated());
+}
+
+#[test]
+fn tictactoe_stops_when_cancelled() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let cancellation = CancellationToken::default();
+    cancellation.cancel();
+    let config = SimulationConfig::default()
+        .samples(100)
+        .cancellation(cancellation);
+    let result = parsed.simulate_with(&config, None).unwrap();
+    assert!(result.interrupted);
+    assert!(result.result);
+    assert_eq!(result.samples, 0);
+    assert!(result.best_traces.is_empty());
+}
+
+#[test]
+fn tictactoe_stops_at_deadline() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let config = SimulationConfig::default()
+        .samples(usize::MAX)
+        .deadline(Instant::now() + Duration::from_millis(200));
+    let result = parsed.simulate_with(&config, None).unwrap();
+    // Only the samples completed before the deadline are reported
+    assert!(result.interrupted);
+    assert!(result.result);
+    assert!(result.samples > 0);
+    assert_eq!(result.best_traces.len(), 1);
+    assert!(result.best_traces[0].sample <= Some(result.samples));
+}
+
+#[test]
+fn tictactoe_seed_reproduces_simulation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let config = SimulationConfig::default().samples(20).seed(42);
+    let first = parsed.simulate_with(&config, None).unwrap();
+    let second = parsed.simulate_with(&config, None).unwrap();
+    assert_eq!(first.best_traces, second.best_traces);
+}
+
+#[test]
+fn invalid_sampling_bounds_are_rejected() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let config = SimulationConfig::default().sampling(SamplingBounds {
+        min_int: 1,
+        max_int: 0,
+        ..SamplingBounds::default()
+    });
+    let result = parsed.simulate_with(&config, None);
+    assert_eq!(result.err().unwrap().code, "QNT522");
+}
+
+#[test]
+fn simulation_in_threads_is_not_supported() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let parsed = helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let config = SimulationConfig::default().threads(4);
+    let result = parsed.simulate_with(&config, None);
+    assert_eq!(result.err().unwrap().code, "QNT523");
+}
+
+#[test]
+fn tictactoe_config_invariants_and_witnesses() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let output: QuintOutput = serde_json::from_reader(File::open(file_path).unwrap()).unwrap();
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+
+    let invariants = output
+        .find_invariants(&["XHasNotWon", "NotStalemate"])
+        .unwrap();
+    let config = SimulationConfig::default()
+        .samples(1000)
+        .traces(2)
+        .invariant(invariants[0].clone())
+        .invariant(invariants[1].clone())
+        .stop(StopCondition::AllViolations)
+        .seed(8)
+        // Witnesses holding in every run, in some of them, and in none
+        .witness(output.find_definition_by_name("inv").unwrap().expr.clone())
+        .witness(invariants[0].expr.clone())
+        .witness(json_expr(IrBuilder::new(&mut parsed.table).bool(false)));
+    let result = parsed.simulate_with(&config, None).unwrap();
+
+    assert!(!result.result);
+    let names = result
+        .invariants
+        .iter()
+        .map(|inv| inv.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["XHasNotWon", "NotStalemate"]);
+    assert!(result.invariants.iter().all(|inv| inv.violated()));
+
+    let [always, sometimes, never] = result.witnessing_traces[..] else {
+        panic!("one count per witness: {:?}", result.witnessing_traces);
+    };
+    assert_eq!(always, result.samples);
+    assert!(sometimes > 0 && sometimes <= result.samples);
+    assert_eq!(never, 0);
+}
+
+fn json_expr(expr: serde_json::Value) -> QuintEx {
+    serde_json::from_value(expr).unwrap()
+}
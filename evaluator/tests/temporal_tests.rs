@@ -0,0 +1,192 @@
+mod common;
+
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::ir::QuintEx;
+use quint_evaluator::itf::ItfContext;
+use quint_evaluator::simulator::{ParsedQuint, SimulationConfig};
+
+use common::IrBuilder;
+
+/// Parse the tictactoe fixture, checking the temporal property built by
+/// `property`
+fn tictactoe_with_temporal(
+    property: impl FnOnce(&mut IrBuilder) -> serde_json::Value,
+) -> ParsedQuint {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let property = property(&mut IrBuilder::new(&mut parsed.table));
+    parsed.temporal = Some(serde_json::from_value::<QuintEx>(property).unwrap());
+    parsed
+}
+
+/// `nextTurn' = player`
+fn turn_to(ir: &mut IrBuilder, player: &str) -> serde_json::Value {
+    let next_turn = ir.name("nextTurn");
+    let player = ir.name(player);
+    ir.app("assign", vec![next_turn, player])
+}
+
+/// `(board, nextTurn)`
+fn vars(ir: &mut IrBuilder) -> serde_json::Value {
+    let board = ir.name("board");
+    let next_turn = ir.name("nextTurn");
+    ir.app("Tup", vec![board, next_turn])
+}
+
+/// `weakFair(nextTurn' = player, (board, nextTurn))`
+fn weak_fair_turn_to(ir: &mut IrBuilder, player: &str) -> serde_json::Value {
+    let action = turn_to(ir, player);
+    let vars = vars(ir);
+    ir.app("weakFair", vec![action, vars])
+}
+
+#[test]
+fn tictactoe_x_must_eventually_win() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+
+    let parsed = helpers::parse_from_json(
+        file_path,
+        "q::init",
+        "q::step",
+        "inv",
+        Some("XMustEventuallyWin"),
+    )
+    .unwrap();
+    // Games end in a stalemate or with O winning every now and then, and then
+    // repeat the final state forever. The seed finds one in the first samples.
+    let config = SimulationConfig::default().steps(20).samples(100).seed(8);
+    let result = parsed.simulate_with(&config, None).unwrap();
+
+    assert!(!result.result);
+    let trace = &result.best_traces[0];
+    assert!(trace.violation);
+    assert_eq!(trace.loop_index, Some(trace.states.len() - 1));
+
+    let itf = trace
+        .clone()
+        .to_itf(&ItfContext::from_source("tictactoe.qnt".to_string()))
+        .unwrap();
+    assert_eq!(itf.loop_index, Some(trace.states.len() as u64 - 1));
+}
+
+/// `eventually(gameOver)`
+fn eventually_game_over(ir: &mut IrBuilder) -> serde_json::Value {
+    let game_over = ir.name("gameOver");
+    ir.app("eventually", vec![game_over])
+}
+
+#[test]
+fn tictactoe_eventually_game_over() {
+    let parsed = tictactoe_with_temporal(eventually_game_over);
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+
+    assert!(result.result);
+    assert!(result.best_traces[0].loop_index.is_some());
+}
+
+#[test]
+fn tictactoe_eventually_game_over_on_finite_traces() {
+    // Traces are cut before the game is over, but they could still get there
+    let parsed = tictactoe_with_temporal(eventually_game_over);
+    let result = parsed.simulate(3, 100, 1, None).unwrap();
+
+    assert!(result.result);
+    assert!(result.best_traces[0].loop_index.is_none());
+}
+
+#[test]
+fn tictactoe_always_not_game_over() {
+    // Safety is checked on finite traces too
+    let parsed = tictactoe_with_temporal(|ir| {
+        let game_over = ir.name("gameOver");
+        let not_game_over = ir.app("not", vec![game_over]);
+        ir.app("always", vec![not_game_over])
+    });
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+
+    assert!(!result.result);
+    let trace = &result.best_traces[0];
+    assert!(trace.violation);
+    // X needs at least three moves to end the game
+    assert!(trace.states.len() >= 6);
+}
+
+#[test]
+fn tictactoe_always_next_turn_alternates() {
+    // An action under `always` has to match every transition, including the
+    // one looping back
+    let parsed = tictactoe_with_temporal(|ir| {
+        let to_x = turn_to(ir, "X");
+        let to_o = turn_to(ir, "O");
+        let any = ir.app("actionAny", vec![to_x, to_o]);
+        ir.app("always", vec![any])
+    });
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+    assert!(result.result);
+
+    let parsed = tictactoe_with_temporal(|ir| {
+        let to_x = turn_to(ir, "X");
+        ir.app("always", vec![to_x])
+    });
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+    assert!(!result.result);
+}
+
+#[test]
+fn tictactoe_always_or_keep() {
+    // Every transition is a move by X or O, or keeps the board (when the game
+    // is over), but never just passes the turn
+    let parsed = tictactoe_with_temporal(|ir| {
+        let step = ir.name("step");
+        let vars = vars(ir);
+        let or_keep = ir.app("orKeep", vec![step, vars]);
+        ir.app("always", vec![or_keep])
+    });
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+    assert!(result.result);
+
+    let parsed = tictactoe_with_temporal(|ir| {
+        let to_x = turn_to(ir, "X");
+        let vars = vars(ir);
+        let or_keep = ir.app("orKeep", vec![to_x, vars]);
+        ir.app("always", vec![or_keep])
+    });
+    let result = parsed.simulate(20, 100, 1, None).unwrap();
+    assert!(!result.result);
+}
+
+#[test]
+fn tictactoe_weak_fairness() {
+    // Once the game is over, giving the turn to the other player is enabled
+    // forever but never taken, so one of these is violated in every lasso
+    let fairness = |ir: &mut IrBuilder| {
+        let fair_x = weak_fair_turn_to(ir, "X");
+        let fair_o = weak_fair_turn_to(ir, "O");
+        ir.app("and", vec![fair_x, fair_o])
+    };
+
+    let parsed = tictactoe_with_temporal(fairness);
+    let result = parsed.simulate(20, 10, 1, None).unwrap();
+    assert!(!result.result);
+    assert!(result.best_traces[0].loop_index.is_some());
+
+    // No finite trace violates fairness
+    let parsed = tictactoe_with_temporal(fairness);
+    let result = parsed.simulate(3, 10, 1, None).unwrap();
+    assert!(result.result);
+}
+
+#[test]
+fn temporal_operators_outside_of_temporal_properties() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.json");
+    let mut parsed =
+        helpers::parse_from_json(file_path, "q::init", "q::step", "inv", None).unwrap();
+    let invariant = eventually_game_over(&mut IrBuilder::new(&mut parsed.table));
+    parsed.invariant = serde_json::from_value(invariant).unwrap();
+
+    let result = parsed.simulate(20, 10, 1, None);
+    assert_eq!(result.err().unwrap().code, "QNT501");
+}
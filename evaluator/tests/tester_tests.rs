@@ -0,0 +1,134 @@
+mod common;
+
+use std::fs::File;
+
+use quint_evaluator::ir::{QuintDeclaration, QuintOutput};
+use quint_evaluator::tester::{self, TestOptions};
+use regex::Regex;
+
+use common::IrBuilder;
+
+/// The tictactoe fixture with some runs added to the main module
+fn tictactoe_with_runs() -> QuintOutput {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let mut output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let mut ir = IrBuilder::new(&mut output.table);
+
+    let mut runs = Vec::new();
+    // init.then(MoveX).then(MoveO)
+    let init = ir.name("init");
+    let move_x = ir.name("MoveX");
+    let x_then_o = ir.app("then", vec![init, move_x]);
+    let move_o = ir.name("MoveO");
+    runs.push(("xThenOTest", ir.app("then", vec![x_then_o, move_o])));
+    // init.then(MoveO)
+    let init = ir.name("init");
+    let move_o = ir.name("MoveO");
+    runs.push(("oStartsTest", ir.app("then", vec![init, move_o])));
+    // init.expect(gameOver)
+    let init = ir.name("init");
+    let game_over = ir.name("gameOver");
+    runs.push(("gameOverTest", ir.app("expect", vec![init, game_over])));
+    // init.then(4.reps(_ => step))
+    let init = ir.name("init");
+    let times = ir.int(4);
+    let step = ir.name("step");
+    let step = ir.lambda("_", step);
+    let reps = ir.app("reps", vec![times, step]);
+    runs.push(("repsTest", ir.app("then", vec![init, reps])));
+
+    let runs = runs
+        .into_iter()
+        .map(|(run_name, expr)| ir.def(run_name, "run", expr))
+        .collect::<Vec<_>>();
+
+    let main = output
+        .modules
+        .iter_mut()
+        .find(|m| m.name == output.main)
+        .unwrap();
+    for def in runs {
+        main.declarations
+            .push(serde_json::from_value::<QuintDeclaration>(def).unwrap());
+    }
+
+    output
+}
+
+#[test]
+fn finds_runs() {
+    let output = tictactoe_with_runs();
+
+    let names = |filter: Option<&Regex>| {
+        tester::find_runs(&output, filter)
+            .into_iter()
+            .map(|def| def.name.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(None),
+        ["xThenOTest", "oStartsTest", "gameOverTest", "repsTest"]
+    );
+    assert_eq!(
+        names(Some(&Regex::new("^o|^x").unwrap())),
+        ["xThenOTest", "oStartsTest"]
+    );
+}
+
+#[test]
+fn runs_tests() {
+    let output = tictactoe_with_runs();
+    let options = TestOptions {
+        max_samples: 10,
+        seed: Some(42),
+        ..Default::default()
+    };
+    let results = tester::run_tests(&output, &options);
+
+    let passing = &results[0];
+    assert!(passing.passed);
+    assert_eq!(passing.samples, 10);
+    // The states after init, MoveX and MoveO
+    let trace = passing.trace.as_ref().unwrap();
+    assert_eq!(trace.states.len(), 3);
+    assert!(!trace.violation);
+
+    // O can't start, so the run evaluates to false
+    let returns_false = &results[1];
+    assert!(!returns_false.passed);
+    assert_eq!(returns_false.samples, 1);
+    assert_eq!(returns_false.seed, 42);
+    assert!(returns_false.error.is_none());
+    // Only the state after init, since MoveO is disabled there
+    let trace = returns_false.trace.as_ref().unwrap();
+    assert_eq!(trace.states.len(), 1);
+    assert!(trace.violation);
+
+    let failed_expect = &results[2];
+    assert!(!failed_expect.passed);
+    assert_eq!(failed_expect.error.as_ref().unwrap().code, "QNT508");
+    assert_eq!(failed_expect.trace.as_ref().unwrap().states.len(), 1);
+
+    // The states after init and after each of the 4 steps
+    let reps = &results[3];
+    assert!(reps.passed);
+    assert_eq!(reps.trace.as_ref().unwrap().states.len(), 5);
+}
+
+#[test]
+fn reports_junit_xml() {
+    let output = tictactoe_with_runs();
+    let options = TestOptions {
+        max_samples: 1,
+        seed: Some(0x2a),
+        ..Default::default()
+    };
+    let results = tester::run_tests(&output, &options);
+    let xml = tester::to_junit_xml(&output.main, &results);
+
+    assert!(xml.contains(r#"<testsuite name="tictactoe" tests="4" failures="2""#));
+    assert!(xml.contains(r#"<testcase name="xThenOTest" classname="tictactoe""#));
+    assert!(xml.contains(r#"<failure message="oStartsTest returns false">Use --seed 0x2a"#));
+    assert!(xml.contains("Expect condition does not hold true"));
+    assert_eq!(xml.matches("</testcase>").count(), 2);
+}
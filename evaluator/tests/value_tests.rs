@@ -0,0 +1,185 @@
+use quint_evaluator::value::{Difference, DifferenceKind, PathSegment, Value};
+
+fn set(elems: Vec<i64>) -> Value {
+    Value::Set(elems.into_iter().map(Value::Int).collect())
+}
+
+fn list(elems: Vec<i64>) -> Value {
+    Value::List(elems.into_iter().map(Value::Int).collect())
+}
+
+fn record(fields: Vec<(&str, Value)>) -> Value {
+    Value::Record(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.into(), value))
+            .collect(),
+    )
+}
+
+fn difference(path: Vec<PathSegment>, kind: DifferenceKind) -> Difference {
+    Difference { path, kind }
+}
+
+#[test]
+fn diffs_nested_records_and_maps() {
+    let balances = |entries: Vec<(&str, i64)>| {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(name, n)| (Value::Str(name.into()), Value::Int(n)))
+                .collect(),
+        )
+    };
+    let old = record(vec![
+        ("balances", balances(vec![("alice", 1), ("bob", 2)])),
+        ("owner", Value::Str("alice".into())),
+    ]);
+    let new = record(vec![
+        ("balances", balances(vec![("alice", 3), ("carol", 0)])),
+        ("paused", Value::Bool(true)),
+    ]);
+
+    let key = |name: &str| PathSegment::Key(Value::Str(name.into()));
+    let field = |name: &str| PathSegment::Field(name.into());
+    assert_eq!(
+        old.diff(&new).differences,
+        vec![
+            difference(
+                vec![field("balances"), key("alice")],
+                DifferenceKind::Changed {
+                    old: Value::Int(1),
+                    new: Value::Int(3)
+                }
+            ),
+            difference(
+                vec![field("balances"), key("bob")],
+                DifferenceKind::Removed(Value::Int(2))
+            ),
+            difference(
+                vec![field("balances"), key("carol")],
+                DifferenceKind::Added(Value::Int(0))
+            ),
+            difference(
+                vec![field("owner")],
+                DifferenceKind::Removed(Value::Str("alice".into()))
+            ),
+            difference(
+                vec![field("paused")],
+                DifferenceKind::Added(Value::Bool(true))
+            ),
+        ]
+    );
+    assert!(old.diff(&old).is_empty());
+}
+
+#[test]
+fn diffs_set_elements() {
+    let diff = set(vec![1, 2, 3]).diff(&set(vec![2, 3, 4, 5]));
+    assert_eq!(
+        diff.differences
+            .into_iter()
+            .map(|d| d.kind)
+            .collect::<Vec<_>>(),
+        vec![
+            DifferenceKind::ElementRemoved(Value::Int(1)),
+            DifferenceKind::ElementAdded(Value::Int(4)),
+            DifferenceKind::ElementAdded(Value::Int(5)),
+        ]
+    );
+
+    // Sets are compared by their elements, however they are represented
+    assert!(Value::Interval(1, 3).diff(&set(vec![1, 2, 3])).is_empty());
+    assert_eq!(
+        Value::Interval(1, 3)
+            .diff(&set(vec![1, 2]))
+            .differences
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn diffs_lists_as_edits() {
+    let index = |i| vec![PathSegment::Index(i)];
+
+    // Removing an element does not shift the following ones
+    assert_eq!(
+        list(vec![1, 2, 3, 4])
+            .diff(&list(vec![1, 3, 4]))
+            .differences,
+        vec![difference(index(1), DifferenceKind::Removed(Value::Int(2)))]
+    );
+    assert_eq!(
+        list(vec![1, 2]).diff(&list(vec![1, 2, 3])).differences,
+        vec![difference(index(2), DifferenceKind::Added(Value::Int(3)))]
+    );
+    assert_eq!(
+        list(vec![1, 2, 3]).diff(&list(vec![1, 5, 3])).differences,
+        vec![difference(
+            index(1),
+            DifferenceKind::Changed {
+                old: Value::Int(2),
+                new: Value::Int(5)
+            }
+        )]
+    );
+}
+
+#[test]
+fn diffs_lazy_sets_without_enumerating() {
+    // These would take forever to enumerate
+    let big = Value::Interval(0, 1 << 40);
+    let powerset = Value::PowerSet(big.clone().into());
+    assert!(powerset.diff(&powerset.clone()).is_empty());
+    assert!(Value::MapSet(big.clone().into(), big.clone().into())
+        .diff(&Value::MapSet(big.clone().into(), big.clone().into()))
+        .is_empty());
+
+    // Intervals are compared by their bounds
+    assert_eq!(
+        big.diff(&Value::Interval(1, (1 << 40) + 1)).differences,
+        vec![
+            difference(Vec::new(), DifferenceKind::ElementRemoved(Value::Int(0))),
+            difference(
+                Vec::new(),
+                DifferenceKind::ElementAdded(Value::Int((1 << 40) + 1))
+            ),
+        ]
+    );
+
+    // Unless too many integers are added or removed
+    let shifted = Value::Interval(1 << 39, 1 << 41);
+    assert_eq!(
+        big.diff(&shifted).differences,
+        vec![difference(
+            Vec::new(),
+            DifferenceKind::Changed {
+                old: big.clone(),
+                new: shifted
+            }
+        )]
+    );
+
+    // Sets of other kinds are not enumerated to compare them with lazy sets
+    let small = set(vec![1, 2]);
+    for lazy in [big.clone(), powerset.clone()] {
+        assert!(matches!(
+            &small.diff(&lazy).differences[..],
+            [Difference {
+                kind: DifferenceKind::Changed { .. },
+                ..
+            }]
+        ));
+    }
+
+    // Other lazy sets that differ are reported as a whole
+    let other = Value::PowerSet(Value::Interval(0, 1 << 41).into());
+    assert!(matches!(
+        &powerset.diff(&other).differences[..],
+        [Difference {
+            kind: DifferenceKind::Changed { .. },
+            ..
+        }]
+    ));
+}
@@ -0,0 +1,10 @@
+//!
+//! A Debug Adapter Protocol server for Quint, talking to the editor over stdin
+//! and stdout.
+//!
+
+use quint_parser::dap::Server;
+
+fn main() -> std::io::Result<()> {
+    Server::new(std::io::stdin().lock(), std::io::stdout()).run()
+}
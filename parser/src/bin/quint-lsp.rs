@@ -0,0 +1,10 @@
+//!
+//! A Language Server Protocol server for Quint, talking to the editor over
+//! stdin and stdout.
+//!
+
+use quint_parser::lsp::Server;
+
+fn main() -> std::io::Result<()> {
+    Server::new(std::io::stdin().lock(), std::io::stdout()).run()
+}
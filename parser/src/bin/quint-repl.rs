@@ -0,0 +1,100 @@
+//!
+//! An interactive REPL for Quint, with line editing and history.
+//!
+//! Input spanning multiple lines is read until all brackets are closed.
+//!
+
+use std::path::PathBuf;
+
+use argh::FromArgs;
+use quint_evaluator::rand::parse_seed;
+use quint_parser::repl::{HELP, Repl};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{
+    MatchingBracketValidator, ValidationContext, ValidationResult, Validator,
+};
+use rustyline::{Editor, Helper};
+
+/// Quint REPL
+#[derive(FromArgs)]
+struct Args {
+    /// a file to load on startup
+    #[argh(positional)]
+    file: Option<PathBuf>,
+
+    /// the random seed, in decimal or hexadecimal (0x...)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+}
+
+/// Keeps reading lines while brackets are open
+struct InputHelper(MatchingBracketValidator);
+
+impl Validator for InputHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        self.0.validate(ctx)
+    }
+}
+
+impl Completer for InputHelper {
+    type Candidate = String;
+}
+
+impl Hinter for InputHelper {
+    type Hint = String;
+}
+
+impl Highlighter for InputHelper {}
+
+impl Helper for InputHelper {}
+
+fn main() -> eyre::Result<()> {
+    let args: Args = argh::from_env();
+    let mut repl = args.seed.map(Repl::new).unwrap_or_default();
+
+    let mut editor = Editor::new()?;
+    editor.set_helper(Some(InputHelper(MatchingBracketValidator::new())));
+    let history =
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".quint_repl_history"));
+    if let Some(history) = &history {
+        // There is no history on the first run
+        let _ = editor.load_history(history);
+    }
+
+    println!("Quint REPL. Type :help for help.");
+    if let Some(file) = &args.file {
+        print_result(repl.load(file).map(Some));
+    }
+
+    loop {
+        match editor.readline(">>> ") {
+            Ok(input) => {
+                editor.add_history_entry(input.as_str())?;
+                match input.trim() {
+                    ":quit" | ":exit" => break,
+                    ":help" => println!("{HELP}"),
+                    input => print_result(repl.process(input)),
+                }
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    if let Some(history) = &history {
+        editor.save_history(history)?;
+    }
+    Ok(())
+}
+
+fn print_result(result: eyre::Result<Option<String>>) {
+    match result {
+        Ok(Some(output)) => println!("{output}"),
+        Ok(None) => {}
+        Err(err) => eprintln!("error: {err}"),
+    }
+}
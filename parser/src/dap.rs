@@ -0,0 +1,650 @@
+//!
+//! A Debug Adapter Protocol (DAP) server, to debug Quint specs from editors.
+//!
+//! The server talks to a single client over a pair of streams (usually stdin
+//! and stdout), with the `Content-Length` framing of the protocol. A `launch`
+//! request loads a file with a single module, written in the subset of Quint
+//! read by this parser, and either simulates it (`init` and then a number of
+//! `step`s) or runs one of its `run` tests, with the given seed.
+//!
+//! Breakpoints are set on lines. Each line is mapped, through the source spans
+//! recorded by the parser, to the outermost definition or expression starting
+//! on it: a definition pauses before its body is evaluated, and an expression
+//! (e.g. a branch of `any { ... }`) before it is evaluated. While paused, the
+//! current and next states and the lambda parameters in scope are shown as
+//! scopes, and pauses on the branches of an `actionAny` tell which branch is
+//! being tried.
+//!
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use eyre::{Result, bail, eyre};
+use quint_evaluator::debugger::{
+    Breakpoint, DebugCommand, DebugFrontend, Debugger, Pause, PauseReason,
+};
+use quint_evaluator::evaluator::{Env, Interpreter};
+use quint_evaluator::rand::Rand;
+use quint_evaluator::render::QuintSyntax;
+use quint_evaluator::simulator::ParsedQuint;
+use quint_evaluator::transport::{read_message, write_message};
+use quint_evaluator::value::Value;
+use serde_json::{Value as Json, json};
+
+use crate::resolver::Resolver;
+use crate::utils::QuintIdGenerator;
+use crate::{
+    LookupTable, OpDef, OpQualifier, QuintDeclaration, QuintError, QuintEx, QuintId, QuintModule,
+    QuintName, parse_quint_module,
+};
+
+/// Evaluation is sequential, so there is a single thread
+const THREAD_ID: u64 = 1;
+
+// The variable references of the scopes shown while paused. The elements of
+// compound values get the references after these.
+const STATE_SCOPE: usize = 1;
+const NEXT_STATE_SCOPE: usize = 2;
+const PARAMETERS_SCOPE: usize = 3;
+
+/// Reads requests from the client, and writes responses and events to it.
+struct Connection<R, W> {
+    input: R,
+    output: W,
+    seq: u64,
+}
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        read_message(&mut self.input)
+    }
+
+    fn send(&mut self, mut message: Json) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Json, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(json!({"type": "event", "event": event, "body": body}))
+    }
+}
+
+/// A parsed and resolved spec, with the source spans of its definitions and
+/// of some of its expressions.
+struct Program {
+    path: String,
+    source: String,
+    module: QuintModule,
+    table: LookupTable,
+    spans: HashMap<QuintId, (usize, usize)>,
+}
+
+impl Program {
+    fn load(path: &str) -> Result<Self> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| eyre!("Could not read {path}: {e}"))?;
+        let mut ids = QuintIdGenerator::default();
+        let module = parse_quint_module(&source, &mut ids)
+            .map_err(|e| QuintError::new("QNT000", &format!("Could not parse {path}: {e:?}")))?;
+        let mut resolver = Resolver::default();
+        resolver.resolve_module(&module)?;
+
+        Ok(Self {
+            path: path.to_string(),
+            source,
+            module,
+            table: resolver.table,
+            spans: ids.spans,
+        })
+    }
+
+    fn definition(&self, name: &str) -> Option<&OpDef> {
+        self.module.declarations.iter().find_map(|d| match d {
+            QuintDeclaration::QuintOpDef(def) if def.name == name => Some(def),
+            _ => None,
+        })
+    }
+
+    /// The line (starting at 1) where a definition or expression starts, if
+    /// its span was recorded
+    fn line_of(&self, id: QuintId) -> Option<usize> {
+        self.spans
+            .get(&id)
+            .map(|(start, _)| self.source[..*start].matches('\n').count() + 1)
+    }
+
+    /// The breakpoint on the outermost definition or expression starting on
+    /// a line, if any
+    fn breakpoint_at(&self, line: usize) -> Option<Breakpoint> {
+        let (id, _) = self
+            .spans
+            .iter()
+            .filter(|(id, _)| self.line_of(**id) == Some(line))
+            .max_by_key(|(id, (start, end))| (end - start, Reverse(**id)))?;
+
+        let definition = self.module.declarations.iter().find_map(|d| match d {
+            QuintDeclaration::QuintOpDef(def) if def.id == *id => Some(def.name.clone()),
+            _ => None,
+        });
+        Some(match definition {
+            Some(name) => Breakpoint::Definition(name),
+            None => Breakpoint::Id(*id),
+        })
+    }
+
+    /// The breakpoints for the lines of a `setBreakpoints` request, and the
+    /// body of the response, telling which lines have one.
+    fn set_breakpoints(&self, args: &Json) -> (Vec<Breakpoint>, Json) {
+        let lines = args["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b["line"].as_u64());
+
+        let mut breakpoints = Vec::new();
+        let mut verified = Vec::new();
+        for line in lines {
+            match self.breakpoint_at(line as usize) {
+                Some(breakpoint) => {
+                    breakpoints.push(breakpoint);
+                    verified.push(json!({"verified": true, "line": line}));
+                }
+                None => verified.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "No definition or expression starts on this line",
+                })),
+            }
+        }
+        (breakpoints, json!({"breakpoints": verified}))
+    }
+
+    fn source(&self) -> Json {
+        json!({"name": self.module.name, "path": self.path})
+    }
+}
+
+enum Mode {
+    /// Evaluate `init` and up to `max_steps` steps
+    Simulate {
+        init: QuintEx,
+        step: QuintEx,
+        max_steps: usize,
+    },
+    /// Evaluate a `run` definition
+    Test { name: QuintName, body: QuintEx },
+}
+
+/// What to debug, from the arguments of a `launch` request.
+struct Launch {
+    program: Rc<Program>,
+    mode: Mode,
+    seed: u64,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn new(args: &Json) -> Result<Self> {
+        let path = args["program"]
+            .as_str()
+            .ok_or_else(|| eyre!("Missing the program to debug"))?;
+        let program = Program::load(path)?;
+
+        let body = |name: &str| {
+            program
+                .definition(name)
+                .map(|def| def.expr.clone())
+                .ok_or_else(|| eyre!("Definition {name} not found"))
+        };
+        let mode = match args["mode"].as_str().unwrap_or("simulate") {
+            "simulate" => Mode::Simulate {
+                init: body(args["init"].as_str().unwrap_or("init"))?,
+                step: body(args["step"].as_str().unwrap_or("step"))?,
+                max_steps: args["maxSteps"].as_u64().unwrap_or(10) as usize,
+            },
+            "test" => {
+                let name = args["test"]
+                    .as_str()
+                    .ok_or_else(|| eyre!("Missing the test to run"))?;
+                match program.definition(name) {
+                    Some(def) if def.qualifier == OpQualifier::Run => Mode::Test {
+                        name: name.into(),
+                        body: def.expr.clone(),
+                    },
+                    _ => bail!("Test {name} not found, it must be a `run` definition"),
+                }
+            }
+            mode => bail!("Unknown mode {mode}, expected simulate or test"),
+        };
+
+        Ok(Self {
+            program: Rc::new(program),
+            mode,
+            seed: args["seed"]
+                .as_u64()
+                .unwrap_or_else(|| Rand::new().get_state()),
+            stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Evaluate under the debugger, returning what to show when done and
+    /// whether it succeeded.
+    fn run(&self, debugger: Debugger) -> Result<(String, bool), QuintError> {
+        match &self.mode {
+            Mode::Simulate {
+                init,
+                step,
+                max_steps,
+            } => {
+                let parsed = ParsedQuint {
+                    init: init.clone(),
+                    step: step.clone(),
+                    invariant: QuintEx::QuintBool { id: 0, value: true },
+                    invariants: Vec::new(),
+                    temporal: None,
+                    table: self.program.table.clone(),
+                };
+                let states = parsed.debug(*max_steps, self.seed, debugger)?;
+                let mut output = format!("Simulated with seed {:#x}\n", self.seed);
+                for (i, state) in states.iter().enumerate() {
+                    output.push_str(&format!("State {i}: {}\n", QuintSyntax(state)));
+                }
+                Ok((output, true))
+            }
+            Mode::Test { name, body } => {
+                let mut interpreter = Interpreter::new(&self.program.table);
+                interpreter.enable_debugging(debugger);
+                for declaration in &self.program.module.declarations {
+                    if let QuintDeclaration::QuintVar(var) = declaration {
+                        interpreter.declare_var(var.id, &var.name);
+                    }
+                }
+                let test = interpreter.compile(body);
+                let mut env = Env::with_rand_state(interpreter.var_storage.clone(), self.seed);
+                let passed = test.execute(&mut env)?.as_bool();
+
+                let outcome = if passed { "passed" } else { "failed" };
+                Ok((
+                    format!("Test {name} {outcome} with seed {:#x}\n", self.seed),
+                    passed,
+                ))
+            }
+        }
+    }
+}
+
+/// A DAP server for a single debugging session.
+pub struct Server<R, W> {
+    connection: Rc<RefCell<Connection<R, W>>>,
+    launch: Option<Launch>,
+    breakpoints: Vec<Breakpoint>,
+    configured: bool,
+    started: bool,
+    // Set when the client disconnects, which can happen while paused
+    disconnected: Rc<Cell<bool>>,
+}
+
+impl<R: BufRead + 'static, W: Write + 'static> Server<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            connection: Rc::new(RefCell::new(Connection {
+                input,
+                output,
+                seq: 0,
+            })),
+            launch: None,
+            breakpoints: Vec::new(),
+            configured: false,
+            started: false,
+            disconnected: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Serve requests until the client disconnects or the input ends.
+    pub fn run(&mut self) -> io::Result<()> {
+        while !self.disconnected.get() {
+            let Some(request) = self.connection.borrow_mut().read()? else {
+                break;
+            };
+            self.handle(&request)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, request: &Json) -> io::Result<()> {
+        let args = &request["arguments"];
+        let mut connection = self.connection.borrow_mut();
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(request, json!({"supportsConfigurationDoneRequest": true}))
+            }
+            // Configuration requests come after `initialized`, so breakpoints
+            // can be mapped to the launched program
+            "launch" => match Launch::new(args) {
+                Ok(launch) => {
+                    self.launch = Some(launch);
+                    connection.respond(request, json!({}))?;
+                    connection.event("initialized", json!({}))
+                }
+                Err(err) => connection.fail(request, &err.to_string()),
+            },
+            "setBreakpoints" => match &self.launch {
+                Some(launch) => {
+                    let (breakpoints, body) = launch.program.set_breakpoints(args);
+                    self.breakpoints = breakpoints;
+                    connection.respond(request, body)
+                }
+                None => connection.fail(request, "Nothing was launched"),
+            },
+            "configurationDone" => {
+                self.configured = true;
+                connection.respond(request, json!({}))?;
+                drop(connection);
+                self.start()
+            }
+            "threads" => connection.respond(request, threads()),
+            "disconnect" | "terminate" => {
+                self.disconnected.set(true);
+                connection.respond(request, json!({}))
+            }
+            command => connection.fail(request, &format!("Unsupported request {command}")),
+        }
+    }
+
+    /// Evaluate what was launched, once configured. Pauses are handled by
+    /// [`Frontend`] until evaluation is done.
+    fn start(&mut self) -> io::Result<()> {
+        let Some(launch) = &self.launch else {
+            return Ok(());
+        };
+        if !self.configured || self.started {
+            return Ok(());
+        }
+        self.started = true;
+
+        let frontend = Frontend {
+            connection: self.connection.clone(),
+            program: launch.program.clone(),
+            disconnected: self.disconnected.clone(),
+            entry: launch.stop_on_entry,
+        };
+        let debugger =
+            Debugger::new(frontend, self.breakpoints.clone()).stop_on_entry(launch.stop_on_entry);
+        let (output, category, exit_code) = match launch.run(debugger) {
+            Ok((output, succeeded)) => (output, "stdout", if succeeded { 0 } else { 1 }),
+            // Evaluation was aborted, there is no one to tell
+            Err(_) if self.disconnected.get() => return Ok(()),
+            Err(err) => (format!("{err}\n"), "stderr", 1),
+        };
+
+        let mut connection = self.connection.borrow_mut();
+        connection.event("output", json!({"category": category, "output": output}))?;
+        connection.event("terminated", json!({}))?;
+        connection.event("exited", json!({"exitCode": exit_code}))
+    }
+}
+
+fn threads() -> Json {
+    json!({"threads": [{"id": THREAD_ID, "name": "main"}]})
+}
+
+/// Serves the requests made while evaluation is paused.
+struct Frontend<R, W> {
+    connection: Rc<RefCell<Connection<R, W>>>,
+    program: Rc<Program>,
+    disconnected: Rc<Cell<bool>>,
+    // Whether the next pause is the one on entry
+    entry: bool,
+}
+
+impl<R: BufRead, W: Write> Frontend<R, W> {
+    fn session(
+        &mut self,
+        pause: &Pause,
+        breakpoints: &mut Vec<Breakpoint>,
+    ) -> io::Result<DebugCommand> {
+        let reason = match pause.reason {
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step if std::mem::take(&mut self.entry) => "entry",
+            PauseReason::Step => "step",
+        };
+        let description = match pause.branch {
+            Some((index, count)) => format!(
+                "Trying branch {} of {count} of any: {}",
+                index + 1,
+                pause.label
+            ),
+            None => format!("Paused at {}", pause.label),
+        };
+        let mut connection = self.connection.borrow_mut();
+        connection.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )?;
+
+        let variables = Variables::new(pause);
+        loop {
+            let Some(request) = connection.read()? else {
+                self.disconnected.set(true);
+                return Ok(DebugCommand::Abort);
+            };
+            let args = &request["arguments"];
+            let command = match request["command"].as_str().unwrap_or_default() {
+                "continue" => DebugCommand::Continue,
+                "next" => DebugCommand::StepOver,
+                "stepIn" => DebugCommand::StepIn,
+                "stepOut" => DebugCommand::StepOut,
+                "disconnect" | "terminate" => {
+                    self.disconnected.set(true);
+                    DebugCommand::Abort
+                }
+                "threads" => {
+                    connection.respond(&request, threads())?;
+                    continue;
+                }
+                "stackTrace" => {
+                    connection.respond(&request, self.stack_trace(pause))?;
+                    continue;
+                }
+                "scopes" => {
+                    connection.respond(&request, scopes())?;
+                    continue;
+                }
+                "variables" => {
+                    let reference = args["variablesReference"].as_u64().unwrap_or_default();
+                    let body = json!({"variables": variables.get(reference as usize)});
+                    connection.respond(&request, body)?;
+                    continue;
+                }
+                "setBreakpoints" => {
+                    let (new_breakpoints, body) = self.program.set_breakpoints(args);
+                    *breakpoints = new_breakpoints;
+                    connection.respond(&request, body)?;
+                    continue;
+                }
+                command => {
+                    connection.fail(&request, &format!("Unsupported request {command}"))?;
+                    continue;
+                }
+            };
+            let body = match command {
+                DebugCommand::Continue => json!({"allThreadsContinued": true}),
+                _ => json!({}),
+            };
+            connection.respond(&request, body)?;
+            return Ok(command);
+        }
+    }
+
+    /// The expression being evaluated, on top of the definitions being
+    /// evaluated, innermost first. Expressions without a recorded span are
+    /// shown on the line of their definition.
+    fn stack_trace(&self, pause: &Pause) -> Json {
+        let definition_line = |name: &QuintName| {
+            self.program
+                .definition(name)
+                .and_then(|def| self.program.line_of(def.id))
+        };
+        let line = self
+            .program
+            .line_of(pause.id)
+            .or_else(|| pause.stack.last().and_then(definition_line));
+
+        let frames = std::iter::once((pause.label.to_string(), line))
+            .chain(
+                pause
+                    .stack
+                    .iter()
+                    .rev()
+                    .map(|name| (name.to_string(), definition_line(name))),
+            )
+            .enumerate()
+            .map(|(id, (name, line))| {
+                json!({
+                    "id": id,
+                    "name": name,
+                    "line": line.unwrap_or(1),
+                    "column": 1,
+                    "source": self.program.source(),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({"stackFrames": frames, "totalFrames": frames.len()})
+    }
+}
+
+impl<R: BufRead, W: Write> DebugFrontend for Frontend<R, W> {
+    fn paused(&mut self, pause: &Pause, breakpoints: &mut Vec<Breakpoint>) -> DebugCommand {
+        self.session(pause, breakpoints)
+            .unwrap_or(DebugCommand::Abort)
+    }
+}
+
+fn scopes() -> Json {
+    let scope = |name: &str, reference: usize| json!({"name": name, "variablesReference": reference, "expensive": false});
+    json!({"scopes": [
+        scope("State", STATE_SCOPE),
+        scope("Next state", NEXT_STATE_SCOPE),
+        scope("Parameters", PARAMETERS_SCOPE),
+    ]})
+}
+
+/// The variables shown in a pause, by reference (starting at 1). The elements
+/// of compound values get references of their own.
+struct Variables(Vec<Vec<Json>>);
+
+impl Variables {
+    fn new(pause: &Pause) -> Self {
+        let mut variables = Self(vec![Vec::new(); PARAMETERS_SCOPE]);
+        let state = pause
+            .vars
+            .iter()
+            .map(|(name, value)| variables.variable(name.to_string(), value.as_ref()))
+            .collect();
+        let next_state = pause
+            .next_vars
+            .iter()
+            .map(|(name, value)| variables.variable(name.to_string(), value.as_ref()))
+            .collect();
+        let params = pause
+            .params
+            .iter()
+            .map(|(name, value)| variables.variable(name.to_string(), Some(value)))
+            .collect();
+
+        variables.0[STATE_SCOPE - 1] = state;
+        variables.0[NEXT_STATE_SCOPE - 1] = next_state;
+        variables.0[PARAMETERS_SCOPE - 1] = params;
+        variables
+    }
+
+    fn variable(&mut self, name: String, value: Option<&Value>) -> Json {
+        let Some(value) = value else {
+            return json!({"name": name, "value": "(not set)", "variablesReference": 0});
+        };
+
+        let elements = elements(value);
+        let reference = if elements.is_empty() {
+            0
+        } else {
+            let elements = elements
+                .into_iter()
+                .map(|(name, value)| self.variable(name, Some(value)))
+                .collect();
+            self.0.push(elements);
+            self.0.len()
+        };
+        json!({
+            "name": name,
+            "value": QuintSyntax(value).to_string(),
+            "variablesReference": reference,
+        })
+    }
+
+    fn get(&self, reference: usize) -> &[Json] {
+        reference
+            .checked_sub(1)
+            .and_then(|i| self.0.get(i))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The elements of a compound value, with their names. Intermediate values
+/// (intervals, power sets, etc.) are not expanded.
+fn elements(value: &Value) -> Vec<(String, &Value)> {
+    match value {
+        Value::Record(fields) => {
+            let mut fields = fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<Vec<_>>();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            fields
+        }
+        Value::Tuple(elements) | Value::List(elements) => elements
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("[{i}]"), value))
+            .collect(),
+        Value::Set(elements) => elements
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("[{i}]"), value))
+            .collect(),
+        Value::Map(pairs) => pairs
+            .iter()
+            .map(|(key, value)| (QuintSyntax(key).to_string(), value))
+            .collect(),
+        Value::Variant(_, value) => vec![("value".to_string(), value.as_ref())],
+        _ => Vec::new(),
+    }
+}
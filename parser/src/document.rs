@@ -0,0 +1,618 @@
+//!
+//! A Quint file being edited, parsed one top-level declaration at a time.
+//!
+//! The file is split into declarations with the lexer, following brackets, so
+//! that after an edit only the declarations whose text changed are parsed
+//! again. The others are reused, with their ids, from the previous version.
+//! Names are then resolved across the whole module, which is cheap compared to
+//! parsing. Declarations this parser doesn't support yet (types, constants,
+//! imports, ...) are skipped with a warning, so the rest of the file can still
+//! be navigated.
+//!
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use quint_evaluator::builtins::is_builtin_op;
+use quint_evaluator::evaluator;
+use quint_evaluator::value::Value;
+
+use crate::lexer::{QuintLexer, Token};
+use crate::quint::DeclarationParser;
+use crate::resolver::{BUILTIN_NAMES, Resolver};
+use crate::utils::QuintIdGenerator;
+use crate::{
+    LookupDefinition, LookupTable, OpDef, QuintDeclaration, QuintEx, QuintId, QuintModule,
+    QuintName,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the file, between two byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A top-level declaration, as found by the lexer.
+pub struct Item {
+    pub start: usize,
+    pub end: usize,
+    /// The keywords starting the declaration (e.g. `pure def`, `var`, `type`)
+    pub keyword: String,
+    /// The declared name, and where it is
+    pub name: Option<(String, usize, usize)>,
+    // `None` for declarations this parser doesn't support
+    parsed: Option<Rc<Parsed>>,
+}
+
+impl Item {
+    /// The parsed declaration, if it is supported and has no syntax errors
+    pub fn declaration(&self) -> Option<&QuintDeclaration> {
+        self.parsed.as_ref()?.declaration.as_ref().ok()
+    }
+}
+
+/// The result of parsing the text of an item, with byte offsets relative to
+/// where the item starts.
+struct Parsed {
+    declaration: Result<QuintDeclaration, Diagnostic>,
+    name_spans: HashMap<QuintId, (usize, usize)>,
+}
+
+/// Keywords starting the declarations this parser supports
+const SUPPORTED: [&str; 8] = [
+    "val", "def", "pure val", "pure def", "action", "temporal", "run", "var",
+];
+
+pub struct Document {
+    text: String,
+    line_starts: Vec<usize>,
+    module_name: Option<(QuintName, usize, usize)>,
+    items: Vec<Item>,
+    // Keeps counting across versions, so new declarations never reuse the ids
+    // of the ones kept from the previous version
+    ids: QuintIdGenerator,
+    name_spans: HashMap<QuintId, (usize, usize)>,
+    // The ids of lambda parameters
+    params: HashSet<QuintId>,
+    table: LookupTable,
+    diagnostics: Vec<Diagnostic>,
+    reparsed: usize,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Self {
+            text: String::new(),
+            line_starts: Vec::new(),
+            module_name: None,
+            items: Vec::new(),
+            ids: QuintIdGenerator::default(),
+            name_spans: HashMap::new(),
+            params: HashSet::new(),
+            table: LookupTable::default(),
+            diagnostics: Vec::new(),
+            reparsed: 0,
+        };
+        document.set_text(text);
+        document
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, parsing the declarations that changed and resolving
+    /// names again.
+    pub fn set_text(&mut self, text: String) {
+        // Previous parses, by the text they were parsed from. Each one is
+        // reused at most once, so ids are never shared by two items.
+        let mut previous: HashMap<String, Vec<Rc<Parsed>>> = HashMap::new();
+        for item in std::mem::take(&mut self.items) {
+            if let Some(parsed) = item.parsed {
+                previous
+                    .entry(self.text[item.start..item.end].to_string())
+                    .or_default()
+                    .push(parsed);
+            }
+        }
+
+        self.line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.text = text;
+        self.diagnostics.clear();
+
+        self.reparsed = 0;
+        let (module_name, items) = self.split();
+        self.module_name = module_name;
+        for mut item in items {
+            if SUPPORTED.contains(&item.keyword.as_str()) {
+                let source = &self.text[item.start..item.end];
+                let parsed = match previous.get_mut(source).and_then(Vec::pop) {
+                    Some(parsed) => parsed,
+                    None => {
+                        self.reparsed += 1;
+                        Rc::new(parse_item(source, &mut self.ids))
+                    }
+                };
+                item.parsed = Some(parsed);
+            } else if item.keyword != "module" {
+                self.diagnostics.push(Diagnostic {
+                    start: item.start,
+                    end: item.start + item.keyword.len(),
+                    severity: Severity::Warning,
+                    message: format!("`{}` declarations are not supported yet", item.keyword),
+                });
+            }
+            self.items.push(item);
+        }
+
+        self.analyze();
+    }
+
+    /// How many declarations were parsed by the last change, as opposed to
+    /// reused from the previous version
+    pub fn reparsed(&self) -> usize {
+        self.reparsed
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// The name of the module, and where it is, if the file has one
+    pub fn module_name(&self) -> Option<&(QuintName, usize, usize)> {
+        self.module_name.as_ref()
+    }
+
+    /// The resolution of names to their definitions, for the whole module
+    pub fn table(&self) -> &LookupTable {
+        &self.table
+    }
+
+    /// Where a name is: of a definition, variable or parameter (by its id) or
+    /// of an expression referring to one (by the id of the expression)
+    pub fn name_span(&self, id: QuintId) -> Option<(usize, usize)> {
+        self.name_spans.get(&id).copied()
+    }
+
+    /// The id of the definition, variable or parameter named at an offset,
+    /// either where it is declared or where it is referred to
+    pub fn definition_at(&self, offset: usize) -> Option<QuintId> {
+        let (id, _) = self
+            .name_spans
+            .iter()
+            .filter(|(_, (start, end))| *start <= offset && offset <= *end)
+            .min_by_key(|(_, (start, end))| end - start)?;
+
+        match self.table.get(id) {
+            Some(def) => Some(def.id()),
+            None if self.params.contains(id) => Some(*id),
+            None => None,
+        }
+    }
+
+    /// Where the definition, variable or parameter with this id is referred to
+    pub fn references(&self, id: QuintId) -> Vec<(usize, usize)> {
+        let mut references = self
+            .name_spans
+            .iter()
+            .filter(|(reference, _)| {
+                **reference != id && self.table.get(*reference).is_some_and(|def| def.id() == id)
+            })
+            .map(|(_, span)| *span)
+            .collect::<Vec<_>>();
+        references.sort();
+        references
+    }
+
+    /// The value of a `pure val`, if it can be evaluated: all the names it
+    /// depends on, directly or not, must be resolved.
+    pub fn evaluate(&self, def: &OpDef) -> Option<Value> {
+        if !self.is_resolved(&def.expr, &mut HashSet::new()) {
+            return None;
+        }
+        evaluator::run(&self.table, &def.expr).ok()
+    }
+
+    fn is_resolved(&self, expr: &QuintEx, visited: &mut HashSet<QuintId>) -> bool {
+        let resolved = |id: &QuintId, visited: &mut HashSet<QuintId>| match self.table.get(id) {
+            Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) => {
+                !visited.insert(def.id) || self.is_resolved(&def.expr, visited)
+            }
+            Some(_) => true,
+            None => false,
+        };
+        match expr {
+            QuintEx::QuintName { id, name } => {
+                resolved(id, visited) || BUILTIN_NAMES.contains(&name.as_str())
+            }
+            QuintEx::QuintApp { id, opcode, args } => {
+                (resolved(id, visited) || is_builtin_op(opcode))
+                    && args.iter().all(|arg| self.is_resolved(arg, visited))
+            }
+            QuintEx::QuintLambda { expr, .. } => self.is_resolved(expr, visited),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.is_resolved(&opdef.expr, visited) && self.is_resolved(expr, visited)
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => true,
+        }
+    }
+
+    /// The (zero-based) line and UTF-16 column of a byte offset, as in LSP
+    /// positions
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line as u32, column as u32)
+    }
+
+    /// The byte offset of a line and UTF-16 column
+    pub fn offset(&self, line: u32, column: u32) -> usize {
+        let Some(start) = self.line_starts.get(line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.text[*start..].char_indices() {
+            if units >= column as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// The text indented by bracket depth (two spaces per level, and one more
+    /// for lines continuing a declaration), without trailing whitespace, and
+    /// with at most one blank line in a row. Multi-line comments and strings
+    /// are kept as they are.
+    pub fn format(&self) -> String {
+        let tokens = tokens(&self.text);
+        let top = usize::from(self.module_name.is_some());
+        let mut depth = 0usize;
+        let mut next = 0;
+        let mut blank = false;
+        let mut output = String::new();
+
+        for (i, start) in self.line_starts.iter().enumerate() {
+            let end = self
+                .line_starts
+                .get(i + 1)
+                .map_or(self.text.len(), |next| next - 1);
+            let line = &self.text[*start..end];
+            while let Some((token_start, token, _)) = tokens.get(next)
+                && token_start < start
+            {
+                depth = match token {
+                    Token::LParen | Token::LBrace | Token::LBracket => depth + 1,
+                    Token::RParen | Token::RBrace | Token::RBracket => depth.saturating_sub(1),
+                    _ => depth,
+                };
+                next += 1;
+            }
+            if next > 0 && tokens[next - 1].2 > *start {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+
+            let content = line.trim();
+            if content.is_empty() {
+                blank = !output.is_empty();
+                continue;
+            }
+            if std::mem::take(&mut blank) {
+                output.push('\n');
+            }
+
+            let first = tokens
+                .get(next)
+                .filter(|(token_start, _, _)| *token_start == end - line.trim_start().len())
+                .map(|(_, token, _)| token);
+            let indent = match first {
+                Some(Token::RParen | Token::RBrace | Token::RBracket) => depth.saturating_sub(1),
+                Some(token) if depth == top && keyword(token).is_none() => depth + 1,
+                _ => depth,
+            };
+            output.push_str(&"  ".repeat(indent));
+            output.push_str(content);
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Find the module header and the top-level declarations. Problems with
+    /// the structure of the file are added to the diagnostics.
+    fn split(&mut self) -> (Option<(QuintName, usize, usize)>, Vec<Item>) {
+        let tokens = tokens(&self.text);
+        let mut rest = tokens.as_slice();
+
+        let module_name = match rest {
+            [
+                (_, Token::Module, _),
+                (start, Token::LowId(name) | Token::CapId(name), end),
+                (_, Token::LBrace, _),
+                ..,
+            ] => {
+                rest = &rest[3..];
+                Some((name.as_str().into(), *start, *end))
+            }
+            _ => {
+                self.diagnostics.push(Diagnostic {
+                    start: 0,
+                    end: tokens.first().map_or(0, |(_, _, end)| *end),
+                    severity: Severity::Error,
+                    message: "Expected a module: `module <name> { ... }`".to_string(),
+                });
+                None
+            }
+        };
+
+        let top = usize::from(module_name.is_some());
+        let mut depth = top;
+        let mut items: Vec<Item> = Vec::new();
+        let mut last_end = None;
+        let mut closed = module_name.is_none();
+        for (i, (start, token, end)) in rest.iter().enumerate() {
+            let closing = matches!(token, Token::RParen | Token::RBrace | Token::RBracket);
+            if closing && depth == top && top == 1 {
+                // The end of the module
+                closed = true;
+                if let Some((extra, _, _)) = rest.get(i + 1) {
+                    self.diagnostics.push(Diagnostic {
+                        start: *extra,
+                        end: self.text.len(),
+                        severity: Severity::Error,
+                        message: "Unexpected text after the module".to_string(),
+                    });
+                }
+                break;
+            }
+            if closing {
+                depth = depth.saturating_sub(1);
+            }
+
+            let after_pure = i > 0 && rest[i - 1].1 == Token::Pure;
+            match keyword(token) {
+                Some(keyword) if depth == top && !after_pure => {
+                    if let (Some(item), Some(end)) = (items.last_mut(), last_end) {
+                        item.end = end;
+                    }
+                    items.push(Item {
+                        start: *start,
+                        end: *end,
+                        keyword: keyword.to_string(),
+                        name: None,
+                        parsed: None,
+                    });
+                }
+                Some(keyword) if after_pure && depth == top => {
+                    if let Some(item) = items.last_mut() {
+                        item.keyword = format!("pure {keyword}");
+                    }
+                }
+                _ if depth == top && items.is_empty() => {
+                    self.diagnostics.push(Diagnostic {
+                        start: *start,
+                        end: *end,
+                        severity: Severity::Error,
+                        message: "Expected a declaration".to_string(),
+                    });
+                }
+                _ => {}
+            }
+            if let (Some(item), Token::LowId(name) | Token::CapId(name)) = (items.last_mut(), token)
+                && item.name.is_none()
+            {
+                item.name = Some((name.clone(), *start, *end));
+            }
+
+            if matches!(token, Token::LParen | Token::LBrace | Token::LBracket) {
+                depth += 1;
+            }
+            last_end = Some(*end);
+        }
+        if let (Some(item), Some(end)) = (items.last_mut(), last_end) {
+            item.end = end;
+        }
+        if !closed {
+            self.diagnostics.push(Diagnostic {
+                start: self.text.len(),
+                end: self.text.len(),
+                severity: Severity::Error,
+                message: "Missing `}` closing the module".to_string(),
+            });
+        }
+
+        (module_name, items)
+    }
+
+    /// Resolve names across the module, and gather the diagnostics of all
+    /// declarations.
+    fn analyze(&mut self) {
+        self.name_spans.clear();
+        self.params.clear();
+        let mut declarations = Vec::new();
+        let mut defined = HashMap::new();
+        let mut imports = false;
+        for item in &self.items {
+            imports |= item.keyword == "import";
+            let Some(parsed) = &item.parsed else {
+                continue;
+            };
+            let declaration = match &parsed.declaration {
+                Ok(declaration) => declaration,
+                Err(diagnostic) => {
+                    self.diagnostics.push(Diagnostic {
+                        start: item.start + diagnostic.start,
+                        end: item.start + diagnostic.end,
+                        ..diagnostic.clone()
+                    });
+                    continue;
+                }
+            };
+
+            self.name_spans.extend(
+                parsed
+                    .name_spans
+                    .iter()
+                    .map(|(id, (start, end))| (*id, (item.start + start, item.start + end))),
+            );
+            if let QuintDeclaration::QuintOpDef(def) = declaration {
+                collect_params(&def.expr, &mut self.params);
+            }
+            if let Some((name, start, end)) = &item.name
+                && defined.insert(name.clone(), ()).is_some()
+            {
+                self.diagnostics.push(Diagnostic {
+                    start: *start,
+                    end: *end,
+                    severity: Severity::Error,
+                    message: format!("'{name}' is already defined"),
+                });
+            }
+            declarations.push(declaration.clone());
+        }
+
+        let module = QuintModule {
+            name: self
+                .module_name
+                .as_ref()
+                .map_or_else(|| "main".into(), |(name, _, _)| name.clone()),
+            declarations,
+        };
+        let mut resolver = Resolver::default();
+        for (id, error) in resolver.check_module(&module) {
+            let Some((start, end)) = self.name_spans.get(&id).copied() else {
+                continue;
+            };
+            // Names that aren't defined here could come from imported modules,
+            // which aren't loaded
+            if imports && !defined.contains_key(&self.text[start..end]) {
+                continue;
+            }
+            self.diagnostics.push(Diagnostic {
+                start,
+                end,
+                severity: Severity::Error,
+                message: error.to_string(),
+            });
+        }
+        self.table = resolver.table;
+        self.diagnostics.sort_by_key(|d| (d.start, d.end));
+    }
+}
+
+/// The tokens of a text, with their byte offsets, skipping what can't be
+/// lexed and comments
+fn tokens(text: &str) -> Vec<(usize, Token, usize)> {
+    QuintLexer::new(text)
+        .filter_map(Result::ok)
+        .filter(|(_, token, _)| *token != Token::Comment)
+        .collect()
+}
+
+/// The keyword, if the token starts a top-level declaration
+fn keyword(token: &Token) -> Option<&'static str> {
+    Some(match token {
+        Token::Val => "val",
+        Token::Def => "def",
+        Token::Pure => "pure",
+        Token::Action => "action",
+        Token::Temporal => "temporal",
+        Token::Run => "run",
+        Token::Var => "var",
+        Token::Const => "const",
+        Token::Assume => "assume",
+        Token::Type => "type",
+        Token::Import => "import",
+        Token::Export => "export",
+        Token::Module => "module",
+        _ => return None,
+    })
+}
+
+/// Parse the text of a declaration, with offsets relative to its start.
+fn parse_item(source: &str, ids: &mut QuintIdGenerator) -> Parsed {
+    let lexer =
+        QuintLexer::new(source).filter(|token| !matches!(token, Ok((_, Token::Comment, _))));
+    let declaration = DeclarationParser::new().parse(ids, lexer).map_err(|error| {
+        use lalrpop_util::ParseError;
+        let (start, end, message) = match error {
+            ParseError::InvalidToken { location } => {
+                (location, location + 1, "Invalid token".to_string())
+            }
+            ParseError::UnrecognizedEof { location, expected } => (
+                location,
+                location,
+                format!(
+                    "Unexpected end of declaration, expected {}",
+                    expected.join(", ")
+                ),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (start, token, end),
+                expected,
+            } => (
+                start,
+                end,
+                format!("Unexpected {token:?}, expected {}", expected.join(", ")),
+            ),
+            ParseError::ExtraToken {
+                token: (start, token, end),
+            } => (start, end, format!("Unexpected {token:?}")),
+            ParseError::User { error } => (0, 0, format!("{error:?}")),
+        };
+        Diagnostic {
+            start,
+            end,
+            severity: Severity::Error,
+            message,
+        }
+    });
+
+    // Breakpoints aren't set from here
+    ids.spans.clear();
+    Parsed {
+        declaration,
+        name_spans: std::mem::take(&mut ids.name_spans),
+    }
+}
+
+fn collect_params(expr: &QuintEx, params: &mut HashSet<QuintId>) {
+    match expr {
+        QuintEx::QuintApp { args, .. } => {
+            for arg in args {
+                collect_params(arg, params);
+            }
+        }
+        QuintEx::QuintLambda {
+            params: lambda_params,
+            expr,
+            ..
+        } => {
+            params.extend(lambda_params.iter().map(|p| p.id));
+            collect_params(expr, params);
+        }
+        QuintEx::QuintLet { opdef, expr, .. } => {
+            collect_params(&opdef.expr, params);
+            collect_params(expr, params);
+        }
+        _ => {}
+    }
+}
@@ -3,7 +3,12 @@
 //!
 //!
 
+pub mod dap;
+pub mod document;
 pub mod lexer;
+pub mod lsp;
+pub mod repl;
+pub mod resolver;
 pub mod utils;
 pub use quint_evaluator::ir::*;
 
@@ -11,16 +16,47 @@ use lalrpop_util::{ParseError, lalrpop_mod};
 
 lalrpop_mod!(pub quint);
 
-use crate::quint::ExpressionParser;
+use crate::quint::{DeclarationParser, ExpressionParser, ModuleParser};
 use eyre::Result;
 use lexer::{LexicalError, QuintLexer, Token};
 use utils::QuintIdGenerator;
 
 pub fn parse_quint_expr(content: &str) -> Result<QuintEx, ParseError<usize, Token, LexicalError>> {
     let mut generator = QuintIdGenerator::default();
+    parse_quint_expr_with(content, &mut generator)
+}
+
+/// Parse an expression taking ids from `generator`, so they don't collide with
+/// the ids of previously parsed expressions.
+pub fn parse_quint_expr_with(
+    content: &str,
+    generator: &mut QuintIdGenerator,
+) -> Result<QuintEx, ParseError<usize, Token, LexicalError>> {
     let lexer = QuintLexer::new(content);
     let parser = ExpressionParser::new();
-    parser.parse(&mut generator, lexer)
+    parser.parse(generator, lexer)
+}
+
+/// Parse a top-level declaration (`val`, `def`, `action`, `var`, etc.),
+/// taking ids from `generator`.
+pub fn parse_quint_declaration(
+    content: &str,
+    generator: &mut QuintIdGenerator,
+) -> Result<QuintDeclaration, ParseError<usize, Token, LexicalError>> {
+    let lexer = QuintLexer::new(content);
+    let parser = DeclarationParser::new();
+    parser.parse(generator, lexer)
+}
+
+/// Parse a module (`module name { ... }`), taking ids from `generator`, which
+/// also records the source spans of its definitions.
+pub fn parse_quint_module(
+    content: &str,
+    generator: &mut QuintIdGenerator,
+) -> Result<QuintModule, ParseError<usize, Token, LexicalError>> {
+    let lexer = QuintLexer::new(content);
+    let parser = ModuleParser::new();
+    parser.parse(generator, lexer)
 }
 
 #[cfg(test)]
@@ -0,0 +1,425 @@
+//!
+//! A Language Server Protocol (LSP) server for Quint.
+//!
+//! The server talks to an editor over a pair of streams (usually stdin and
+//! stdout), keeping a [`Document`] for each open file, and offers:
+//!  - diagnostics from parsing and name resolution, sent on every change;
+//!  - go-to-definition and find-references, through the lookup table;
+//!  - hover, showing the qualifier and type annotation of definitions, and the
+//!    value of `pure val`s;
+//!  - document symbols for the declarations of the module;
+//!  - formatting (see [`Document::format`]);
+//!  - inlay hints with the values of `pure val`s, evaluated with
+//!    [`evaluator::run`](quint_evaluator::evaluator::run).
+//!
+//! Changes can be sent incrementally, and only the declarations they touch are
+//! parsed again.
+//!
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use quint_evaluator::render::QuintSyntax;
+use quint_evaluator::transport::{read_message, write_message};
+use quint_evaluator::types::QuintType;
+use serde_json::{Value as Json, json};
+
+use crate::document::{Document, Severity};
+use crate::{LookupDefinition, OpQualifier, QuintDeclaration, QuintId};
+
+// JSON-RPC error codes
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+type RequestResult = Result<Json, (i64, String)>;
+
+pub struct Server<R, W> {
+    input: R,
+    output: W,
+    documents: HashMap<String, Document>,
+    shutting_down: bool,
+}
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            documents: HashMap::new(),
+            shutting_down: false,
+        }
+    }
+
+    /// Serve requests until the `exit` notification or the end of the input.
+    pub fn run(&mut self) -> io::Result<()> {
+        while let Some(message) = read_message(&mut self.input)? {
+            let method = message["method"].as_str().unwrap_or_default();
+            let params = &message["params"];
+            if method == "exit" {
+                break;
+            }
+
+            if message.get("id").is_none() {
+                self.notification(method, params)?;
+                continue;
+            }
+            let result = if self.shutting_down {
+                Err((INVALID_REQUEST, "The server is shutting down".to_string()))
+            } else {
+                self.request(method, params)
+            };
+            let response = match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": message["id"], "result": result}),
+                Err((code, message_text)) => json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": {"code": code, "message": message_text},
+                }),
+            };
+            write_message(&mut self.output, &response)?;
+        }
+        Ok(())
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_string()));
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didChange" => {
+                let Some(document) = self.documents.get_mut(&uri) else {
+                    return Ok(());
+                };
+                for change in params["contentChanges"].as_array().into_iter().flatten() {
+                    let text = change["text"].as_str().unwrap_or_default();
+                    let range = &change["range"];
+                    let text = if range.is_null() {
+                        text.to_string()
+                    } else {
+                        let start = offset(document, &range["start"]);
+                        let end = offset(document, &range["end"]).max(start);
+                        let old = document.text();
+                        format!("{}{text}{}", &old[..start], &old[end..])
+                    };
+                    document.set_text(text);
+                }
+                self.publish_diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                let params = json!({"uri": uri, "diagnostics": []});
+                self.notify("textDocument/publishDiagnostics", params)
+            }
+            // `initialized`, `$/cancelRequest`, etc.
+            _ => Ok(()),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> RequestResult {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Incremental changes
+                    "textDocumentSync": {"openClose": true, "change": 2},
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "documentFormattingProvider": true,
+                    "inlayHintProvider": true,
+                },
+                "serverInfo": {"name": "quint-lsp"},
+            })),
+            "shutdown" => {
+                self.shutting_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/formatting" => self.formatting(params),
+            "textDocument/inlayHint" => self.inlay_hints(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unsupported request {method}"))),
+        }
+    }
+
+    fn document(&self, params: &Json) -> Result<(&str, &Document), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents
+            .get_key_value(uri)
+            .map(|(uri, document)| (uri.as_str(), document))
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document {uri}")))
+    }
+
+    fn definition(&self, params: &Json) -> RequestResult {
+        let (uri, document) = self.document(params)?;
+        let definition = document
+            .definition_at(offset(document, &params["position"]))
+            .and_then(|id| document.name_span(id));
+        Ok(match definition {
+            Some((start, end)) => location(uri, document, start, end),
+            None => Json::Null,
+        })
+    }
+
+    fn references(&self, params: &Json) -> RequestResult {
+        let (uri, document) = self.document(params)?;
+        let Some(id) = document.definition_at(offset(document, &params["position"])) else {
+            return Ok(Json::Null);
+        };
+
+        let mut spans = document.references(id);
+        if params["context"]["includeDeclaration"].as_bool() == Some(true)
+            && let Some(declaration) = document.name_span(id)
+        {
+            spans.insert(0, declaration);
+        }
+        Ok(spans
+            .into_iter()
+            .map(|(start, end)| location(uri, document, start, end))
+            .collect())
+    }
+
+    fn hover(&self, params: &Json) -> RequestResult {
+        let (_, document) = self.document(params)?;
+        let hover = document
+            .definition_at(offset(document, &params["position"]))
+            .and_then(|id| describe(document, id));
+        Ok(match hover {
+            Some(value) => json!({"contents": {"kind": "markdown", "value": value}}),
+            None => Json::Null,
+        })
+    }
+
+    fn document_symbols(&self, params: &Json) -> RequestResult {
+        let (_, document) = self.document(params)?;
+        let symbols = document
+            .items()
+            .iter()
+            .filter_map(|item| {
+                let (name, start, end) = item.name.as_ref()?;
+                let kind = symbol_kind(&item.keyword)?;
+                Some(json!({
+                    "name": name,
+                    "detail": item.keyword,
+                    "kind": kind,
+                    "range": range(document, item.start, item.end),
+                    "selectionRange": range(document, *start, *end),
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(match document.module_name() {
+            Some((name, start, end)) => json!([{
+                "name": name,
+                "kind": MODULE_SYMBOL,
+                "range": range(document, 0, document.text().len()),
+                "selectionRange": range(document, *start, *end),
+                "children": symbols,
+            }]),
+            None => symbols.into(),
+        })
+    }
+
+    fn formatting(&self, params: &Json) -> RequestResult {
+        let (_, document) = self.document(params)?;
+        let formatted = document.format();
+        if formatted == document.text() {
+            return Ok(json!([]));
+        }
+        Ok(json!([{
+            "range": range(document, 0, document.text().len()),
+            "newText": formatted,
+        }]))
+    }
+
+    fn inlay_hints(&self, params: &Json) -> RequestResult {
+        let (_, document) = self.document(params)?;
+        let start = offset(document, &params["range"]["start"]);
+        let end = match &params["range"]["end"] {
+            Json::Null => document.text().len(),
+            position => offset(document, position),
+        };
+
+        let hints = document
+            .items()
+            .iter()
+            .filter(|item| start <= item.end && item.end <= end)
+            .filter_map(|item| match item.declaration()? {
+                QuintDeclaration::QuintOpDef(def) if def.qualifier == OpQualifier::PureVal => {
+                    let value = document.evaluate(def)?;
+                    let (line, character) = document.position(item.end);
+                    Some(json!({
+                        "position": {"line": line, "character": character},
+                        "label": format!("= {}", QuintSyntax(&value)),
+                        "paddingLeft": true,
+                    }))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        Ok(hints.into())
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let Some(document) = self.documents.get(uri) else {
+            return Ok(());
+        };
+        let diagnostics = document
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": range(document, diagnostic.start, diagnostic.end),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    },
+                    "source": "quint",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect::<Vec<_>>();
+        let params = json!({"uri": uri, "diagnostics": diagnostics});
+        self.notify("textDocument/publishDiagnostics", params)
+    }
+
+    fn notify(&mut self, method: &str, params: Json) -> io::Result<()> {
+        let notification = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        write_message(&mut self.output, &notification)
+    }
+}
+
+fn offset(document: &Document, position: &Json) -> usize {
+    let number = |key: &str| position[key].as_u64().unwrap_or_default() as u32;
+    document.offset(number("line"), number("character"))
+}
+
+fn range(document: &Document, start: usize, end: usize) -> Json {
+    let position = |offset| {
+        let (line, character) = document.position(offset);
+        json!({"line": line, "character": character})
+    };
+    json!({"start": position(start), "end": position(end)})
+}
+
+fn location(uri: &str, document: &Document, start: usize, end: usize) -> Json {
+    json!({"uri": uri, "range": range(document, start, end)})
+}
+
+/// The hover text for a definition, variable or parameter
+fn describe(document: &Document, id: QuintId) -> Option<String> {
+    let (signature, value) = match document.table().get(&id) {
+        Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) => {
+            let mut signature = format!("{} {}", qualifier(&def.qualifier), def.name);
+            if let Some(type_annotation) = &def.type_annotation {
+                signature.push_str(&format!(": {}", quint_type(type_annotation)));
+            }
+            let value = match def.qualifier {
+                OpQualifier::PureVal => document.evaluate(def),
+                _ => None,
+            };
+            (signature, value)
+        }
+        Some(LookupDefinition::Definition(QuintDeclaration::QuintVar(var))) => {
+            let mut signature = format!("var {}", var.name);
+            if let Some(type_annotation) = &var.type_annotation {
+                signature.push_str(&format!(": {}", quint_type(type_annotation)));
+            }
+            (signature, None)
+        }
+        Some(_) => return None,
+        None => {
+            let (start, end) = document.name_span(id)?;
+            (
+                format!("(parameter) {}", &document.text()[start..end]),
+                None,
+            )
+        }
+    };
+
+    let mut text = format!("```quint\n{signature}\n```");
+    if let Some(value) = value {
+        text.push_str(&format!("\n\nValue: `{}`", QuintSyntax(&value)));
+    }
+    Some(text)
+}
+
+fn qualifier(qualifier: &OpQualifier) -> &'static str {
+    match qualifier {
+        OpQualifier::PureDef => "pure def",
+        OpQualifier::PureVal => "pure val",
+        OpQualifier::Def => "def",
+        OpQualifier::Val => "val",
+        OpQualifier::Nondet => "nondet",
+        OpQualifier::Action => "action",
+        OpQualifier::Run => "run",
+        OpQualifier::Temporal => "temporal",
+    }
+}
+
+/// A type in Quint syntax
+fn quint_type(t: &QuintType) -> String {
+    let list = |types: &mut dyn Iterator<Item = String>| types.collect::<Vec<_>>().join(", ");
+    match t {
+        QuintType::Bool => "bool".to_string(),
+        QuintType::Int => "int".to_string(),
+        QuintType::Str => "str".to_string(),
+        QuintType::Const { name } | QuintType::Var { name } => name.to_string(),
+        QuintType::Set { elem } => format!("Set[{}]", quint_type(elem)),
+        QuintType::List { elem } => format!("List[{}]", quint_type(elem)),
+        QuintType::Fun { arg, res } => format!("{} -> {}", quint_type(arg), quint_type(res)),
+        QuintType::Oper { args, res } => format!(
+            "({}) => {}",
+            list(&mut args.iter().map(quint_type)),
+            quint_type(res)
+        ),
+        QuintType::Tup { fields } => format!(
+            "({})",
+            list(&mut fields.fields().iter().map(|f| quint_type(&f.field_type)))
+        ),
+        QuintType::Rec { fields } => format!(
+            "{{ {} }}",
+            list(&mut fields.fields().iter().map(|f| format!(
+                "{}: {}",
+                f.field_name,
+                quint_type(&f.field_type)
+            )))
+        ),
+        QuintType::Sum { fields } => fields
+            .fields()
+            .iter()
+            .map(|f| format!("{}({})", f.field_name, quint_type(&f.field_type)))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        QuintType::App { ctor, args } => format!(
+            "{}[{}]",
+            quint_type(ctor),
+            list(&mut args.iter().map(quint_type))
+        ),
+    }
+}
+
+const MODULE_SYMBOL: u64 = 2;
+
+/// The LSP symbol kind for a declaration, if it declares a symbol
+fn symbol_kind(keyword: &str) -> Option<u64> {
+    Some(match keyword {
+        "var" => 13,
+        "val" | "pure val" | "const" => 14,
+        "def" | "pure def" => 12,
+        "action" | "run" => 6,
+        "temporal" | "assume" => 7,
+        "type" => 26,
+        _ => return None,
+    })
+}
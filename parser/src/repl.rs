@@ -0,0 +1,260 @@
+//!
+//! A read-eval-print loop session for Quint.
+//!
+//! Inputs are either declarations (`val`, `def`, `action`, `var`, ...), which
+//! are added to the session's scope, or expressions, which are evaluated in the
+//! current state. Names are resolved against the scope by a single, persistent
+//! [`Resolver`], whose lookup table the interpreter reads.
+//!
+//! Evaluating an action that assigns the state variables moves to the next
+//! state, so a trace can be built with `init`, `step`, `init.then(step)`, etc.
+//!
+
+use std::path::Path;
+
+use eyre::{Result, bail, eyre};
+use quint_evaluator::evaluator::{Env, Interpreter};
+use quint_evaluator::helpers;
+use quint_evaluator::itf::Trace;
+use quint_evaluator::rand::{Rand, parse_seed};
+use quint_evaluator::render::{QuintSyntax, render_trace};
+use quint_evaluator::value::Value;
+
+use crate::resolver::Resolver;
+use crate::utils::QuintIdGenerator;
+use crate::{
+    QuintDeclaration, QuintError, QuintEx, QuintId, parse_quint_declaration, parse_quint_expr_with,
+};
+
+pub const HELP: &str = "\
+:help              show this help
+:load <file>       load the definitions of a module (a .qnt file, or the .json output of `quint compile`)
+:reset             go back to no state, forgetting the trace
+:seed [<n>]        show the random seed, or set it to <n>
+:trace             show the states reached so far
+:quit              exit";
+
+/// Keywords starting a declaration, as opposed to an expression
+const DECLARATION_KEYWORDS: [&str; 7] = ["val", "def", "pure", "action", "run", "temporal", "var"];
+
+pub struct Repl {
+    // The top-level definitions in scope, and the resolution of names for
+    // every expression evaluated or declared so far
+    resolver: Resolver,
+
+    ids: QuintIdGenerator,
+
+    // The values of the state variables in the current state, if any
+    state: Option<Value>,
+
+    // The states reached so far, with their metadata
+    trace: Vec<Value>,
+
+    seed: u64,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new(Rand::new().get_state())
+    }
+}
+
+impl Repl {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            resolver: Resolver::default(),
+            ids: QuintIdGenerator::default(),
+            state: None,
+            trace: Vec::new(),
+            seed,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The values of the state variables in the current state, if any
+    pub fn state(&self) -> Option<&Value> {
+        self.state.as_ref()
+    }
+
+    /// Process a line (or lines) of input: a command (`:load`, `:trace`, etc.),
+    /// a declaration or an expression. Returns what should be printed, if
+    /// anything.
+    pub fn process(&mut self, input: &str) -> Result<Option<String>> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        if let Some(command) = input.strip_prefix(':') {
+            return self.command(command);
+        }
+
+        let first_word = input.split_whitespace().next().unwrap_or_default();
+        if DECLARATION_KEYWORDS.contains(&first_word) {
+            self.declare(input)?;
+            return Ok(None);
+        }
+
+        let value = self.evaluate(input)?;
+        Ok(Some(QuintSyntax(&value).to_string()))
+    }
+
+    fn command(&mut self, command: &str) -> Result<Option<String>> {
+        let (name, arg) = match command.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (command, ""),
+        };
+        match (name, arg) {
+            ("help", _) => Ok(Some(HELP.to_string())),
+            ("load", "") => bail!("Usage: :load <file>"),
+            ("load", file) => self.load(Path::new(file)).map(Some),
+            ("reset", _) => {
+                self.reset();
+                Ok(None)
+            }
+            ("seed", "") => Ok(Some(format!("{:#x}", self.seed))),
+            ("seed", seed) => {
+                self.seed = parse_seed(seed).map_err(|e| eyre!(e))?;
+                Ok(None)
+            }
+            ("trace", _) => Ok(Some(self.render_trace())),
+            _ => bail!("Unknown command :{name}, see :help"),
+        }
+    }
+
+    /// Go back to no state, forgetting the trace. Definitions are kept.
+    pub fn reset(&mut self) {
+        self.state = None;
+        self.trace.clear();
+    }
+
+    /// Load the definitions from the main module of a Quint file into scope.
+    pub fn load(&mut self, file: &Path) -> Result<String> {
+        let output = helpers::compile_from_path(file, None, &[]).map_err(|e| eyre!("{e}"))?;
+
+        // Names in the REPL must not reuse the ids from the file
+        for module in &output.modules {
+            for declaration in &module.declarations {
+                if let QuintDeclaration::QuintOpDef(def) = declaration {
+                    self.ids.skip_past(def.id.max(max_id(&def.expr)));
+                }
+            }
+        }
+        for (id, def) in &output.table {
+            self.ids.skip_past((*id).max(def.id()));
+        }
+        self.resolver.table.extend(output.table.clone());
+
+        let main = output
+            .modules
+            .iter()
+            .find(|m| m.name == output.main)
+            .ok_or_else(|| eyre!("Main module {} not found", output.main))?;
+        let mut count = 0;
+        for declaration in &main.declarations {
+            if matches!(
+                declaration,
+                QuintDeclaration::QuintOpDef(_)
+                    | QuintDeclaration::QuintVar(_)
+                    | QuintDeclaration::QuintConst(_)
+            ) {
+                self.resolver.add(declaration.clone());
+                count += 1;
+            }
+        }
+
+        Ok(format!(
+            "Loaded {count} definitions from module {}",
+            main.name
+        ))
+    }
+
+    /// Add a declaration to the scope, replacing any previous one with the
+    /// same name.
+    pub fn declare(&mut self, input: &str) -> Result<()> {
+        let declaration =
+            parse_quint_declaration(input, &mut self.ids).map_err(|e| parse_error(input, e))?;
+        self.resolver.declare(declaration)
+    }
+
+    /// Evaluate an expression in the current state. If it is an action that
+    /// succeeds, the state moves to the resulting one.
+    pub fn evaluate(&mut self, input: &str) -> Result<Value> {
+        let expr =
+            parse_quint_expr_with(input, &mut self.ids).map_err(|e| parse_error(input, e))?;
+        self.resolver.resolve(&expr)?;
+
+        let mut interpreter = Interpreter::new(&self.resolver.table);
+        // All variables are part of the state, even if the expression doesn't
+        // refer to them
+        for (id, name) in self.resolver.vars() {
+            interpreter.declare_var(id, name);
+        }
+        let compiled = interpreter.compile(&expr);
+
+        let storage = interpreter.var_storage.clone();
+        storage.borrow_mut().store_metadata = true;
+        if let Some(state) = &self.state {
+            storage.borrow_mut().set_vars_from_record(state);
+        }
+
+        let mut env = Env::with_rand_state(storage.clone(), self.seed);
+        env.collect_trace();
+        let result = compiled.execute(&mut env);
+        self.seed = env.rand.get_state();
+        let value = result?;
+
+        // States shifted into by `then`, `reps`, etc.
+        let states = env.take_collected_trace(false);
+        let mut storage = storage.borrow_mut();
+        if value == Value::Bool(true) && storage.next_vars_assigned() {
+            storage.keep_unassigned_vars();
+            storage.shift_vars();
+            self.trace.extend(states);
+            self.trace.push(storage.as_record());
+            self.state = Some(storage.vars_record());
+        }
+
+        Ok(value)
+    }
+
+    fn render_trace(&self) -> String {
+        if self.trace.is_empty() {
+            return "No states yet, evaluate an action (e.g. init) first".to_string();
+        }
+
+        let trace = Trace {
+            states: self.trace.clone(),
+            violation: false,
+            deadlock: false,
+            loop_index: None,
+            choices: Vec::new(),
+            seed: None,
+            sample: None,
+            violated_invariant: None,
+        };
+        render_trace(&trace).trim_end().to_string()
+    }
+}
+
+fn parse_error(input: &str, error: impl std::fmt::Debug) -> eyre::Report {
+    QuintError::new("QNT000", &format!("Could not parse `{input}`: {error:?}")).into()
+}
+
+/// The greatest id in an expression
+fn max_id(expr: &QuintEx) -> QuintId {
+    match expr {
+        QuintEx::QuintApp { id, args, .. } => args.iter().map(max_id).fold(*id, QuintId::max),
+        QuintEx::QuintLambda { id, params, expr } => params
+            .iter()
+            .map(|p| p.id)
+            .fold((*id).max(max_id(expr)), QuintId::max),
+        QuintEx::QuintLet { id, opdef, expr } => (*id)
+            .max(opdef.id)
+            .max(max_id(&opdef.expr))
+            .max(max_id(expr)),
+        expr => expr.id(),
+    }
+}
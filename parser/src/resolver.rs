@@ -0,0 +1,180 @@
+//!
+//! Name resolution for parsed declarations and expressions.
+//!
+//! Names are resolved against the lambda parameters in scope, then against the
+//! top-level definitions in scope, by name. Resolutions are added to a
+//! `LookupTable`, keyed by the id of the expression referring to the name, which
+//! is what the interpreter reads.
+//!
+
+use std::collections::HashMap;
+
+use eyre::Result;
+use quint_evaluator::builtins::is_builtin_op;
+
+use crate::{
+    LookupDefinition, LookupTable, QuintDeclaration, QuintError, QuintEx, QuintId,
+    QuintLambdaParameter, QuintModule, QuintName, QuintVar,
+};
+
+/// Names that are resolved by the interpreter itself
+pub(crate) const BUILTIN_NAMES: [&str; 5] = ["true", "false", "Bool", "Int", "Nat"];
+
+#[derive(Default)]
+pub struct Resolver {
+    // Resolution of names (by the id of the expression referring to them) to
+    // their definitions
+    pub table: LookupTable,
+
+    // The top-level definitions in scope, by name
+    scope: HashMap<QuintName, LookupDefinition>,
+}
+
+impl Resolver {
+    /// Add a declaration to the scope without resolving it, replacing any
+    /// previous one with the same name.
+    pub fn add(&mut self, declaration: QuintDeclaration) {
+        self.scope.insert(
+            declaration.name().clone(),
+            LookupDefinition::Definition(declaration),
+        );
+    }
+
+    /// Resolve the body of a declaration, then add it to the scope. The body
+    /// can only refer to what is already in scope.
+    pub fn declare(&mut self, declaration: QuintDeclaration) -> Result<()> {
+        if let QuintDeclaration::QuintOpDef(def) = &declaration {
+            self.resolve(&def.expr)?;
+        }
+        self.add(declaration);
+        Ok(())
+    }
+
+    /// Add all the declarations of a module to the scope, then resolve their
+    /// bodies, so they can refer to each other in any order. Declarations are
+    /// also added to the table under their own ids, so the interpreter knows
+    /// about them even if nothing refers to them (e.g. `run` tests).
+    pub fn resolve_module(&mut self, module: &QuintModule) -> Result<()> {
+        match self.check_module(module).into_iter().next() {
+            Some((_, err)) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Like [`Resolver::resolve_module`], but going on after errors. Returns
+    /// all of them, with the ids of the expressions where they are.
+    pub fn check_module(&mut self, module: &QuintModule) -> Vec<(QuintId, QuintError)> {
+        for declaration in &module.declarations {
+            self.add(declaration.clone());
+        }
+        let mut errors = Vec::new();
+        for declaration in &module.declarations {
+            if let QuintDeclaration::QuintOpDef(def) = declaration {
+                self.resolve_with(&def.expr, &mut Vec::new(), &mut errors);
+            }
+            self.table.insert(
+                declaration.id(),
+                LookupDefinition::Definition(declaration.clone()),
+            );
+        }
+        errors
+    }
+
+    /// The definition in scope with this name, if any
+    pub fn get(&self, name: &str) -> Option<&LookupDefinition> {
+        self.scope.get(name)
+    }
+
+    /// The ids and names of the state variables in scope
+    pub fn vars(&self) -> impl Iterator<Item = (QuintId, &QuintName)> {
+        self.scope.values().filter_map(|def| match def {
+            LookupDefinition::Definition(QuintDeclaration::QuintVar(QuintVar {
+                id, name, ..
+            })) => Some((*id, name)),
+            _ => None,
+        })
+    }
+
+    /// Add the definitions of the names used in `expr` to the lookup table.
+    pub fn resolve(&mut self, expr: &QuintEx) -> Result<()> {
+        let mut errors = Vec::new();
+        self.resolve_with(expr, &mut Vec::new(), &mut errors);
+        match errors.into_iter().next() {
+            Some((_, err)) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// `params` are the lambda parameters in scope, innermost last. Names that
+    /// can't be resolved are added to `errors`, with the ids of the
+    /// expressions referring to them.
+    fn resolve_with(
+        &mut self,
+        expr: &QuintEx,
+        params: &mut Vec<QuintLambdaParameter>,
+        errors: &mut Vec<(QuintId, QuintError)>,
+    ) {
+        match expr {
+            QuintEx::QuintName { id, name } => {
+                if let Some(param) = params.iter().rev().find(|p| &p.name == name) {
+                    self.table
+                        .insert(*id, LookupDefinition::Param(param.clone()));
+                } else if let Some(def) = self.scope.get(name) {
+                    self.table.insert(*id, def.clone());
+                } else if !BUILTIN_NAMES.contains(&name.as_str()) {
+                    errors.push((
+                        *id,
+                        QuintError::new("QNT404", &format!("Name '{name}' not found")),
+                    ));
+                }
+            }
+            QuintEx::QuintApp { id, opcode, args } => {
+                if let Some(param) = params.iter().rev().find(|p| &p.name == opcode) {
+                    self.table
+                        .insert(*id, LookupDefinition::Param(param.clone()));
+                } else if let Some(def) = self.scope.get(opcode) {
+                    self.table.insert(*id, def.clone());
+                } else if !is_builtin_op(opcode) {
+                    errors.push((
+                        *id,
+                        QuintError::new("QNT404", &format!("Operator '{opcode}' not found")),
+                    ));
+                }
+                if opcode == "assign"
+                    && let QuintEx::QuintName { id, name } = &args[0]
+                    && !matches!(
+                        self.scope.get(name),
+                        Some(LookupDefinition::Definition(QuintDeclaration::QuintVar(_)))
+                    )
+                {
+                    errors.push((
+                        *id,
+                        QuintError::new(
+                            "QNT404",
+                            &format!("Cannot assign to '{name}', it is not a state variable"),
+                        ),
+                    ));
+                    return;
+                }
+                for arg in args {
+                    self.resolve_with(arg, params, errors);
+                }
+            }
+            QuintEx::QuintLambda {
+                params: lambda_params,
+                expr,
+                ..
+            } => {
+                let depth = params.len();
+                params.extend(lambda_params.iter().cloned());
+                self.resolve_with(expr, params, errors);
+                params.truncate(depth);
+            }
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.resolve_with(&opdef.expr, params, errors);
+                self.resolve_with(expr, params, errors);
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use quint_evaluator::ir::QuintEx;
 
 ///
@@ -13,6 +15,14 @@ use crate::QuintId;
 #[derive(Debug, Default)]
 pub struct QuintIdGenerator {
     counter: QuintId,
+    /// Byte ranges in the source of operator definitions and of some
+    /// expressions (definition bodies, the items of `all`/`any`/`and`/`or`
+    /// blocks and operator calls), by id
+    pub spans: HashMap<QuintId, (usize, usize)>,
+    /// Byte ranges in the source of names: of definitions, variables and
+    /// parameters, by their id, and of the names and operators referring to
+    /// them, by the id of the expression
+    pub name_spans: HashMap<QuintId, (usize, usize)>,
 }
 
 impl QuintIdGenerator {
@@ -20,6 +30,23 @@ impl QuintIdGenerator {
     pub fn get(&mut self) -> QuintId {
         self.next().unwrap()
     }
+
+    /// Make sure the ids generated from now on are greater than `id`
+    pub fn skip_past(&mut self, id: QuintId) {
+        self.counter = self.counter.max(id);
+    }
+
+    /// Record where the definition or expression with this id is in the
+    /// source
+    pub fn record_span(&mut self, id: QuintId, start: usize, end: usize) {
+        self.spans.insert(id, (start, end));
+    }
+
+    /// Record where the name of the definition, parameter or expression with
+    /// this id is in the source
+    pub fn record_name_span(&mut self, id: QuintId, start: usize, end: usize) {
+        self.name_spans.insert(id, (start, end));
+    }
 }
 
 impl Iterator for QuintIdGenerator {
@@ -30,6 +57,16 @@ impl Iterator for QuintIdGenerator {
     }
 }
 
+/// Used in grammar to construct a QuintName, recording where it is
+pub fn name_expr(id: &mut QuintIdGenerator, (name, start, end): (String, usize, usize)) -> QuintEx {
+    let name_id = id.get();
+    id.record_name_span(name_id, start, end);
+    QuintEx::QuintName {
+        id: name_id,
+        name: name.into(),
+    }
+}
+
 /// Used in grammar to construct QuintApp
 pub fn make_quint_app(id: QuintId, opcode: &str, args: Vec<QuintEx>) -> QuintEx {
     QuintEx::QuintApp {
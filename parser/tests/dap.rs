@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+
+use quint_parser::dap::Server;
+use serde_json::{Value, json};
+
+const PROGRAM: &str = "fixtures/counter.qnt";
+
+/// Output that can be read after the server is done
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Send the requests to a server, all at once, and return what it sent back
+fn session(requests: &[(&str, Value)]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for (seq, (command, arguments)) in requests.iter().enumerate() {
+        let body = json!({
+            "seq": seq + 1,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+
+    let output = SharedOutput::default();
+    Server::new(Cursor::new(input), output.clone())
+        .run()
+        .unwrap();
+
+    let output = String::from_utf8(output.0.take()).unwrap();
+    let mut messages = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(header) = rest.strip_prefix("Content-Length: ") {
+        let (length, body) = header.split_once("\r\n\r\n").unwrap();
+        let length = length.parse::<usize>().unwrap();
+        messages.push(serde_json::from_str(&body[..length]).unwrap());
+        rest = &body[length..];
+    }
+    assert!(rest.is_empty(), "unexpected output: {rest}");
+    messages
+}
+
+fn response<'a>(messages: &'a [Value], command: &str) -> &'a Value {
+    messages
+        .iter()
+        .find(|m| m["type"] == "response" && m["command"] == command)
+        .unwrap_or_else(|| panic!("no response to {command}"))
+}
+
+fn events<'a>(messages: &'a [Value], event: &str) -> Vec<&'a Value> {
+    messages
+        .iter()
+        .filter(|m| m["type"] == "event" && m["event"] == event)
+        .collect()
+}
+
+#[test]
+fn stops_at_breakpoints_on_actionany_branches() {
+    let messages = session(&[
+        ("initialize", json!({"adapterID": "quint"})),
+        (
+            "launch",
+            json!({"program": PROGRAM, "seed": 1, "maxSteps": 3}),
+        ),
+        (
+            "setBreakpoints",
+            json!({"source": {"path": PROGRAM}, "breakpoints": [{"line": 14}, {"line": 3}]}),
+        ),
+        ("configurationDone", json!({})),
+        ("threads", json!({})),
+        ("stackTrace", json!({"threadId": 1})),
+        ("scopes", json!({"frameId": 0})),
+        ("variables", json!({"variablesReference": 1})),
+        ("continue", json!({"threadId": 1})),
+        ("continue", json!({"threadId": 1})),
+        ("continue", json!({"threadId": 1})),
+        ("disconnect", json!({})),
+    ]);
+
+    // Only lines where a definition or expression starts have breakpoints
+    let breakpoints = &response(&messages, "setBreakpoints")["body"]["breakpoints"];
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    // `increment` is tried in all steps, as `reset` is never enabled
+    let stopped = events(&messages, "stopped");
+    assert_eq!(stopped.len(), 3);
+    for event in &stopped {
+        assert_eq!(event["body"]["reason"], "breakpoint");
+        assert_eq!(
+            event["body"]["description"],
+            "Trying branch 1 of 2 of any: increment"
+        );
+    }
+
+    let frames = &response(&messages, "stackTrace")["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "increment");
+    assert_eq!(frames[0]["line"], 14);
+    assert_eq!(frames[1]["name"], "step");
+    assert_eq!(frames[1]["line"], 13);
+    assert_eq!(frames[1]["source"]["path"], PROGRAM);
+
+    let scopes = &response(&messages, "scopes")["body"]["scopes"];
+    assert_eq!(scopes[0]["name"], "State");
+    assert_eq!(scopes[1]["name"], "Next state");
+    let variables = &response(&messages, "variables")["body"]["variables"];
+    assert_eq!(
+        variables,
+        &json!([{"name": "x", "value": "0", "variablesReference": 0}])
+    );
+
+    let output = events(&messages, "output");
+    assert_eq!(
+        output[0]["body"]["output"],
+        "Simulated with seed 0x1\nState 0: { x: 0 }\nState 1: { x: 1 }\nState 2: { x: 2 }\nState 3: { x: 3 }\n"
+    );
+    assert_eq!(events(&messages, "terminated").len(), 1);
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+}
+
+#[test]
+fn steps_through_run_tests() {
+    let messages = session(&[
+        ("initialize", json!({})),
+        (
+            "launch",
+            json!({
+                "program": PROGRAM,
+                "mode": "test",
+                "test": "incrementTwice",
+                "seed": 1,
+                "stopOnEntry": true,
+            }),
+        ),
+        ("configurationDone", json!({})),
+        ("stepIn", json!({"threadId": 1})),
+        ("next", json!({"threadId": 1})),
+        ("continue", json!({"threadId": 1})),
+        ("disconnect", json!({})),
+    ]);
+
+    let reasons = events(&messages, "stopped")
+        .iter()
+        .map(|event| event["body"]["reason"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(reasons, ["entry", "step", "step"]);
+
+    let output = events(&messages, "output");
+    assert_eq!(
+        output[0]["body"]["output"],
+        "Test incrementTwice passed with seed 0x1\n"
+    );
+    assert_eq!(events(&messages, "exited")[0]["body"]["exitCode"], 0);
+}
+
+#[test]
+fn stops_when_disconnected_while_paused() {
+    let messages = session(&[
+        ("initialize", json!({})),
+        (
+            "launch",
+            json!({"program": PROGRAM, "seed": 1, "stopOnEntry": true}),
+        ),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+        // Not served, the session is over
+        ("threads", json!({})),
+    ]);
+
+    assert_eq!(events(&messages, "stopped").len(), 1);
+    assert!(
+        response(&messages, "disconnect")["success"]
+            .as_bool()
+            .unwrap()
+    );
+    assert!(events(&messages, "terminated").is_empty());
+    assert!(messages.iter().all(|m| m["command"] != "threads"));
+}
+
+#[test]
+fn fails_to_launch_unknown_tests() {
+    let messages = session(&[
+        ("initialize", json!({})),
+        (
+            "launch",
+            json!({"program": PROGRAM, "mode": "test", "test": "step"}),
+        ),
+    ]);
+
+    let launch = response(&messages, "launch");
+    assert_eq!(launch["success"], false);
+    assert_eq!(
+        launch["message"],
+        "Test step not found, it must be a `run` definition"
+    );
+    assert!(events(&messages, "initialized").is_empty());
+}
@@ -69,6 +69,7 @@ fn basic_integration() {
 
     check_expr!("if (1==1) true else false", true, bool);
     check_expr!("if (false) 0 else 10", 10i64, i64);
+    check_expr!("if 1 > 2 0 else 10", 10i64, i64);
 
     check_expr!("all { true, false }", false, bool);
     check_expr!("any { true, false }", true, bool);
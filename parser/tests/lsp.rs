@@ -0,0 +1,271 @@
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+
+use quint_parser::document::Document;
+use quint_parser::lsp::Server;
+use serde_json::{Value, json};
+
+const URI: &str = "file:///spec.qnt";
+
+const SPEC: &str = "module spec {
+  var x: int
+  pure val limit: int = 2 + 3
+  pure def double(n: int): int = n * 2
+  action init = x' = 0
+  action step = x' = double(x) + limit
+}
+";
+
+/// Output that can be read after the server is done
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Send the messages to a server, all at once, and return what it sent back.
+/// Messages with params that have no `id` key are sent as notifications.
+fn session(messages: &[(&str, Option<u64>, Value)]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for (method, id, params) in messages {
+        let mut message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        if let Some(id) = id {
+            message["id"] = json!(id);
+        }
+        let body = message.to_string();
+        write!(input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+
+    let output = SharedOutput::default();
+    Server::new(Cursor::new(input), output.clone())
+        .run()
+        .unwrap();
+
+    let output = String::from_utf8(output.0.take()).unwrap();
+    let mut messages = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(header) = rest.strip_prefix("Content-Length: ") {
+        let (length, body) = header.split_once("\r\n\r\n").unwrap();
+        let length = length.parse::<usize>().unwrap();
+        messages.push(serde_json::from_str(&body[..length]).unwrap());
+        rest = &body[length..];
+    }
+    assert!(rest.is_empty(), "unexpected output: {rest}");
+    messages
+}
+
+fn open(text: &str) -> (&'static str, Option<u64>, Value) {
+    (
+        "textDocument/didOpen",
+        None,
+        json!({"textDocument": {"uri": URI, "languageId": "quint", "version": 1, "text": text}}),
+    )
+}
+
+fn at(
+    method: &'static str,
+    id: u64,
+    line: u32,
+    character: u32,
+) -> (&'static str, Option<u64>, Value) {
+    (
+        method,
+        Some(id),
+        json!({
+            "textDocument": {"uri": URI},
+            "position": {"line": line, "character": character},
+            "context": {"includeDeclaration": true},
+        }),
+    )
+}
+
+fn result(messages: &[Value], id: u64) -> &Value {
+    &messages
+        .iter()
+        .find(|m| m["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {id}"))["result"]
+}
+
+fn diagnostics(messages: &[Value]) -> Vec<&Value> {
+    messages
+        .iter()
+        .filter(|m| m["method"] == "textDocument/publishDiagnostics")
+        .map(|m| &m["params"]["diagnostics"])
+        .collect()
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Value {
+    json!({
+        "start": {"line": start.0, "character": start.1},
+        "end": {"line": end.0, "character": end.1},
+    })
+}
+
+#[test]
+fn reports_parse_and_name_errors() {
+    let broken = "module spec {\n  val a = b + 1\n  val c = 1 +\n}\n";
+    let fixed = "module spec {\n  val b = 1\n  val a = b + 1\n}\n";
+    let messages = session(&[
+        ("initialize", Some(1), json!({})),
+        open(broken),
+        (
+            "textDocument/didChange",
+            None,
+            json!({"textDocument": {"uri": URI, "version": 2}, "contentChanges": [{"text": fixed}]}),
+        ),
+        ("shutdown", Some(2), Value::Null),
+        ("exit", None, Value::Null),
+    ]);
+
+    let capabilities = &result(&messages, 1)["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"]["change"], 2);
+    assert_eq!(capabilities["hoverProvider"], true);
+
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 2);
+    let errors = published[0].as_array().unwrap();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert_eq!(errors[0]["range"], range((1, 10), (1, 11)));
+    assert!(
+        errors[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Name 'b' not found")
+    );
+    assert_eq!(errors[1]["range"]["start"]["line"], 2);
+    assert_eq!(errors[1]["severity"], 1);
+    assert_eq!(published[1], &json!([]));
+}
+
+#[test]
+fn finds_definitions_and_references() {
+    let messages = session(&[
+        open(SPEC),
+        // `double` in `step`
+        at("textDocument/definition", 1, 5, 22),
+        // `x` in its declaration
+        at("textDocument/references", 2, 1, 6),
+    ]);
+
+    assert_eq!(
+        result(&messages, 1),
+        &json!({"uri": URI, "range": range((3, 11), (3, 17))})
+    );
+
+    let references = result(&messages, 2).as_array().unwrap();
+    let lines = references
+        .iter()
+        .map(|r| r["range"]["start"]["line"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [1, 4, 5, 5]);
+}
+
+#[test]
+fn hovers_with_types_and_values() {
+    let messages = session(&[
+        open(SPEC),
+        // `limit` in `step`
+        at("textDocument/hover", 1, 5, 33),
+        // `double` in `step`
+        at("textDocument/hover", 2, 5, 22),
+        // `n` in the body of `double`
+        at("textDocument/hover", 3, 3, 33),
+    ]);
+
+    let hover = |id| result(&messages, id)["contents"]["value"].as_str().unwrap();
+    assert_eq!(hover(1), "```quint\npure val limit: int\n```\n\nValue: `5`");
+    assert_eq!(hover(2), "```quint\npure def double: (int) => int\n```");
+    assert_eq!(hover(3), "```quint\n(parameter) n\n```");
+}
+
+#[test]
+fn lists_symbols_and_value_hints() {
+    let messages = session(&[
+        open(SPEC),
+        (
+            "textDocument/documentSymbol",
+            Some(1),
+            json!({"textDocument": {"uri": URI}}),
+        ),
+        (
+            "textDocument/inlayHint",
+            Some(2),
+            json!({"textDocument": {"uri": URI}, "range": range((0, 0), (7, 0))}),
+        ),
+    ]);
+
+    let symbols = result(&messages, 1).as_array().unwrap();
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0]["name"], "spec");
+    assert_eq!(symbols[0]["kind"], 2);
+    let children = symbols[0]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["name"].as_str().unwrap(), s["kind"].as_u64().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        children,
+        [
+            ("x", 13),
+            ("limit", 14),
+            ("double", 12),
+            ("init", 6),
+            ("step", 6)
+        ]
+    );
+
+    assert_eq!(
+        result(&messages, 2),
+        &json!([{
+            "position": {"line": 2, "character": 29},
+            "label": "= 5",
+            "paddingLeft": true,
+        }])
+    );
+}
+
+#[test]
+fn formats_documents() {
+    let messy = "module spec {\nval a = {\n1\n}\n\n\n   val b = a\n}\n";
+    let messages = session(&[
+        open(messy),
+        (
+            "textDocument/formatting",
+            Some(1),
+            json!({"textDocument": {"uri": URI}, "options": {"tabSize": 2, "insertSpaces": true}}),
+        ),
+        ("textDocument/unknown", Some(2), json!({})),
+    ]);
+
+    assert_eq!(
+        result(&messages, 1),
+        &json!([{
+            "range": range((0, 0), (8, 0)),
+            "newText": "module spec {\n  val a = {\n    1\n  }\n\n  val b = a\n}\n",
+        }])
+    );
+    let unknown = messages.iter().find(|m| m["id"] == 2).unwrap();
+    assert_eq!(unknown["error"]["code"], -32601);
+}
+
+#[test]
+fn reparses_only_changed_declarations() {
+    let text = std::fs::read_to_string("../evaluator/fixtures/jmt/tree.qnt").unwrap();
+    let mut document = Document::new(text.clone());
+    assert!(document.reparsed() > 10);
+
+    let edited = text.replacen("pure def ", "pure def  ", 1);
+    assert_ne!(edited, text);
+    document.set_text(edited);
+    assert_eq!(document.reparsed(), 1);
+    assert_eq!(document.module_name().unwrap().0, "tree");
+}
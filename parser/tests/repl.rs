@@ -0,0 +1,88 @@
+use quint_evaluator::value::Value;
+use quint_parser::repl::Repl;
+
+fn eval(repl: &mut Repl, input: &str) -> String {
+    repl.process(input)
+        .unwrap_or_else(|e| panic!("error processing {input}: {e}"))
+        .unwrap_or_default()
+}
+
+#[test]
+fn keeps_definitions_between_inputs() {
+    let mut repl = Repl::new(1);
+    eval(&mut repl, "val a = 2");
+    eval(&mut repl, "def triple(n) = n * 3");
+    eval(&mut repl, "pure val b = triple(a) + 1");
+
+    assert_eq!(eval(&mut repl, "b"), "7");
+    assert_eq!(eval(&mut repl, "triple(b)"), "21");
+
+    // Redefining a name only affects what comes after
+    eval(&mut repl, "val a = 10");
+    assert_eq!(eval(&mut repl, "a + b"), "17");
+
+    let err = repl.process("c + 1").unwrap_err();
+    assert_eq!(err.to_string(), "[QNT404] Name 'c' not found");
+    let err = repl.process("foo(1)").unwrap_err();
+    assert_eq!(err.to_string(), "[QNT404] Operator 'foo' not found");
+}
+
+#[test]
+fn builds_traces_with_actions() {
+    let mut repl = Repl::new(1);
+    eval(&mut repl, "var x: int");
+    eval(&mut repl, "action init = x' = 0");
+    eval(&mut repl, "action step = x' = x + 1");
+
+    assert_eq!(eval(&mut repl, "init"), "true");
+    assert_eq!(eval(&mut repl, "step.then(step)"), "true");
+    assert_eq!(eval(&mut repl, "x"), "2");
+    assert_eq!(
+        repl.state(),
+        Some(&Value::Record(
+            [("x".into(), Value::Int(2))].into_iter().collect()
+        ))
+    );
+
+    colored::control::set_override(false);
+    let trace = eval(&mut repl, ":trace");
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "State 0 (init)");
+    assert_eq!(lines[1], "  x = 0");
+    assert_eq!(lines[4], "State 2 (step)");
+    assert_eq!(lines[5], "  x = 2 (was 1)");
+
+    // Failed actions keep the state
+    assert_eq!(eval(&mut repl, "all { x > 5, step }"), "false");
+    assert_eq!(eval(&mut repl, "x"), "2");
+
+    eval(&mut repl, ":reset");
+    assert!(repl.state().is_none());
+    assert!(repl.process("x").is_err());
+    assert!(repl.process("y' = 1").is_err());
+}
+
+#[test]
+fn sets_the_seed() {
+    let mut repl = Repl::new(1);
+    eval(&mut repl, ":seed 0x2a");
+    assert_eq!(repl.seed(), 42);
+    assert_eq!(eval(&mut repl, ":seed"), "0x2a");
+}
+
+#[test]
+fn loads_modules() {
+    let mut repl = Repl::new(1);
+    let loaded = eval(&mut repl, ":load ../evaluator/fixtures/tictactoe.json");
+    assert!(loaded.starts_with("Loaded "), "{loaded}");
+    assert!(loaded.ends_with(" from module tictactoe"), "{loaded}");
+
+    assert_eq!(eval(&mut repl, "init"), "true");
+    assert_eq!(eval(&mut repl, "nextTurn"), "X");
+    assert_eq!(eval(&mut repl, "MoveX"), "true");
+    assert_eq!(eval(&mut repl, "nextTurn"), "O");
+
+    // Definitions typed at the prompt can use the loaded ones
+    eval(&mut repl, "val turns = boardCoordinates.size()");
+    assert_eq!(eval(&mut repl, "turns"), "9");
+}